@@ -1,7 +1,6 @@
 use pixel_ui::prelude::*;
 use winit::{
     dpi::PhysicalSize,
-    error::EventLoopError,
     event::{ElementState, MouseButton},
     window::WindowAttributes,
 };
@@ -9,7 +8,7 @@ use winit::{
 const WIDTH: usize = 640;
 const HEIGHT: usize = 480;
 
-fn main() -> Result<(), EventLoopError> {
+fn main() -> Result<(), BackendError> {
     let window_attributes = WindowAttributes::default()
         .with_inner_size(PhysicalSize::new(WIDTH as u32, HEIGHT as u32))
         .with_title("pixel-ui Button Test")
@@ -22,7 +21,7 @@ fn main() -> Result<(), EventLoopError> {
     let (hidden, set_hidden) = app.create_signal(false);
 
     let mut button = Element::button(60, 25, Color::BLACK)
-        .with_offset(300, 180);
+        .with_anchor(Anchor::Center, 0);
     
     let mut rect = Element::rect(20, 20, Color::WHITE)
         .with_offset(100, 190);
@@ -33,12 +32,6 @@ fn main() -> Result<(), EventLoopError> {
         }
     });
 
-    button.on_resize(|app, el, ev| {
-        let button = app.get_mut(el);
-
-        button.set_offset((ev.size.x / 2) as isize, (ev.size.y / 2) as isize);
-    });
-
     rect.on_rehydrate(move |app, el| {
         let hid = hidden.get(app);
         let element = app.get_mut(el);
@@ -1,14 +1,15 @@
-use std::{any::Any, cell::RefCell, num::{NonZeroU32, NonZeroUsize}, rc::Rc};
+use std::{collections::HashSet, rc::Rc};
 
 use chrono::TimeDelta;
 use slotmap::{new_key_type, SlotMap};
-use softbuffer::Surface;
 use winit::{
-    application::ApplicationHandler, error::EventLoopError, event::{ElementState, Modifiers, WindowEvent}, event_loop::{ControlFlow, EventLoop}, window::{Window, WindowAttributes}
+    event::{ElementState, Modifiers, MouseButton}, keyboard::PhysicalKey, window::WindowAttributes
 };
 
+#[cfg(all(feature = "palette", not(feature = "full-color")))]
+use crate::color::Palette;
 use crate::{
-    color::Color, element::Element, event::{Event, MouseEvent}, prelude::{MouseMoveEvent, ReadSignal, ResizeEvent, WriteSignal}, react::{Context, Ctx, IntervalId, ProxyEvent, SignalId, TimeoutId}, util::{IVec2, UVec2}
+    backend::{Backend, BackendError, BackendEvent, Frame}, color::Color, dither::{self, Dither}, draw::Framebuffer, layout, element::{Element, KeyHandler, MouseHandler, MouseMoveHandler, RehydrateHandler, ResizeHandler}, event::{Event, KeyEvent, MouseEvent}, prelude::{MouseMoveEvent, ReadSignal, ResizeEvent, WriteSignal}, react::{Context, Ctx, IntervalId, ProxyEvent, TimeoutId}, recorder::{Capture, Recorder, Recording}, screenshot::Screenshot, util::{BoundingBox, IVec2, UVec2}, viewport::{Rotation, Viewport}
 };
 
 new_key_type! { 
@@ -21,38 +22,99 @@ new_key_type! {
     pub(crate) struct RehydrateId;
 }
 
+// See [`HandlerId`](`crate::element::HandlerId`) for why the key types stay private.
+#[allow(private_interfaces)]
 pub enum AppHandlerId {
     Resize(ResizeId),
 }
 
+pub(crate) type AppResizeHandler = Rc<dyn Fn(&mut Application, ResizeEvent)>;
+
+/// Inserts an element as the last child of `parent`.
+fn attach_child(elements: &mut SlotMap<ElementId, Element>, parent: ElementId, element: Element) -> ElementId {
+    assert!(elements.contains_key(parent), "parent element should exist");
+
+    let mut element = element;
+    element.parent = Some(parent);
+
+    let id = elements.insert(element);
+    elements[parent].children.push(id);
+
+    id
+}
+
+/// Where an element ends up once its ancestors are taken into account.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Placement {
+    pub(crate) id: ElementId,
+    /// The absolute position of the element's top-left corner.
+    pub(crate) origin: IVec2,
+    /// The area the element's ancestors clip it to, or `None` for top-level elements.
+    pub(crate) clip: Option<BoundingBox>,
+}
+
+/// Past this many separate damaged regions, a frame is recomposited as a single region.
+const MAX_DAMAGE_REGIONS: usize = 16;
+
+/// Clips damaged regions to the screen and merges the ones that overlap,
+/// so that no pixel is recomposited twice.
+fn merge_damage(regions: Vec<BoundingBox>, screen: BoundingBox) -> Vec<BoundingBox> {
+    let mut merged: Vec<BoundingBox> = Vec::new();
+
+    for mut region in regions.into_iter().filter_map(|region| region.intersection(&screen)) {
+        // A merged region can grow into one that was merged earlier
+        while let Some(index) = merged.iter().position(|other| other.intersection(&region).is_some()) {
+            region = region.union(&merged.swap_remove(index));
+        }
+
+        merged.push(region);
+    }
+
+    if merged.len() > MAX_DAMAGE_REGIONS {
+        let first = merged[0];
+        merged = vec![merged.iter().fold(first, |all, region| all.union(region))];
+    }
+
+    merged
+}
+
 pub struct ApplicationBuilder {
-    event_loop: EventLoop<ProxyEvent>,
+    backend: Box<dyn Backend>,
     elements: SlotMap<ElementId, Element>,
     context: Context,
-    window_attributes: Option<WindowAttributes>,
     background: Color,
-
-    resize_handlers: SlotMap<ResizeId, Rc<dyn Fn(&mut Application, ResizeEvent)>>,
+    #[cfg(all(feature = "palette", not(feature = "full-color")))]
+    palette: Palette,
+    dither: Option<Dither>,
+    resolution: Option<UVec2>,
+    rotation: Rotation,
+    mirrored: bool,
+
+    resize_handlers: SlotMap<ResizeId, AppResizeHandler>,
 }
 
 impl ApplicationBuilder {
-    pub fn run(self) -> Result<(), EventLoopError> {
-        let mut app = Application {
-            surface: None,
-            window_attributes: self.window_attributes.unwrap_or_default(),
-            background: self.background,
-            mouse_position: IVec2::default(),
-            modifiers: Modifiers::default(),
-            focused: None,
-            elements: self.elements,
-            ctx: self.context,
-
-            resize_handlers: SlotMap::with_key(),
-        };
-
-        self.event_loop.run_app(&mut app)?;
-
-        Ok(())
+    /// Runs the application until its backend is closed.
+    pub fn run(mut self) -> Result<(), BackendError> {
+        let mut app = Application::with_context(self.context);
+        app.background = self.background;
+        app.dither = self.dither;
+        app.resolution = self.resolution;
+        app.rotation = self.rotation;
+        app.mirrored = self.mirrored;
+        #[cfg(all(feature = "palette", not(feature = "full-color")))]
+        {
+            app.palette = self.palette;
+        }
+        app.order = self.elements
+            .iter()
+            .filter(|(_, el)| el.parent.is_none())
+            .map(|(key, _)| key)
+            .collect();
+        app.elements = self.elements;
+        app.resize_handlers = self.resize_handlers;
+
+        app.run(self.backend.as_mut())
     }
 
     pub fn with_attributes(mut self, attrs: WindowAttributes) -> Self {
@@ -60,8 +122,9 @@ impl ApplicationBuilder {
         self
     }
 
+    /// Sets up the window, for backends that open one.
     pub fn set_attributes(&mut self, attrs: WindowAttributes) {
-        self.window_attributes = Some(attrs);
+        self.backend.set_window_attributes(attrs);
     }
 
     pub fn with_background(mut self, background: Color) -> Self {
@@ -73,6 +136,57 @@ impl ApplicationBuilder {
         self.background = background;
     }
 
+    #[cfg(all(feature = "palette", not(feature = "full-color")))]
+    pub fn with_palette(mut self, palette: Palette) -> Self {
+        self.set_palette(palette);
+        self
+    }
+
+    #[cfg(all(feature = "palette", not(feature = "full-color")))]
+    pub fn set_palette(&mut self, palette: Palette) {
+        self.palette = palette;
+    }
+
+    /// Dithers every frame down to black and white before it is presented.
+    pub fn with_dither(mut self, dither: Dither) -> Self {
+        self.set_dither(Some(dither));
+        self
+    }
+
+    pub fn set_dither(&mut self, dither: Option<Dither>) {
+        self.dither = dither;
+    }
+
+    /// Fixes the size of the framebuffer, which is scaled up by a whole number to fit the window.
+    pub fn with_resolution(mut self, width: usize, height: usize) -> Self {
+        self.set_resolution(Some(UVec2::new(width, height)));
+        self
+    }
+
+    pub fn set_resolution(&mut self, resolution: Option<UVec2>) {
+        self.resolution = resolution;
+    }
+
+    /// Turns the framebuffer clockwise before it is shown in the window.
+    pub fn with_rotation(mut self, rotation: Rotation) -> Self {
+        self.set_rotation(rotation);
+        self
+    }
+
+    pub fn set_rotation(&mut self, rotation: Rotation) {
+        self.rotation = rotation;
+    }
+
+    /// Flips the framebuffer left to right, after it is rotated.
+    pub fn with_mirrored(mut self, mirrored: bool) -> Self {
+        self.set_mirrored(mirrored);
+        self
+    }
+
+    pub fn set_mirrored(&mut self, mirrored: bool) {
+        self.mirrored = mirrored;
+    }
+
     pub fn query_class<'a>(&'a self, target: &'a str) -> impl Iterator<Item = &'a Element> {
         self.elements.values().filter(|el| el.contains_class(target))
     }
@@ -81,6 +195,16 @@ impl ApplicationBuilder {
         self.elements.insert(el.into())
     }
 
+    /// Inserts an element whose offset is relative to `parent`,
+    /// and which is clipped to the parent's bounds.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `parent` doesn't exist.
+    pub fn insert_child<E: Into<Element>>(&mut self, parent: ElementId, el: E) -> ElementId {
+        attach_child(&mut self.elements, parent, el.into())
+    }
+
     pub fn create_signal<T: 'static>(&mut self, init: T) -> (ReadSignal<T>, WriteSignal<T>) {
         self.context.create_signal(init)
     }
@@ -101,6 +225,13 @@ impl ApplicationBuilder {
         self.context.clear_interval(id);
     }
 
+    /// Runs timeouts and intervals on a clock that only moves forward with
+    /// [`advance_clock`](`Application::advance_clock`), instead of in real time.
+    pub fn with_manual_clock(mut self) -> Self {
+        self.context.use_manual_clock();
+        self
+    }
+
     pub fn on_resize(&mut self, f: impl Fn(&mut Application, ResizeEvent) + 'static) -> AppHandlerId {
         let id = self.resize_handlers.insert(Rc::new(f));
 
@@ -119,33 +250,98 @@ impl Ctx for ApplicationBuilder {
 }
 
 pub struct Application {
-    surface: Option<Surface<Rc<Window>, Rc<Window>>>,
-    window_attributes: WindowAttributes,
     background: Color,
+    #[cfg(all(feature = "palette", not(feature = "full-color")))]
+    palette: Palette,
+    dither: Option<Dither>,
+    /// The fixed size of the framebuffer, or `None` to match the window.
+    resolution: Option<UVec2>,
+    rotation: Rotation,
+    mirrored: bool,
+    /// The size of the framebuffer in logical pixels.
+    size: UVec2,
+    /// The size of the display in physical pixels.
+    window_size: UVec2,
+    /// Whether something outside of the elements changed since the last frame.
+    dirty: bool,
+    /// The size of the last frame presented to the backend.
+    presented: Option<UVec2>,
+    /// The last composed frame, which damaged regions are recomposited into.
+    frame: Framebuffer,
+    /// `frame` after the dither pass, if there is one.
+    dithered: Option<Framebuffer>,
+    /// Damaged regions that can't be found from the elements,
+    /// such as where a removed element used to be.
+    damage: Vec<BoundingBox>,
     mouse_position: IVec2,
     modifiers: Modifiers,
+    /// The element that key events go to before bubbling up to its ancestors.
     focused: Option<ElementId>,
     elements: SlotMap<ElementId, Element>,
+    /// Every top-level element in insertion order,
+    /// used to break ties between equal z-indices.
+    order: Vec<ElementId>,
     pub(crate) ctx: Context,
+    /// The recording in progress, if there is one.
+    recorder: Option<Recorder>,
     //----- Handlers -----//
-    resize_handlers: SlotMap<ResizeId, Rc<dyn Fn(&mut Application, ResizeEvent)>>,
+    resize_handlers: SlotMap<ResizeId, AppResizeHandler>,
 }
 
 impl Application {
-    pub fn builder() -> Result<ApplicationBuilder, EventLoopError> {
-        let event_loop = EventLoop::with_user_event().build()?;
-        event_loop.set_control_flow(ControlFlow::Wait);
+    /// Starts building an application that runs in a window.
+    ///
+    /// Only available where the window backend is, which excludes iOS and the web.
+    #[cfg(any(windows, target_os = "macos", target_os = "android", all(unix, not(any(target_vendor = "apple", target_os = "emscripten", target_os = "redox")))))]
+    pub fn builder() -> Result<ApplicationBuilder, winit::error::EventLoopError> {
+        Ok(Application::builder_with_backend(crate::backend::window::WindowBackend::new()?))
+    }
 
-        let proxy = event_loop.create_proxy();
+    /// Starts building an application that presents to and takes input from `backend`.
+    pub fn builder_with_backend(backend: impl Backend + 'static) -> ApplicationBuilder {
+        let context = Context::new(backend.proxy());
 
-        Ok(ApplicationBuilder {
-            event_loop,
+        ApplicationBuilder {
+            backend: Box::new(backend),
             elements: SlotMap::with_key(),
-            context: Context::new(proxy),
-            window_attributes: None,
+            context,
             background: Color::BLACK,
+            #[cfg(all(feature = "palette", not(feature = "full-color")))]
+            palette: Palette::default(),
+            dither: None,
+            resolution: None,
+            rotation: Rotation::default(),
+            mirrored: false,
             resize_handlers: SlotMap::with_key(),
-        })
+        }
+    }
+
+    /// Creates an application that isn't attached to a backend yet.
+    pub(crate) fn with_context(ctx: Context) -> Application {
+        Application {
+            background: Color::BLACK,
+            #[cfg(all(feature = "palette", not(feature = "full-color")))]
+            palette: Palette::default(),
+            dither: None,
+            resolution: None,
+            rotation: Rotation::default(),
+            mirrored: false,
+            size: UVec2::default(),
+            window_size: UVec2::default(),
+            dirty: true,
+            presented: None,
+            frame: Framebuffer::new(UVec2::default(), Color::BLACK),
+            dithered: None,
+            damage: Vec::new(),
+            mouse_position: IVec2::default(),
+            modifiers: Modifiers::default(),
+            focused: None,
+            elements: SlotMap::with_key(),
+            order: Vec::new(),
+            ctx,
+            recorder: None,
+            resize_handlers: SlotMap::with_key(),
+        }
     }
 
     pub fn create_signal<T: 'static>(&mut self, init: T) -> (ReadSignal<T>, WriteSignal<T>) {
@@ -168,12 +364,260 @@ impl Application {
         self.ctx.clear_interval(id);
     }
 
+    /// Makes timeouts and intervals set from now on run on a clock that only moves forward with
+    /// [`advance_clock`](`Application::advance_clock`), so that they fire at the same points on every run.
+    ///
+    /// Timeouts and intervals that were already set keep running in real time.
+    pub fn use_manual_clock(&mut self) {
+        self.ctx.use_manual_clock();
+    }
+
+    pub fn is_clock_manual(&self) -> bool {
+        self.ctx.manual_clock().is_some()
+    }
+
+    /// The time on the clock that timeouts and intervals run on,
+    /// which is how far a manual clock has been advanced, or how long the app has existed.
+    pub fn get_clock_time(&self) -> TimeDelta {
+        self.ctx.now()
+    }
+
+    /// Moves the manual clock forward, firing every timeout and interval that comes due
+    /// in the order they are due. Callbacks see the clock at the time they were due,
+    /// and anything they schedule inside of the advanced time fires too.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the app isn't using a [manual clock](`Application::use_manual_clock`).
+    pub fn advance_clock(&mut self, delta: TimeDelta) {
+        self.advance_clock_with(delta, |_| {});
+    }
+
+    /// Advances the manual clock, calling `between` before the first event and after every one.
+    pub(crate) fn advance_clock_with(&mut self, delta: TimeDelta, mut between: impl FnMut(&mut Application)) {
+        let until = self.ctx
+            .manual_clock()
+            .expect("app should use a manual clock")
+            .now() + delta;
+
+        between(self);
+
+        while let Some(event) = self.ctx.manual_clock_mut().and_then(|clock| clock.next_due(until)) {
+            self.handle_proxy_event(event);
+            between(self);
+        }
+
+        if let Some(clock) = self.ctx.manual_clock_mut() {
+            clock.set_now(until);
+        }
+    }
+
     pub fn insert_element<E: Into<Element>>(&mut self, element: E) -> ElementId {
-        self.elements.insert(element.into())
+        let id = self.elements.insert(element.into());
+        self.order.push(id);
+
+        id
     }
 
+    /// Inserts an element whose offset is relative to `parent`,
+    /// and which is clipped to the parent's bounds.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `parent` doesn't exist.
+    pub fn insert_child<E: Into<Element>>(&mut self, parent: ElementId, element: E) -> ElementId {
+        attach_child(&mut self.elements, parent, element.into())
+    }
+
+    /// Removes an element along with all of its descendants.
     pub fn remove_element(&mut self, id: ElementId) -> Option<Element> {
-        self.elements.remove(id)
+        let parent = self.elements.get(id)?.parent;
+
+        match parent.and_then(|parent| self.elements.get_mut(parent)) {
+            Some(parent) => parent.children.retain(|key| *key != id),
+            None => self.order.retain(|key| *key != id),
+        }
+
+        for descendant in self.descendants(id) {
+            if let Some(el) = self.elements.remove(descendant) {
+                self.damage.extend(el.replace_drawn(None));
+            }
+        }
+
+        if let Some(drawn) = self.elements[id].replace_drawn(None) {
+            self.damage.push(drawn);
+        }
+
+        if self.focused.is_some_and(|focused| !self.elements.contains_key(focused) || focused == id) {
+            self.focused = None;
+        }
+
+        self.elements.remove(id).map(|mut el| {
+            el.parent = None;
+            el.children.clear();
+            el
+        })
+    }
+
+    /// Every element without a parent, in insertion order.
+    pub fn root_elements(&self) -> &[ElementId] {
+        &self.order
+    }
+
+    /// Every descendant of an element, parents before their children.
+    pub fn descendants(&self, id: ElementId) -> Vec<ElementId> {
+        let mut out = Vec::new();
+        let mut stack: Vec<ElementId> = self.elements
+            .get(id)
+            .map(|el| el.children.iter().rev().copied().collect())
+            .unwrap_or_default();
+
+        while let Some(key) = stack.pop() {
+            out.push(key);
+            stack.extend(self.elements[key].children.iter().rev());
+        }
+
+        out
+    }
+
+    /// The parent of an element followed by each of its ancestors.
+    pub fn ancestors(&self, id: ElementId) -> Vec<ElementId> {
+        let mut out = Vec::new();
+        let mut current = self.elements.get(id).and_then(|el| el.parent);
+
+        while let Some(key) = current {
+            out.push(key);
+            current = self.elements[key].parent;
+        }
+
+        out
+    }
+
+    /// The bounds of an element in window coordinates,
+    /// after adding up the offsets of its ancestors.
+    pub fn get_absolute_bounding_box(&self, id: ElementId) -> BoundingBox {
+        let el = self.get(id);
+        let origin = self.ancestors(id)
+            .into_iter()
+            .fold(el.get_offset(), |origin, key| origin + self.elements[key].get_offset());
+
+        BoundingBox::from_size(origin, el.get_size())
+    }
+
+    fn siblings(&self, id: ElementId) -> Vec<ElementId> {
+        let siblings = match self.elements[id].parent {
+            Some(parent) => &self.elements[parent].children,
+            None => &self.order,
+        };
+
+        siblings.iter().copied().filter(|key| *key != id).collect()
+    }
+
+    /// Raises an element above every one of its siblings.
+    pub fn bring_to_front(&mut self, id: ElementId) {
+        if !self.elements.contains_key(id) {
+            return;
+        }
+
+        let top = self.siblings(id)
+            .into_iter()
+            .map(|key| self.elements[key].get_z_index())
+            .max();
+
+        if let Some(top) = top {
+            let el = self.get_mut(id);
+
+            if el.get_z_index() <= top {
+                el.set_z_index(top + 1);
+            }
+        }
+    }
+
+    /// Lowers an element below every one of its siblings.
+    ///
+    /// Since z-indices cannot go below zero,
+    /// every sibling is raised by one if one of them is already at the bottom.
+    pub fn send_to_back(&mut self, id: ElementId) {
+        if !self.elements.contains_key(id) {
+            return;
+        }
+
+        let siblings = self.siblings(id);
+        let blocked = siblings
+            .iter()
+            .any(|key| self.elements[*key].get_z_index() == 0);
+
+        if blocked {
+            for key in siblings {
+                let el = self.get_mut(key);
+                el.set_z_index(el.get_z_index() + 1);
+            }
+        }
+
+        self.get_mut(id).set_z_index(0);
+    }
+
+    /// Every visible element from bottom to top.
+    ///
+    /// Siblings are sorted by z-index, with equal z-indices ordered by when they were inserted,
+    /// and every element is followed by its own children.
+    pub fn draw_order(&self) -> Vec<ElementId> {
+        self.placements().into_iter().map(|placement| placement.id).collect()
+    }
+
+    fn sorted_by_z_index(&self, keys: &[ElementId]) -> Vec<ElementId> {
+        let mut keys = keys.to_vec();
+        keys.sort_by_key(|key| self.elements[*key].get_z_index());
+
+        keys
+    }
+
+    /// Resolves the position and clipping of every visible element, in draw order.
+    pub(crate) fn placements(&self) -> Vec<Placement> {
+        let mut out = Vec::new();
+
+        for key in self.sorted_by_z_index(&self.order) {
+            self.place(key, IVec2::default(), None, &mut out);
+        }
+
+        out
+    }
+
+    fn place(&self, id: ElementId, parent_origin: IVec2, clip: Option<BoundingBox>, out: &mut Vec<Placement>) {
+        let el = &self.elements[id];
+
+        // Hiding an element hides its whole subtree
+        if el.is_hidden() {
+            return;
+        }
+
+        let origin = parent_origin + el.get_offset();
+        out.push(Placement { id, origin, clip });
+
+        let bounds = BoundingBox::from_size(origin, el.get_size());
+        let child_clip = match clip {
+            Some(clip) => clip.intersection(&bounds),
+            None => Some(bounds),
+        };
+
+        // Children of an element that is clipped away entirely can't be seen either
+        if let Some(child_clip) = child_clip {
+            for key in self.sorted_by_z_index(&el.children) {
+                self.place(key, origin, Some(child_clip), out);
+            }
+        }
+    }
+
+    /// Finds the topmost visible element under a position in window coordinates.
+    pub fn element_at(&self, pos: IVec2) -> Option<ElementId> {
+        self.placements()
+            .into_iter()
+            .rev()
+            .find(|placement| {
+                let inside_clip = placement.clip.is_none_or(|clip| clip.contains(pos));
+                inside_clip && self.elements[placement.id].intersects_local(pos - placement.origin)
+            })
+            .map(|placement| placement.id)
     }
 
     pub fn get(&self, id: ElementId) -> &Element {
@@ -192,12 +636,124 @@ impl Application {
         self.elements.get_mut(id)
     }
 
+    /// The size of the framebuffer in logical pixels, as of the last resize.
+    ///
+    /// This is the size of the window unless a [resolution](`Application::set_resolution`) is set.
+    pub fn get_size(&self) -> UVec2 {
+        self.size
+    }
+
+    pub fn get_resolution(&self) -> Option<UVec2> {
+        self.resolution
+    }
+
+    /// Fixes the size of the framebuffer, which is scaled up by a whole number to fit the window
+    /// and centered in it. `None` makes the framebuffer match the window again.
+    pub fn set_resolution(&mut self, resolution: Option<UVec2>) {
+        self.resolution = resolution;
+        self.handle_resize(self.logical_size());
+        self.invalidate();
+    }
+
+    pub fn get_rotation(&self) -> Rotation {
+        self.rotation
+    }
+
+    /// Turns the framebuffer clockwise before it is shown in the window.
+    /// Elements keep laying out and receiving mouse events in the unrotated framebuffer.
+    /// Without a fixed resolution, turning it sideways swaps its width and height so it still fills the window.
+    pub fn set_rotation(&mut self, rotation: Rotation) {
+        self.rotation = rotation;
+        self.handle_resize(self.logical_size());
+        self.invalidate();
+    }
+
+    pub fn is_mirrored(&self) -> bool {
+        self.mirrored
+    }
+
+    /// Flips the framebuffer left to right, after it is rotated.
+    pub fn set_mirrored(&mut self, mirrored: bool) {
+        self.mirrored = mirrored;
+        self.invalidate();
+    }
+
+    /// How many physical pixels each logical pixel is scaled up to.
+    pub fn get_scale(&self) -> usize {
+        self.viewport().get_scale()
+    }
+
+    /// The size of the framebuffer, which is the fixed resolution if there is one,
+    /// or else the window turned back by the rotation.
+    fn logical_size(&self) -> UVec2 {
+        match self.resolution {
+            Some(resolution) => resolution,
+            None if self.rotation.is_sideways() => UVec2::new(self.window_size.y, self.window_size.x),
+            None => self.window_size,
+        }
+    }
+
+    pub(crate) fn viewport(&self) -> Viewport {
+        Viewport::new(self.size, self.window_size, self.rotation, self.mirrored)
+    }
+
+    /// Recomputes the offsets of the children of every layout container.
+    ///
+    /// This runs automatically whenever the window is resized, and before a frame
+    /// whenever an element was added, removed or changed, so changes to the size
+    /// of a child are picked up without calling it.
+    pub fn update_layout(&mut self) {
+        layout::update(self);
+    }
+
+    /// Lays out the elements again if anything changed since the last frame.
+    fn refresh_layout(&mut self) {
+        if self.needs_redraw() {
+            self.update_layout();
+        }
+    }
+
     pub fn get_background(&self) -> Color {
         self.background
     }
 
     pub fn set_background(&mut self, background: Color) {
         self.background = background;
+        self.invalidate();
+    }
+
+    #[cfg(all(feature = "palette", not(feature = "full-color")))]
+    pub fn get_palette(&self) -> &Palette {
+        &self.palette
+    }
+
+    /// Swaps the active palette.
+    /// Elements keep their indices, so the new palette applies on the next present
+    /// without redrawing any of them.
+    #[cfg(all(feature = "palette", not(feature = "full-color")))]
+    pub fn set_palette(&mut self, palette: Palette) {
+        self.palette = palette;
+        self.invalidate();
+    }
+
+    pub fn get_dither(&self) -> Option<Dither> {
+        self.dither
+    }
+
+    /// Sets a final pass that dithers every frame down to black and white,
+    /// based on the brightness of the presented colors.
+    pub fn set_dither(&mut self, dither: Option<Dither>) {
+        self.dither = dither;
+        self.invalidate();
+    }
+
+    /// Converts a composed pixel into the `0x00RRGGBB` value that gets presented.
+    pub fn present_color(&self, color: Color) -> u32 {
+        #[cfg(all(feature = "palette", not(feature = "full-color")))]
+        return self.palette.get(color);
+
+        #[cfg(not(all(feature = "palette", not(feature = "full-color"))))]
+        color.into()
     }
 
     pub fn on_resize(&mut self, f: impl Fn(&mut Application, ResizeEvent) + 'static) -> AppHandlerId {
@@ -205,169 +761,442 @@ impl Application {
 
         AppHandlerId::Resize(id)
     }
-}
 
-impl Ctx for Application {
-    fn get_ctx(&self) -> &Context {
-        &self.ctx
-    }
+    /// Brings the composed frame up to date with the elements,
+    /// returning the regions of it that changed.
+    ///
+    /// Only the regions that elements moved, hid or changed in are recomposited,
+    /// unless the size or something outside of the elements changed.
+    pub(crate) fn render(&mut self, size: UVec2) -> Vec<BoundingBox> {
+        let screen = BoundingBox::from_size(IVec2::default(), size);
+        let placements = self.placements();
+        let mut damage = std::mem::take(&mut self.damage);
+        let mut placed = HashSet::new();
+
+        for placement in &placements {
+            let el = &self.elements[placement.id];
+            let bounds = BoundingBox::from_size(placement.origin, el.get_size());
+            let bounds = match placement.clip {
+                Some(clip) => clip.intersection(&bounds),
+                None => Some(bounds),
+            };
+            let bounds = bounds.and_then(|bounds| bounds.intersection(&screen));
+
+            let previous = el.replace_drawn(bounds);
+            if el.is_changed() || previous != bounds {
+                damage.extend(previous);
+                damage.extend(bounds);
+            }
 
-    fn get_ctx_mut(&mut self) -> &mut Context {
-        &mut self.ctx
+            placed.insert(placement.id);
+        }
+
+        // Elements that were hidden since the last frame
+        for (key, el) in &self.elements {
+            if !placed.contains(&key) {
+                damage.extend(el.replace_drawn(None));
+            }
+        }
+
+        if self.dirty || self.frame.get_size() != size {
+            self.frame = Framebuffer::new(size, self.background);
+            damage = vec![screen];
+        }
+
+        let mut damage = merge_damage(damage, screen);
+        let mut frame = std::mem::replace(&mut self.frame, Framebuffer::new(UVec2::default(), self.background));
+
+        for region in &damage {
+            self.recomposite(&mut frame, &placements, *region);
+        }
+
+        self.frame = frame;
+
+        match self.dither {
+            Some(dither) if !damage.is_empty() || self.dithered.as_ref().is_none_or(|dithered| dithered.get_size() != size) => {
+                let levels: Vec<u8> = self.frame
+                    .iter()
+                    .map(|color| dither::luminance(self.present_color(color)))
+                    .collect();
+
+                self.dithered = Some(Framebuffer::from_colors(size, dither.dither(&levels, size)));
+
+                // Error diffusion can carry a change anywhere below it
+                if matches!(dither, Dither::FloydSteinberg | Dither::Atkinson) {
+                    damage = vec![screen];
+                }
+            }
+            Some(_) => {}
+            None => self.dithered = None,
+        }
+
+        self.mark_clean();
+
+        damage
     }
-}
 
-impl ApplicationHandler<ProxyEvent> for Application {
-    fn resumed(&mut self, event_loop: &winit::event_loop::ActiveEventLoop) {
-        let window = Rc::new(event_loop.create_window(self.window_attributes.clone()).expect("should be able to create window"));
+    /// Fills a region with the background and draws every element over it again.
+    fn recomposite(&self, frame: &mut Framebuffer, placements: &[Placement], region: BoundingBox) {
+        frame.fill_rect(region, self.background);
 
-        let context = softbuffer::Context::new(window.clone()).expect("should be able to create draw context");
-        let surface = Surface::new(&context, window).expect("should be able to create draw surface");
+        for placement in placements {
+            let clip = match placement.clip {
+                Some(clip) => clip.intersection(&region),
+                None => Some(region),
+            };
 
-        self.surface = Some(surface);
+            if let Some(clip) = clip {
+                self.elements[placement.id].draw(frame, placement.origin, Some(clip));
+            }
+        }
     }
 
-    fn suspended(&mut self, event_loop: &winit::event_loop::ActiveEventLoop) {
-        self.surface = None;
+    /// The last frame produced by [`render`](`Application::render`), after dithering.
+    pub(crate) fn frame(&self) -> &Framebuffer {
+        match (self.dither, &self.dithered) {
+            (Some(_), Some(dithered)) => dithered,
+            _ => &self.frame,
+        }
     }
 
-    fn window_event(
-            &mut self,
-            event_loop: &winit::event_loop::ActiveEventLoop,
-            window_id: winit::window::WindowId,
-            event: WindowEvent,
-        ) {
-        match event {
-            WindowEvent::CursorMoved { device_id: _, position } => {
-                let mouse_x = position.x as isize;
-                let mouse_y = position.y as isize;
+    /// Captures the framebuffer as it would be presented now, at its logical size
+    /// and before it is scaled or rotated for the window.
+    pub fn screenshot(&mut self) -> Screenshot {
+        self.refresh_layout();
+
+        // The regions are still waiting to be presented, so they are handed back for the next frame
+        let dirty = self.dirty;
+        let damage = self.render(self.size);
+        self.damage.extend(damage);
+        self.dirty = dirty;
 
-                let new_pos = IVec2::new(mouse_x, mouse_y);
-                let delta = self.mouse_position - new_pos;
+        self.capture()
+    }
 
-                self.mouse_position = new_pos;
+    /// Copies the last rendered frame.
+    fn capture(&self) -> Screenshot {
+        let pixels = self.frame().to_colors();
+        let rgb = pixels.iter().map(|color| self.present_color(*color)).collect();
 
-                let ev = MouseMoveEvent {
-                    pos: self.mouse_position,
-                    modifiers: self.modifiers,
-                    delta,
-                };
+        Screenshot::new(self.size, pixels, rgb)
+    }
 
-                let keys: Vec<ElementId> = self.elements.keys().collect();
-                for key in keys {
-                    if let Some(el) = self.elements.get_mut(key) {
-                        
-                        if el.intersects(self.mouse_position) {
-                            el.update(Event::MouseMove(ev));
+    /// Starts recording the frames the application shows, replacing any recording in progress.
+    /// The frame as it is now is captured straight away.
+    pub fn start_recording(&mut self, capture: Capture) {
+        self.stop_recording();
 
-                            let handlers: Vec<Rc<dyn Fn(&mut Application, ElementId, MouseMoveEvent)>> = el.handlers.mouse_move_handlers.values().cloned().collect();
-                            for handler in handlers {
-                                handler(self, key, ev);
-                            }
-                        }
-                    }
+        let screenshot = self.screenshot();
+        let now = self.ctx.now();
+        let mut recorder = Recorder::new(capture, now);
+        recorder.push(now, screenshot);
+
+        if let Capture::Every(delay) = capture {
+            recorder.interval = Some(self.set_interval(delay, |app| {
+                let screenshot = app.screenshot();
+                let now = app.ctx.now();
+
+                if let Some(recorder) = &mut app.recorder {
+                    recorder.push(now, screenshot);
                 }
+            }));
+        }
+
+        self.recorder = Some(recorder);
+    }
 
-                self.surface.as_ref().expect("draw surface should exist").window().request_redraw();
+    pub fn is_recording(&self) -> bool {
+        self.recorder.is_some()
+    }
+
+    /// Stops recording, returning every frame captured since [`start_recording`](`Application::start_recording`).
+    pub fn stop_recording(&mut self) -> Option<Recording> {
+        let recorder = self.recorder.take()?;
+
+        if let Some(interval) = recorder.interval {
+            self.clear_interval(interval);
+        }
+
+        Some(recorder.finish(self.ctx.now()))
+    }
+
+    /// Brings the display up to date, returning the regions of the framebuffer that changed.
+    ///
+    /// Nothing is presented if nothing changed since the last present.
+    pub(crate) fn present(&mut self, backend: &mut dyn Backend) -> Result<Vec<BoundingBox>, BackendError> {
+        self.refresh_layout();
+
+        let window_size = self.window_size;
+
+        // Nothing changed since the last present, so the display already shows this frame
+        if window_size.area() == 0 || !self.needs_redraw() && self.presented == Some(window_size) {
+            return Ok(Vec::new());
+        }
+
+        let full = self.dirty || self.presented != Some(window_size);
+        let damage = self.render(self.size);
+        let viewport = self.viewport();
+
+        let regions = match full {
+            true => vec![viewport.window_bounds()],
+            false => damage.iter().filter_map(|region| viewport.window_region(*region)).collect(),
+        };
+
+        backend.present(&Frame::new(self, viewport, regions))?;
+        self.presented = Some(window_size);
+
+        if self.recorder.as_ref().is_some_and(|recorder| recorder.capture == Capture::EveryPresent) {
+            let screenshot = self.capture();
+            let now = self.ctx.now();
+
+            if let Some(recorder) = &mut self.recorder {
+                recorder.push(now, screenshot);
             }
-            WindowEvent::MouseInput {
-                device_id: _,
-                state, button,
-            } => {
-                let ev = MouseEvent {
-                    pos: self.mouse_position,
-                    modifiers: self.modifiers,
-                    state,
-                    button,
-                };
+        }
 
-                let keys: Vec<ElementId> = self.elements.keys().collect();
-                for key in keys {
-                    if let Some(el) = self.elements.get_mut(key) {
-                        
-                        if el.intersects(self.mouse_position) {
-                            el.update(Event::Mouse(ev));
+        Ok(damage)
+    }
 
-                            let handlers: Vec<Rc<dyn Fn(&mut Application, ElementId, MouseEvent)>> = el.handlers.mouse_handlers.values().cloned().collect();
-                            for handler in handlers {
-                                handler(self, key, ev);
-                            }
-                        }
-                    }
-                }
+    /// Handles events and presents frames until the backend is closed.
+    pub(crate) fn run(&mut self, backend: &mut dyn Backend) -> Result<(), BackendError> {
+        loop {
+            let event = match self.ctx.has_real_time_timers() {
+                true => backend.wait_event()?,
+                false => backend.wait_event_idle()?,
+            };
 
-                self.surface.as_ref().expect("draw surface should exist").window().request_redraw();
+            let Some(event) = event else {
+                break;
+            };
+
+            self.handle_backend_event(event);
+
+            // Handle everything that is already waiting before presenting
+            while let Some(event) = backend.poll_event()? {
+                self.handle_backend_event(event);
             }
-            WindowEvent::Resized(size) => {
-                println!("resized: {size:?}");
 
-                let size_x = size.width as usize;
-                let size_y = size.height as usize;
+            self.present(backend)?;
+        }
 
-                let ev = ResizeEvent {
-                    size: UVec2::new(size_x, size_y),
-                };
+        Ok(())
+    }
 
-                let handlers: Vec<Rc<dyn Fn(&mut Application, ResizeEvent)>> = self.resize_handlers.values().cloned().collect();
-                for handler in handlers {
-                    handler(self, ev);
-                }
+    pub(crate) fn handle_backend_event(&mut self, event: BackendEvent) {
+        match event {
+            BackendEvent::Resized(size) => self.resize(size.x, size.y),
+            BackendEvent::CursorMoved(pos) => {
+                let pos = self.viewport().logical_position(pos);
+                self.handle_mouse_move(pos);
+            }
+            BackendEvent::MouseInput { state, button } => self.handle_mouse_input(state, button),
+            BackendEvent::Key { key, state, repeat } => self.handle_key(key, state, repeat),
+            BackendEvent::Modifiers(modifiers) => self.modifiers = modifiers,
+            BackendEvent::Redraw => self.presented = None,
+            BackendEvent::Proxy(event) => self.handle_proxy_event(event),
+        }
+    }
 
-                let keys: Vec<ElementId> = self.elements.keys().collect();
-                for key in keys {
-                    if let Some(el) = self.elements.get_mut(key) {
-                        el.update(Event::Resize(ev));
-                        
-                        let handlers: Vec<Rc<dyn Fn(&mut Application, ElementId, ResizeEvent)>> = el.handlers.resize_handlers.values().cloned().collect();
-                        for handler in handlers {
-                            handler(self, key, ev);
-                        }
-                    }
+    /// The element that key events go to.
+    pub fn get_focus(&self) -> Option<ElementId> {
+        self.focused
+    }
+
+    /// Sends key events to an element, which bubble up to its ancestors.
+    /// Pressing a mouse button also focuses the element under the mouse.
+    pub fn set_focus(&mut self, id: Option<ElementId>) {
+        self.focused = id.filter(|id| self.elements.contains_key(*id));
+    }
+
+    /// Runs an event through the same handlers and element updates as input from the window.
+    ///
+    /// Positions are in logical pixels, and the mouse is moved to the position of a [`MouseEvent`]
+    /// before it is handled. The delta of a [`MouseMoveEvent`] is worked out again from the last position.
+    ///
+    /// The size of a [`ResizeEvent`] is logical too. Without a fixed resolution, the window is taken
+    /// to have been resized to fit it, the same as [`resize`](`Application::resize`) with that size turned
+    /// by the rotation. With a resolution, only the framebuffer is resized.
+    pub fn dispatch(&mut self, event: Event) {
+        match event {
+            Event::Resize(ev) => {
+                if self.resolution.is_none() {
+                    self.window_size = match self.rotation.is_sideways() {
+                        true => UVec2::new(ev.size.y, ev.size.x),
+                        false => ev.size,
+                    };
                 }
 
-                self.surface.as_ref().expect("window should exist").window().request_redraw();
+                self.handle_resize(ev.size);
+            }
+            Event::Mouse(ev) => {
+                self.modifiers = ev.modifiers;
+                self.mouse_position = ev.pos;
+                self.handle_mouse_input(ev.state, ev.button);
             }
-            WindowEvent::CloseRequested => {
-                event_loop.exit();
+            Event::MouseMove(ev) => {
+                self.modifiers = ev.modifiers;
+                self.handle_mouse_move(ev.pos);
             }
-            WindowEvent::RedrawRequested => {
-                let surface = self.surface.as_ref().expect("draw surface should exist");
-                let window = surface.window();
+            Event::Key(ev) => {
+                self.modifiers = ev.modifiers;
+                self.handle_key(ev.key, ev.state, ev.repeat);
+            }
+        }
+    }
+
+    /// Moves the mouse to a position in logical pixels.
+    pub fn move_mouse_to(&mut self, pos: IVec2) {
+        self.handle_mouse_move(pos);
+    }
+
+    /// Moves the mouse to a position in logical pixels, then presses and releases a button there.
+    pub fn click_at(&mut self, pos: IVec2, button: MouseButton) {
+        self.move_mouse_to(pos);
+        self.handle_mouse_input(ElementState::Pressed, button);
+        self.handle_mouse_input(ElementState::Released, button);
+    }
+
+    /// Presses and releases a key, with the current modifiers held.
+    pub fn press_key(&mut self, key: PhysicalKey) {
+        self.handle_key(key, ElementState::Pressed, false);
+        self.handle_key(key, ElementState::Released, false);
+    }
+
+    /// Handles the window being resized to a size in physical pixels.
+    pub fn resize(&mut self, width: usize, height: usize) {
+        self.window_size = UVec2::new(width, height);
+        self.handle_resize(self.logical_size());
+    }
+
+    /// The topmost element under a position followed by its ancestors,
+    /// which is the order mouse events bubble through the tree.
+    fn hit_path(&self, pos: IVec2) -> Vec<ElementId> {
+        match self.element_at(pos) {
+            Some(key) => std::iter::once(key).chain(self.ancestors(key)).collect(),
+            None => Vec::new(),
+        }
+    }
 
-                let dimensions = window.inner_size();
-                let size = UVec2::new(dimensions.width as usize, dimensions.height as usize);
+    /// Marks the whole frame as needing to be composed again.
+    fn invalidate(&mut self) {
+        self.dirty = true;
+    }
+
+    /// Whether anything changed since the last frame was composed.
+    pub fn needs_redraw(&self) -> bool {
+        self.dirty || !self.damage.is_empty() || self.elements.values().any(Element::is_changed)
+    }
+
+    /// Records that the current state has been composed.
+    pub(crate) fn mark_clean(&mut self) {
+        self.dirty = false;
+
+        for el in self.elements.values() {
+            el.clear_changed();
+        }
+    }
+
+    pub(crate) fn handle_mouse_move(&mut self, new_pos: IVec2) {
+        let delta = self.mouse_position - new_pos;
+
+        self.mouse_position = new_pos;
 
-                let mut buffer = vec![self.background; size.area()];
+        let ev = MouseMoveEvent {
+            pos: self.mouse_position,
+            modifiers: self.modifiers,
+            delta,
+        };
+
+        for key in self.hit_path(self.mouse_position) {
+            if let Some(el) = self.elements.get_mut(key) {
+                el.update(Event::MouseMove(ev));
 
-                for element in self.elements.values() {
-                    element.draw(&mut buffer, size.x, size.y);
+                let handlers: Vec<MouseMoveHandler> = el.handlers.mouse_move_handlers.values().cloned().collect();
+                for handler in handlers {
+                    handler(self, key, ev);
                 }
+            }
+        }
+    }
 
-                window.pre_present_notify();
+    pub(crate) fn handle_mouse_input(&mut self, state: ElementState, button: MouseButton) {
+        if state == ElementState::Pressed {
+            self.focused = self.element_at(self.mouse_position);
+        }
+
+        let ev = MouseEvent {
+            pos: self.mouse_position,
+            modifiers: self.modifiers,
+            state,
+            button,
+        };
 
-                let surface = self.surface.as_mut().expect("draw surface should exist");
+        for key in self.hit_path(self.mouse_position) {
+            if let Some(el) = self.elements.get_mut(key) {
+                el.update(Event::Mouse(ev));
 
-                if dimensions.width == 0 || dimensions.height == 0 {
-                    return;
+                let handlers: Vec<MouseHandler> = el.handlers.mouse_handlers.values().cloned().collect();
+                for handler in handlers {
+                    handler(self, key, ev);
                 }
+            }
+        }
+    }
+
+    pub(crate) fn handle_key(&mut self, key: PhysicalKey, state: ElementState, repeat: bool) {
+        let ev = KeyEvent {
+            key,
+            modifiers: self.modifiers,
+            state,
+            repeat,
+        };
+
+        let path = match self.focused {
+            Some(key) => std::iter::once(key).chain(self.ancestors(key)).collect(),
+            None => Vec::new(),
+        };
+
+        for key in path {
+            if let Some(el) = self.elements.get_mut(key) {
+                el.update(Event::Key(ev));
+
+                let handlers: Vec<KeyHandler> = el.handlers.key_handlers.values().cloned().collect();
+                for handler in handlers {
+                    handler(self, key, ev);
+                }
+            }
+        }
+    }
+
+    pub(crate) fn handle_resize(&mut self, size: UVec2) {
+        let ev = ResizeEvent { size };
+
+        if self.size != size {
+            self.size = size;
+            self.dirty = true;
+        }
 
-                surface.resize(
-                    NonZeroU32::new(dimensions.width).expect("window width should be greater than 0"),
-                    NonZeroU32::new(dimensions.height).expect("window height should be greater than 0")
-                ).expect("should be able to resize draw buffer");
+        self.update_layout();
 
-                let mut window_buffer = surface.buffer_mut().expect("should be able to retrieve draw buffer");
-                window_buffer.iter_mut().zip(buffer.into_iter()).for_each(|(current, write)| {
-                    *current = write.into();
-                });
+        let handlers: Vec<AppResizeHandler> = self.resize_handlers.values().cloned().collect();
+        for handler in handlers {
+            handler(self, ev);
+        }
 
-                window_buffer.present().expect("should be able to present buffer");
+        let keys: Vec<ElementId> = self.elements.keys().collect();
+        for key in keys {
+            if let Some(el) = self.elements.get_mut(key) {
+                el.update(Event::Resize(ev));
+                
+                let handlers: Vec<ResizeHandler> = el.handlers.resize_handlers.values().cloned().collect();
+                for handler in handlers {
+                    handler(self, key, ev);
+                }
             }
-            _ => {}
         }
     }
 
-    fn user_event(&mut self, event_loop: &winit::event_loop::ActiveEventLoop, event: ProxyEvent) {
+    pub(crate) fn handle_proxy_event(&mut self, event: ProxyEvent) {
         match event {
             ProxyEvent::React => {
                 self.ctx.clean();
@@ -375,22 +1204,18 @@ impl ApplicationHandler<ProxyEvent> for Application {
                 let keys: Vec<ElementId> = self.elements.keys().collect();
                 for key in keys {
                     if let Some(el) = self.elements.get(key) {
-                            let handlers: Vec<Rc<dyn Fn(&mut Application, ElementId)>> = el.handlers.rehydrate_handlers.values().cloned().collect();
+                            let handlers: Vec<RehydrateHandler> = el.handlers.rehydrate_handlers.values().cloned().collect();
                             for handler in handlers {
                                 handler(self, key);
                         }
                     }
                 }
-
-                self.surface.as_ref().expect("window should exist").window().request_redraw();
             }
             ProxyEvent::Interval(id) => {
                 if let Some(interval) = self.ctx.intervals.get(id) {
                     let callback = interval.f.clone();
                     callback(self);
                 }
-
-                self.surface.as_ref().expect("window should exist").window().request_redraw();
             }
             ProxyEvent::Timeout(id) => {
                 // Remove in timeout instead of get in order to uphold
@@ -400,9 +1225,108 @@ impl ApplicationHandler<ProxyEvent> for Application {
                     let callback = timeout.f;
                     callback(self);
                 }
-
-                self.surface.as_ref().expect("window should exist").window().request_redraw();
             }
         }
     }
 }
+
+impl Ctx for Application {
+    fn get_ctx(&self) -> &Context {
+        &self.ctx
+    }
+
+    fn get_ctx_mut(&mut self) -> &mut Context {
+        &mut self.ctx
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::headless::Headless;
+
+    fn screen() -> BoundingBox {
+        BoundingBox::new(0, 0, 100, 100)
+    }
+
+    #[test]
+    fn merge_damage_joins_overlapping_regions() {
+        let merged = merge_damage(
+            vec![BoundingBox::new(0, 0, 4, 4), BoundingBox::new(2, 2, 6, 6), BoundingBox::new(10, 10, 12, 12)],
+            screen(),
+        );
+
+        assert_eq!(merged, [BoundingBox::new(0, 0, 6, 6), BoundingBox::new(10, 10, 12, 12)]);
+    }
+
+    #[test]
+    fn merge_damage_joins_regions_a_merged_region_grows_into() {
+        let merged = merge_damage(
+            vec![BoundingBox::new(0, 0, 2, 2), BoundingBox::new(5, 0, 7, 2), BoundingBox::new(1, 0, 6, 2)],
+            screen(),
+        );
+
+        assert_eq!(merged, [BoundingBox::new(0, 0, 7, 2)]);
+    }
+
+    #[test]
+    fn merge_damage_keeps_regions_that_only_touch_apart() {
+        let merged = merge_damage(vec![BoundingBox::new(0, 0, 2, 2), BoundingBox::new(2, 0, 4, 2)], screen());
+
+        assert_eq!(merged.len(), 2);
+    }
+
+    #[test]
+    fn merge_damage_clips_regions_to_the_screen() {
+        let merged = merge_damage(
+            vec![BoundingBox::new(-5, 90, 10, 110), BoundingBox::new(200, 200, 210, 210)],
+            screen(),
+        );
+
+        assert_eq!(merged, [BoundingBox::new(0, 90, 10, 100)]);
+    }
+
+    #[test]
+    fn merge_damage_joins_everything_past_the_cap() {
+        let regions = |count: isize| (0..count).map(|i| BoundingBox::new(i * 2, 0, i * 2 + 1, 1)).collect::<Vec<_>>();
+
+        assert_eq!(merge_damage(regions(MAX_DAMAGE_REGIONS as isize), screen()).len(), MAX_DAMAGE_REGIONS);
+        assert_eq!(
+            merge_damage(regions(MAX_DAMAGE_REGIONS as isize + 1), screen()),
+            [BoundingBox::new(0, 0, MAX_DAMAGE_REGIONS as isize * 2 + 1, 1)],
+        );
+    }
+
+    #[test]
+    fn sideways_rotation_swaps_the_size_of_the_framebuffer() {
+        let mut app = Headless::new(10, 20);
+
+        app.set_rotation(Rotation::Deg90);
+        assert_eq!(app.get_size(), UVec2::new(20, 10));
+
+        app.resize(30, 40);
+        assert_eq!(app.get_size(), UVec2::new(40, 30));
+
+        app.set_rotation(Rotation::Deg180);
+        assert_eq!(app.get_size(), UVec2::new(30, 40));
+
+        app.set_resolution(Some(UVec2::new(5, 6)));
+        app.set_rotation(Rotation::Deg270);
+        assert_eq!(app.get_size(), UVec2::new(5, 6));
+    }
+
+    #[test]
+    fn dispatched_resize_resizes_the_window_without_a_resolution() {
+        let mut app = Headless::new(10, 20);
+        app.set_rotation(Rotation::Deg90);
+
+        app.dispatch(Event::Resize(ResizeEvent { size: UVec2::new(30, 40) }));
+        assert_eq!(app.get_size(), UVec2::new(30, 40));
+        assert_eq!(app.window_size, UVec2::new(40, 30));
+
+        app.set_resolution(Some(UVec2::new(5, 6)));
+        app.dispatch(Event::Resize(ResizeEvent { size: UVec2::new(7, 8) }));
+        assert_eq!(app.get_size(), UVec2::new(7, 8));
+        assert_eq!(app.window_size, UVec2::new(40, 30));
+    }
+}
@@ -0,0 +1,211 @@
+pub mod memory;
+#[cfg(unix)]
+pub mod terminal;
+pub mod vnc;
+// Only platforms where winit can pump its event loop, which excludes iOS and the web
+#[cfg(any(windows, target_os = "macos", target_os = "android", all(unix, not(any(target_vendor = "apple", target_os = "emscripten", target_os = "redox")))))]
+pub mod window;
+
+use std::{error::Error, fmt, io};
+
+use winit::{error::EventLoopError, event::{ElementState, Modifiers, MouseButton}, keyboard::{KeyCode, PhysicalKey}, window::WindowAttributes};
+
+use crate::{
+    app::Application,
+    react::{EventSender, ProxyEvent},
+    util::{BoundingBox, IVec2, UVec2},
+    viewport::Viewport,
+};
+
+pub(crate) const LETTERS: [KeyCode; 26] = [
+    KeyCode::KeyA, KeyCode::KeyB, KeyCode::KeyC, KeyCode::KeyD, KeyCode::KeyE, KeyCode::KeyF, KeyCode::KeyG,
+    KeyCode::KeyH, KeyCode::KeyI, KeyCode::KeyJ, KeyCode::KeyK, KeyCode::KeyL, KeyCode::KeyM, KeyCode::KeyN,
+    KeyCode::KeyO, KeyCode::KeyP, KeyCode::KeyQ, KeyCode::KeyR, KeyCode::KeyS, KeyCode::KeyT, KeyCode::KeyU,
+    KeyCode::KeyV, KeyCode::KeyW, KeyCode::KeyX, KeyCode::KeyY, KeyCode::KeyZ,
+];
+
+pub(crate) const DIGITS: [KeyCode; 10] = [
+    KeyCode::Digit0, KeyCode::Digit1, KeyCode::Digit2, KeyCode::Digit3, KeyCode::Digit4,
+    KeyCode::Digit5, KeyCode::Digit6, KeyCode::Digit7, KeyCode::Digit8, KeyCode::Digit9,
+];
+
+/// Somewhere an [`Application`] can present its frames and get its input from,
+/// such as a window or an in-memory framebuffer.
+///
+/// Every position a backend deals in is in physical pixels.
+/// The application takes care of scaling, rotating and letterboxing the framebuffer.
+pub trait Backend {
+    /// Where timers and signal updates send their [`ProxyEvent`]s.
+    /// They should come back out of [`wait_event`](`Backend::wait_event`) as [`BackendEvent::Proxy`].
+    fn proxy(&self) -> EventSender;
+
+    /// Sets up the window, for backends that open one. Other backends ignore it.
+    fn set_window_attributes(&mut self, _attributes: WindowAttributes) {}
+
+    /// Blocks until there is an event, returning `None` once the display is closed.
+    fn wait_event(&mut self) -> Result<Option<BackendEvent>, BackendError>;
+
+    /// Called instead of [`wait_event`](`Backend::wait_event`) while the application has no timeouts
+    /// or intervals running in real time, so nothing will come through the proxy unless input causes it.
+    /// Backends that take no input of their own can return `None` here to stop the application.
+    fn wait_event_idle(&mut self) -> Result<Option<BackendEvent>, BackendError> {
+        self.wait_event()
+    }
+
+    /// Takes an event that is already waiting, without blocking.
+    fn poll_event(&mut self) -> Result<Option<BackendEvent>, BackendError>;
+
+    /// Shows a frame. Only the [damaged](`Frame::damage`) regions changed since the last present.
+    fn present(&mut self, frame: &Frame) -> Result<(), BackendError>;
+}
+
+/// Input and notifications from a [`Backend`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BackendEvent {
+    /// The display changed size.
+    Resized(UVec2),
+    /// The mouse moved to a position on the display.
+    CursorMoved(IVec2),
+    MouseInput {
+        state: ElementState,
+        button: MouseButton,
+    },
+    Key {
+        key: PhysicalKey,
+        state: ElementState,
+        repeat: bool,
+    },
+    Modifiers(Modifiers),
+    /// The display lost what it was showing, so the next frame is presented in full.
+    Redraw,
+    Proxy(ProxyEvent),
+}
+
+#[derive(Debug)]
+pub enum BackendError {
+    EventLoop(EventLoopError),
+    Io(io::Error),
+}
+
+impl fmt::Display for BackendError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BackendError::EventLoop(err) => write!(f, "event loop error: {err}"),
+            BackendError::Io(err) => write!(f, "io error: {err}"),
+        }
+    }
+}
+
+impl Error for BackendError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            BackendError::EventLoop(err) => Some(err),
+            BackendError::Io(err) => Some(err),
+        }
+    }
+}
+
+impl From<EventLoopError> for BackendError {
+    fn from(err: EventLoopError) -> Self {
+        BackendError::EventLoop(err)
+    }
+}
+
+impl From<io::Error> for BackendError {
+    fn from(err: io::Error) -> Self {
+        BackendError::Io(err)
+    }
+}
+
+/// A composed frame as it appears on the display,
+/// scaled up and surrounded by the letterbox.
+pub struct Frame<'a> {
+    app: &'a Application,
+    viewport: Viewport,
+    damage: Vec<BoundingBox>,
+}
+
+impl<'a> Frame<'a> {
+    pub(crate) fn new(app: &'a Application, viewport: Viewport, damage: Vec<BoundingBox>) -> Self {
+        Self { app, viewport, damage }
+    }
+
+    /// The size of the display in physical pixels.
+    pub fn get_size(&self) -> UVec2 {
+        let bounds = self.viewport.window_bounds();
+
+        UVec2::new(bounds.width(), bounds.height())
+    }
+
+    /// How many physical pixels each logical pixel is scaled up to.
+    pub fn get_scale(&self) -> usize {
+        self.viewport.get_scale()
+    }
+
+    /// The regions of the display that changed since the last present,
+    /// or the whole display if what it shows can't be relied on.
+    pub fn damage(&self) -> &[BoundingBox] {
+        &self.damage
+    }
+
+    /// The `0x00RRGGBB` value shown at a position on the display.
+    pub fn pixel(&self, x: usize, y: usize) -> u32 {
+        let color = match self.viewport.source_index(IVec2::new(x as isize, y as isize)) {
+            Some(index) => self.app.frame().get_index(index),
+            None => self.app.get_background(),
+        };
+
+        self.app.present_color(color)
+    }
+
+    /// Copies regions of the display into a row-major buffer the size of the display.
+    pub fn write_regions(&self, buffer: &mut [u32], regions: &[BoundingBox]) {
+        let width = self.get_size().x;
+
+        for region in regions {
+            let min = region.get_min();
+            let max = region.get_max();
+
+            for y in min.y as usize..max.y as usize {
+                for x in min.x as usize..max.x as usize {
+                    buffer[y * width + x] = self.pixel(x, y);
+                }
+            }
+        }
+    }
+}
+
+/// The key that types a printable character, and whether shift is held for it,
+/// going by a US keyboard layout.
+pub(crate) fn key_for_ascii(byte: u8) -> Option<(KeyCode, bool)> {
+    let key = match byte {
+        b'a'..=b'z' => (LETTERS[(byte - b'a') as usize], false),
+        b'A'..=b'Z' => (LETTERS[(byte - b'A') as usize], true),
+        b'0'..=b'9' => (DIGITS[(byte - b'0') as usize], false),
+        b' ' => (KeyCode::Space, false),
+        b'!' => (KeyCode::Digit1, true),
+        b'@' => (KeyCode::Digit2, true),
+        b'#' => (KeyCode::Digit3, true),
+        b'$' => (KeyCode::Digit4, true),
+        b'%' => (KeyCode::Digit5, true),
+        b'^' => (KeyCode::Digit6, true),
+        b'&' => (KeyCode::Digit7, true),
+        b'*' => (KeyCode::Digit8, true),
+        b'(' => (KeyCode::Digit9, true),
+        b')' => (KeyCode::Digit0, true),
+        b'-' | b'_' => (KeyCode::Minus, byte == b'_'),
+        b'=' | b'+' => (KeyCode::Equal, byte == b'+'),
+        b'[' | b'{' => (KeyCode::BracketLeft, byte == b'{'),
+        b']' | b'}' => (KeyCode::BracketRight, byte == b'}'),
+        b'\\' | b'|' => (KeyCode::Backslash, byte == b'|'),
+        b';' | b':' => (KeyCode::Semicolon, byte == b':'),
+        b'\'' | b'"' => (KeyCode::Quote, byte == b'"'),
+        b',' | b'<' => (KeyCode::Comma, byte == b'<'),
+        b'.' | b'>' => (KeyCode::Period, byte == b'>'),
+        b'/' | b'?' => (KeyCode::Slash, byte == b'?'),
+        b'`' | b'~' => (KeyCode::Backquote, byte == b'~'),
+        _ => return None,
+    };
+
+    Some(key)
+}
@@ -0,0 +1,157 @@
+use std::{cell::{Ref, RefCell}, collections::VecDeque, rc::Rc, sync::mpsc::{self, Receiver, Sender}};
+
+use crate::{
+    react::{EventSender, ProxyEvent},
+    util::{BoundingBox, IVec2, UVec2},
+};
+
+use super::{Backend, BackendError, BackendEvent, Frame};
+
+/// A backend that presents into a buffer in memory and takes its input from a queue,
+/// so that an application can run without a display.
+///
+/// Once the queue is empty and the application has no timeouts or intervals left to wait for,
+/// the backend closes, so [`ApplicationBuilder::run`](`crate::app::ApplicationBuilder::run`) returns.
+/// What was presented can still be read through its [`screen`](`MemoryBackend::screen`).
+pub struct MemoryBackend {
+    screen: MemoryScreen,
+    events: VecDeque<BackendEvent>,
+    sender: Sender<ProxyEvent>,
+    proxy_events: Receiver<ProxyEvent>,
+}
+
+/// The pixels a [`MemoryBackend`] has presented, shared with the backend
+/// so that they can be read after it is handed to an application.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryScreen {
+    presented: Rc<RefCell<Presented>>,
+}
+
+#[derive(Debug, Default)]
+struct Presented {
+    size: UVec2,
+    /// The `0x00RRGGBB` value of every pixel presented so far.
+    pixels: Vec<u32>,
+    /// How many frames have been presented.
+    count: usize,
+}
+
+impl MemoryScreen {
+    /// The size of the last frame presented.
+    pub fn get_size(&self) -> UVec2 {
+        self.presented.borrow().size
+    }
+
+    /// The `0x00RRGGBB` values presented so far, in row-major order.
+    pub fn pixels(&self) -> Ref<'_, [u32]> {
+        Ref::map(self.presented.borrow(), |presented| presented.pixels.as_slice())
+    }
+
+    /// How many frames have been presented, which only goes up when something changed.
+    pub fn get_present_count(&self) -> usize {
+        self.presented.borrow().count
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> Option<u32> {
+        let presented = self.presented.borrow();
+
+        if x >= presented.size.x || y >= presented.size.y {
+            return None;
+        }
+
+        Some(presented.pixels[y * presented.size.x + x])
+    }
+}
+
+impl MemoryBackend {
+    pub fn new() -> MemoryBackend {
+        let (sender, proxy_events) = mpsc::channel();
+
+        MemoryBackend {
+            screen: MemoryScreen::default(),
+            events: VecDeque::new(),
+            sender,
+            proxy_events,
+        }
+    }
+
+    /// A handle to the pixels presented to this backend, which stays valid after the backend is moved.
+    pub fn screen(&self) -> MemoryScreen {
+        self.screen.clone()
+    }
+
+    /// The size of the last frame presented.
+    pub fn get_size(&self) -> UVec2 {
+        self.screen.get_size()
+    }
+
+    /// The `0x00RRGGBB` values presented so far, in row-major order.
+    pub fn pixels(&self) -> Ref<'_, [u32]> {
+        self.screen.pixels()
+    }
+
+    /// How many frames have been presented, which only goes up when something changed.
+    pub fn get_present_count(&self) -> usize {
+        self.screen.get_present_count()
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> Option<u32> {
+        self.screen.pixel(x, y)
+    }
+
+    /// Queues input, which comes out of the backend ahead of any timer or signal update.
+    pub fn push_event(&mut self, event: BackendEvent) {
+        self.events.push_back(event);
+    }
+}
+
+impl Default for MemoryBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Backend for MemoryBackend {
+    fn proxy(&self) -> EventSender {
+        EventSender::from(self.sender.clone())
+    }
+
+    fn wait_event(&mut self) -> Result<Option<BackendEvent>, BackendError> {
+        if let Some(event) = self.events.pop_front() {
+            return Ok(Some(event));
+        }
+
+        // The backend holds a sender itself, so this only returns once an event arrives
+        Ok(self.proxy_events.recv().ok().map(BackendEvent::Proxy))
+    }
+
+    fn wait_event_idle(&mut self) -> Result<Option<BackendEvent>, BackendError> {
+        // Nothing else can add to the queue, so an empty one means the application is done
+        self.poll_event()
+    }
+
+    fn poll_event(&mut self) -> Result<Option<BackendEvent>, BackendError> {
+        if let Some(event) = self.events.pop_front() {
+            return Ok(Some(event));
+        }
+
+        Ok(self.proxy_events.try_recv().ok().map(BackendEvent::Proxy))
+    }
+
+    fn present(&mut self, frame: &Frame) -> Result<(), BackendError> {
+        let size = frame.get_size();
+        let mut presented = self.screen.presented.borrow_mut();
+        presented.count += 1;
+
+        // Anything left over from a different size can't line up with the new frame
+        if presented.size != size {
+            presented.size = size;
+            presented.pixels = vec![0; size.area()];
+            frame.write_regions(&mut presented.pixels, &[BoundingBox::from_size(IVec2::default(), size)]);
+        } else {
+            frame.write_regions(&mut presented.pixels, frame.damage());
+        }
+
+        Ok(())
+    }
+}
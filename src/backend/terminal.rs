@@ -0,0 +1,776 @@
+use std::{
+    collections::VecDeque,
+    fmt::Write as _,
+    io::{self, Read, Stdout, Write},
+    mem,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, RecvTimeoutError, Sender},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use winit::{
+    event::{ElementState, Modifiers, MouseButton},
+    keyboard::{KeyCode, ModifiersState, PhysicalKey},
+};
+
+use crate::{
+    dither,
+    react::{EventSender, ProxyEvent},
+    util::{IVec2, UVec2},
+};
+
+use super::{key_for_ascii, Backend, BackendError, BackendEvent, Frame, LETTERS};
+
+/// How often the size of the terminal is checked while waiting for input.
+const RESIZE_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The most bytes of an unfinished escape sequence that are kept waiting for the rest of it,
+/// since no key or mouse report takes up more.
+const MAX_SEQUENCE_LEN: usize = 32;
+
+/// How the pixels of the framebuffer are packed into terminal cells.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum CellMode {
+    /// An upper half block over a lower one, with each of the two pixels in its own color.
+    #[default]
+    HalfBlock,
+    /// A braille pattern of 2x4 dots, with the brighter pixels as dots
+    /// and only one foreground and one background color for the whole cell.
+    Braille,
+}
+
+impl CellMode {
+    /// How many pixels fit in one terminal cell.
+    pub fn cell_size(&self) -> UVec2 {
+        match self {
+            CellMode::HalfBlock => UVec2::new(1, 2),
+            CellMode::Braille => UVec2::new(2, 4),
+        }
+    }
+
+    /// Works out what the cell with its top-left pixel at `(x, y)` shows.
+    fn cell(&self, frame: &Frame, x: usize, y: usize) -> Cell {
+        match self {
+            CellMode::HalfBlock => {
+                let top = frame.pixel(x, y);
+                let bottom = frame.pixel(x, y + 1);
+
+                match top == bottom {
+                    true => Cell { glyph: ' ', fg: top, bg: top },
+                    false => Cell { glyph: '▀', fg: top, bg: bottom },
+                }
+            }
+            CellMode::Braille => {
+                // The bit of the braille pattern for each pixel, in row-major order
+                const DOTS: [u32; 8] = [0x01, 0x08, 0x02, 0x10, 0x04, 0x20, 0x40, 0x80];
+
+                let pixels: Vec<u32> = (0..8).map(|index| frame.pixel(x + index % 2, y + index / 2)).collect();
+                let darkest = *pixels.iter().min_by_key(|rgb| dither::luminance(**rgb)).expect("cell should have pixels");
+                let brightest = *pixels.iter().max_by_key(|rgb| dither::luminance(**rgb)).expect("cell should have pixels");
+
+                let low = dither::luminance(darkest) as u16;
+                let high = dither::luminance(brightest) as u16;
+
+                if low == high {
+                    return Cell { glyph: ' ', fg: darkest, bg: darkest };
+                }
+
+                let threshold = (low + high).div_ceil(2);
+                let pattern = pixels
+                    .iter()
+                    .zip(DOTS)
+                    .filter(|(rgb, _)| dither::luminance(**rgb) as u16 >= threshold)
+                    .fold(0, |pattern, (_, dot)| pattern | dot);
+
+                Cell {
+                    glyph: char::from_u32(0x2800 + pattern).expect("braille pattern should be a valid char"),
+                    fg: brightest,
+                    bg: darkest,
+                }
+            }
+        }
+    }
+}
+
+/// What a terminal cell shows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Cell {
+    glyph: char,
+    fg: u32,
+    bg: u32,
+}
+
+/// Everything that wakes the backend up.
+enum Input {
+    Bytes(Vec<u8>),
+    Proxy(ProxyEvent),
+    Closed,
+}
+
+/// A backend that draws to an ANSI terminal with 24-bit colors and reads its keys and mouse,
+/// so that an application can run over SSH without any graphical display.
+///
+/// The terminal is put in raw mode and switched to its alternate screen until the backend is dropped.
+/// Since raw mode stops the terminal from turning Ctrl+C into a signal, Ctrl+C closes the backend instead.
+/// Terminals only report key presses, so every key is pressed and released straight away.
+pub struct TerminalBackend {
+    mode: CellMode,
+    /// The terminal settings from before raw mode, which are restored when the backend is dropped.
+    original: libc::termios,
+    out: Stdout,
+    sender: Sender<Input>,
+    input: Receiver<Input>,
+    /// The thread reading stdin, which is stopped and joined when the backend is dropped.
+    reader: Option<JoinHandle<()>>,
+    stop_reader: Arc<AtomicBool>,
+    events: VecDeque<BackendEvent>,
+    parser: InputParser,
+    /// The size of the terminal in cells.
+    cells: UVec2,
+    /// What every cell shows, or `None` if it hasn't been drawn since the screen was cleared.
+    screen: Vec<Option<Cell>>,
+    /// How many cells `screen` is laid out for.
+    screen_size: UVec2,
+    closed: bool,
+}
+
+impl TerminalBackend {
+    pub fn new(mode: CellMode) -> io::Result<TerminalBackend> {
+        let cells = terminal_size()?;
+        let original = enable_raw_mode()?;
+        let (sender, input) = mpsc::channel();
+
+        let stop_reader = Arc::new(AtomicBool::new(false));
+        let reader = {
+            let sender = sender.clone();
+            let stop = stop_reader.clone();
+            thread::spawn(move || read_stdin(sender, &stop))
+        };
+
+        let mut backend = TerminalBackend {
+            mode,
+            original,
+            out: io::stdout(),
+            sender,
+            input,
+            reader: Some(reader),
+            stop_reader,
+            events: VecDeque::new(),
+            parser: InputParser::new(mode.cell_size()),
+            cells,
+            screen: Vec::new(),
+            screen_size: UVec2::default(),
+            closed: false,
+        };
+
+        // Alternate screen, hidden cursor, and SGR reports for every mouse button and movement
+        backend.out.write_all(b"\x1b[?1049h\x1b[?25l\x1b[?1003h\x1b[?1006h")?;
+        backend.out.flush()?;
+        backend.events.push_back(BackendEvent::Resized(backend.pixel_size()));
+
+        Ok(backend)
+    }
+
+    pub fn get_mode(&self) -> CellMode {
+        self.mode
+    }
+
+    /// The size of the terminal in pixels.
+    fn pixel_size(&self) -> UVec2 {
+        let cell = self.mode.cell_size();
+
+        UVec2::new(self.cells.x * cell.x, self.cells.y * cell.y)
+    }
+
+    /// Queues a resize if the terminal changed size since it was last checked.
+    fn check_size(&mut self) {
+        if let Ok(cells) = terminal_size() {
+            if cells != self.cells {
+                self.cells = cells;
+                self.events.push_back(BackendEvent::Resized(self.pixel_size()));
+            }
+        }
+    }
+
+    fn handle_input(&mut self, input: Input) {
+        match input {
+            Input::Bytes(bytes) => {
+                self.parser.parse(&bytes);
+                self.take_parsed();
+            }
+            Input::Proxy(event) => self.events.push_back(BackendEvent::Proxy(event)),
+            Input::Closed => self.closed = true,
+        }
+    }
+
+    /// Moves whatever the parser made of the input into the queue.
+    fn take_parsed(&mut self) {
+        self.events.extend(self.parser.events.drain(..));
+
+        if self.parser.interrupted {
+            self.closed = true;
+        }
+    }
+}
+
+/// Turns the bytes read from the terminal into events.
+///
+/// A read can end partway through an escape sequence,
+/// so the start of one is kept until the rest of it arrives.
+struct InputParser {
+    /// How many pixels fit in one terminal cell.
+    cell_size: UVec2,
+    modifiers: ModifiersState,
+    /// The start of a sequence that the last read ended partway through.
+    pending: Vec<u8>,
+    events: VecDeque<BackendEvent>,
+    /// Whether Ctrl+C was pressed.
+    interrupted: bool,
+}
+
+impl InputParser {
+    fn new(cell_size: UVec2) -> InputParser {
+        InputParser {
+            cell_size,
+            modifiers: ModifiersState::empty(),
+            pending: Vec::new(),
+            events: VecDeque::new(),
+            interrupted: false,
+        }
+    }
+
+    fn parse(&mut self, bytes: &[u8]) {
+        let mut input = mem::take(&mut self.pending);
+        input.extend_from_slice(bytes);
+
+        let mut index = 0;
+
+        while index < input.len() {
+            let rest = &input[index..];
+
+            let len = match rest {
+                [0x1b, b'[', b'<', ..] => self.parse_mouse(rest),
+                [0x1b, b'[' | b'O', _, ..] => self.parse_escape(rest),
+                // Either the start of a sequence or Alt with `[` or `O`, which only `flush` can tell apart
+                [0x1b, b'[' | b'O'] => None,
+                // Either the start of a sequence or the escape key on its own
+                [0x1b] => None,
+                // Alt sends an escape ahead of the key it is held with
+                [0x1b, byte, ..] if key_for_ascii(*byte).is_some() => {
+                    self.alt_key(*byte);
+                    Some(2)
+                }
+                [0x1b, ..] => {
+                    self.key(KeyCode::Escape, ModifiersState::empty());
+                    Some(1)
+                }
+                [byte, ..] => {
+                    self.parse_byte(*byte);
+                    Some(1)
+                }
+                [] => break,
+            };
+
+            let Some(len) = len else {
+                // Anything longer than a sequence can be is noise, which is dropped to get back in step
+                if rest.len() <= MAX_SEQUENCE_LEN {
+                    self.pending = rest.to_vec();
+                }
+
+                break;
+            };
+
+            index += len;
+        }
+    }
+
+    /// Gives up on waiting for the rest of a sequence, once no more input has come in for a while.
+    fn flush(&mut self) {
+        // Anything longer is a sequence that lost its end, which there is nothing to make of
+        match mem::take(&mut self.pending)[..] {
+            [0x1b] => self.key(KeyCode::Escape, ModifiersState::empty()),
+            [0x1b, byte] => self.alt_key(byte),
+            _ => {}
+        }
+    }
+
+    fn parse_byte(&mut self, byte: u8) {
+        match byte {
+            0x03 => self.interrupted = true,
+            b'\r' | b'\n' => self.key(KeyCode::Enter, ModifiersState::empty()),
+            b'\t' => self.key(KeyCode::Tab, ModifiersState::empty()),
+            0x7f | 0x08 => self.key(KeyCode::Backspace, ModifiersState::empty()),
+            // Ctrl with a letter sends the letter's position in the alphabet
+            0x01..=0x1a => self.key(LETTERS[(byte - 1) as usize], ModifiersState::CONTROL),
+            _ => {
+                if let Some((code, shift)) = key_for_ascii(byte) {
+                    self.key(code, shift_if(shift));
+                }
+            }
+        }
+    }
+
+    /// Parses an `ESC [` or `ESC O` sequence for a special key, returning how many bytes it took up,
+    /// or `None` if it hasn't ended yet.
+    fn parse_escape(&mut self, bytes: &[u8]) -> Option<usize> {
+        let end = bytes[2..].iter().position(|byte| !(0x20..=0x3f).contains(byte))? + 2;
+
+        // Only printable bytes can be part of a sequence, so drop what came before anything else
+        if !(0x40..=0x7e).contains(&bytes[end]) {
+            return Some(end);
+        }
+
+        let params: Vec<usize> = std::str::from_utf8(&bytes[2..end])
+            .unwrap_or_default()
+            .split(';')
+            .map(|param| param.parse().unwrap_or(0))
+            .collect();
+
+        // The second parameter is one more than a bit set of shift, alt and ctrl
+        let modifiers = params.get(1).map_or(ModifiersState::empty(), |param| {
+            let bits = param.saturating_sub(1);
+            let mut modifiers = ModifiersState::empty();
+            modifiers.set(ModifiersState::SHIFT, bits & 1 != 0);
+            modifiers.set(ModifiersState::ALT, bits & 2 != 0);
+            modifiers.set(ModifiersState::CONTROL, bits & 4 != 0);
+            modifiers
+        });
+
+        let code = match (bytes[end], params[0]) {
+            (b'A', _) => Some(KeyCode::ArrowUp),
+            (b'B', _) => Some(KeyCode::ArrowDown),
+            (b'C', _) => Some(KeyCode::ArrowRight),
+            (b'D', _) => Some(KeyCode::ArrowLeft),
+            (b'H', _) | (b'~', 1 | 7) => Some(KeyCode::Home),
+            (b'F', _) | (b'~', 4 | 8) => Some(KeyCode::End),
+            // Shift+Tab has a sequence of its own
+            (b'Z', _) => Some(KeyCode::Tab),
+            (b'P', _) => Some(KeyCode::F1),
+            (b'Q', _) => Some(KeyCode::F2),
+            (b'R', _) => Some(KeyCode::F3),
+            (b'S', _) => Some(KeyCode::F4),
+            (b'~', 2) => Some(KeyCode::Insert),
+            (b'~', 3) => Some(KeyCode::Delete),
+            (b'~', 5) => Some(KeyCode::PageUp),
+            (b'~', 6) => Some(KeyCode::PageDown),
+            (b'~', 15) => Some(KeyCode::F5),
+            (b'~', 17) => Some(KeyCode::F6),
+            (b'~', 18) => Some(KeyCode::F7),
+            (b'~', 19) => Some(KeyCode::F8),
+            (b'~', 20) => Some(KeyCode::F9),
+            (b'~', 21) => Some(KeyCode::F10),
+            (b'~', 23) => Some(KeyCode::F11),
+            (b'~', 24) => Some(KeyCode::F12),
+            _ => None,
+        };
+
+        let modifiers = match bytes[end] {
+            b'Z' => modifiers | ModifiersState::SHIFT,
+            _ => modifiers,
+        };
+
+        if let Some(code) = code {
+            self.key(code, modifiers);
+        }
+
+        Some(end + 1)
+    }
+
+    /// Parses an SGR mouse report, `ESC [ < button ; column ; row` followed by `M` for a press
+    /// or `m` for a release, returning how many bytes it took up, or `None` if it hasn't ended yet.
+    fn parse_mouse(&mut self, bytes: &[u8]) -> Option<usize> {
+        let end = bytes[3..].iter().position(|byte| !matches!(byte, b'0'..=b'9' | b';'))? + 3;
+
+        // Anything else cuts the report short, so drop what came before it
+        if !matches!(bytes[end], b'M' | b'm') {
+            return Some(end);
+        }
+
+        let params: Vec<usize> = std::str::from_utf8(&bytes[3..end])
+            .unwrap_or_default()
+            .split(';')
+            .filter_map(|param| param.parse().ok())
+            .collect();
+
+        let [code, column, row] = params[..] else {
+            return Some(end + 1);
+        };
+
+        let mut modifiers = ModifiersState::empty();
+        modifiers.set(ModifiersState::SHIFT, code & 4 != 0);
+        modifiers.set(ModifiersState::ALT, code & 8 != 0);
+        modifiers.set(ModifiersState::CONTROL, code & 16 != 0);
+        self.set_modifiers(modifiers);
+
+        // Cells are numbered from one, and the mouse is put in the middle of its cell
+        let cell = self.cell_size;
+        let pos = IVec2::new(
+            (column.saturating_sub(1) * cell.x + cell.x / 2) as isize,
+            (row.saturating_sub(1) * cell.y + cell.y / 2) as isize,
+        );
+        self.events.push_back(BackendEvent::CursorMoved(pos));
+
+        let motion = code & 32 != 0;
+        let wheel = code & 64 != 0;
+
+        let button = match code & 3 {
+            0 => Some(MouseButton::Left),
+            1 => Some(MouseButton::Middle),
+            2 => Some(MouseButton::Right),
+            _ => None,
+        };
+
+        if let Some(button) = button.filter(|_| !motion && !wheel) {
+            let state = match bytes[end] {
+                b'M' => ElementState::Pressed,
+                _ => ElementState::Released,
+            };
+
+            self.events.push_back(BackendEvent::MouseInput { state, button });
+        }
+
+        Some(end + 1)
+    }
+
+    fn set_modifiers(&mut self, modifiers: ModifiersState) {
+        if self.modifiers != modifiers {
+            self.modifiers = modifiers;
+            self.events.push_back(BackendEvent::Modifiers(Modifiers::from(modifiers)));
+        }
+    }
+
+    fn alt_key(&mut self, byte: u8) {
+        if let Some((code, shift)) = key_for_ascii(byte) {
+            self.key(code, shift_if(shift) | ModifiersState::ALT);
+        }
+    }
+
+    /// Queues a key being pressed and released with `modifiers` held.
+    fn key(&mut self, code: KeyCode, modifiers: ModifiersState) {
+        self.set_modifiers(modifiers);
+
+        for state in [ElementState::Pressed, ElementState::Released] {
+            self.events.push_back(BackendEvent::Key {
+                key: PhysicalKey::Code(code),
+                state,
+                repeat: false,
+            });
+        }
+    }
+}
+
+impl Backend for TerminalBackend {
+    fn proxy(&self) -> EventSender {
+        let sender = self.sender.clone();
+
+        EventSender::new(move |event| {
+            sender.send(Input::Proxy(event)).expect("terminal backend should still be active");
+        })
+    }
+
+    fn wait_event(&mut self) -> Result<Option<BackendEvent>, BackendError> {
+        loop {
+            if let Some(event) = self.events.pop_front() {
+                return Ok(Some(event));
+            }
+
+            if self.closed {
+                return Ok(None);
+            }
+
+            match self.input.recv_timeout(RESIZE_POLL_INTERVAL) {
+                Ok(input) => self.handle_input(input),
+                Err(RecvTimeoutError::Timeout) => {
+                    self.parser.flush();
+                    self.take_parsed();
+                }
+                Err(RecvTimeoutError::Disconnected) => self.closed = true,
+            }
+
+            self.check_size();
+        }
+    }
+
+    fn poll_event(&mut self) -> Result<Option<BackendEvent>, BackendError> {
+        if self.events.is_empty() {
+            while let Ok(input) = self.input.try_recv() {
+                self.handle_input(input);
+            }
+
+            self.check_size();
+        }
+
+        Ok(self.events.pop_front())
+    }
+
+    fn present(&mut self, frame: &Frame) -> Result<(), BackendError> {
+        let cell = self.mode.cell_size();
+        let size = frame.get_size();
+        let cells = UVec2::new(size.x / cell.x, size.y / cell.y);
+        let mut out = String::new();
+
+        if self.screen_size != cells {
+            self.screen_size = cells;
+            self.screen = vec![None; cells.area()];
+            out.push_str("\x1b[0m\x1b[2J");
+        }
+
+        // Where the cursor is and which colors are set, to skip writing them again
+        let mut cursor = None;
+        let mut pen = None;
+
+        for region in frame.damage() {
+            let min = region.get_min();
+            let max = region.get_max();
+
+            let columns = min.x as usize / cell.x..(max.x as usize).div_ceil(cell.x).min(cells.x);
+            let rows = min.y as usize / cell.y..(max.y as usize).div_ceil(cell.y).min(cells.y);
+
+            for y in rows {
+                for x in columns.clone() {
+                    let new = self.mode.cell(frame, x * cell.x, y * cell.y);
+                    let current = &mut self.screen[y * cells.x + x];
+
+                    if *current == Some(new) {
+                        continue;
+                    }
+
+                    *current = Some(new);
+
+                    if cursor != Some((x, y)) {
+                        let _ = write!(out, "\x1b[{};{}H", y + 1, x + 1);
+                    }
+
+                    if pen != Some((new.fg, new.bg)) {
+                        let _ = write!(
+                            out,
+                            "\x1b[38;2;{};{};{};48;2;{};{};{}m",
+                            new.fg >> 16 & 0xff, new.fg >> 8 & 0xff, new.fg & 0xff,
+                            new.bg >> 16 & 0xff, new.bg >> 8 & 0xff, new.bg & 0xff,
+                        );
+                        pen = Some((new.fg, new.bg));
+                    }
+
+                    out.push(new.glyph);
+                    cursor = Some((x + 1, y));
+                }
+            }
+        }
+
+        if !out.is_empty() {
+            out.push_str("\x1b[0m");
+            self.out.write_all(out.as_bytes())?;
+            self.out.flush()?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Drop for TerminalBackend {
+    fn drop(&mut self) {
+        self.stop_reader.store(true, Ordering::Relaxed);
+        if let Some(reader) = self.reader.take() {
+            let _ = reader.join();
+        }
+
+        let _ = self.out.write_all(b"\x1b[0m\x1b[?1006l\x1b[?1003l\x1b[?25h\x1b[?1049l");
+        let _ = self.out.flush();
+
+        // SAFETY: `original` was filled in by `tcgetattr` when the backend was created
+        unsafe {
+            libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &self.original);
+        }
+    }
+}
+
+/// Sends everything read from stdin until it closes, the backend is gone or `stop` is set.
+/// Stdin is polled with a timeout rather than read straight away, so that `stop` is noticed
+/// even when no input comes in.
+fn read_stdin(sender: Sender<Input>, stop: &AtomicBool) {
+    let mut buf = [0; 256];
+    let mut fd = libc::pollfd { fd: libc::STDIN_FILENO, events: libc::POLLIN, revents: 0 };
+
+    while !stop.load(Ordering::Relaxed) {
+        // SAFETY: `fd` is a single valid `pollfd` that only has its `revents` written into
+        let ready = unsafe { libc::poll(&mut fd, 1, RESIZE_POLL_INTERVAL.as_millis() as libc::c_int) };
+
+        match ready {
+            0 => continue,
+            ..0 if io::Error::last_os_error().kind() == io::ErrorKind::Interrupted => continue,
+            _ => {}
+        }
+
+        let input = match io::stdin().read(&mut buf) {
+            Ok(0) | Err(_) => Input::Closed,
+            Ok(len) => Input::Bytes(buf[..len].to_vec()),
+        };
+
+        let closed = matches!(input, Input::Closed);
+        if sender.send(input).is_err() || closed {
+            break;
+        }
+    }
+}
+
+fn shift_if(shift: bool) -> ModifiersState {
+    match shift {
+        true => ModifiersState::SHIFT,
+        false => ModifiersState::empty(),
+    }
+}
+
+/// The size of the terminal in cells.
+fn terminal_size() -> io::Result<UVec2> {
+    // SAFETY: `winsize` is plain data that `TIOCGWINSZ` only writes into
+    let size = unsafe {
+        let mut size: libc::winsize = std::mem::zeroed();
+
+        if libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        size
+    };
+
+    Ok(UVec2::new(size.ws_col as usize, size.ws_row as usize))
+}
+
+/// Stops the terminal from echoing input, waiting for a whole line or turning keys into signals,
+/// returning the settings it had before.
+fn enable_raw_mode() -> io::Result<libc::termios> {
+    // SAFETY: `termios` is plain data that these calls only read from and write into
+    unsafe {
+        let mut original: libc::termios = std::mem::zeroed();
+
+        if libc::tcgetattr(libc::STDIN_FILENO, &mut original) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let mut raw = original;
+        libc::cfmakeraw(&mut raw);
+
+        if libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &raw) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(original)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(parser: &mut InputParser) -> Vec<(KeyCode, ModifiersState)> {
+        let mut modifiers = ModifiersState::empty();
+        let mut keys = Vec::new();
+
+        for event in parser.events.drain(..) {
+            match event {
+                BackendEvent::Modifiers(changed) => modifiers = changed.state(),
+                BackendEvent::Key { key: PhysicalKey::Code(code), state: ElementState::Pressed, .. } => keys.push((code, modifiers)),
+                _ => {}
+            }
+        }
+
+        keys
+    }
+
+    #[test]
+    fn escape_split_across_reads_is_kept_until_it_ends() {
+        let mut parser = InputParser::new(CellMode::HalfBlock.cell_size());
+
+        parser.parse(b"a\x1b[1;");
+        assert_eq!(keys(&mut parser), [(KeyCode::KeyA, ModifiersState::empty())]);
+
+        parser.parse(b"5Ab");
+        assert_eq!(keys(&mut parser), [
+            (KeyCode::ArrowUp, ModifiersState::CONTROL),
+            (KeyCode::KeyB, ModifiersState::empty()),
+        ]);
+    }
+
+    #[test]
+    fn mouse_report_split_across_reads_is_kept_until_it_ends() {
+        let mut parser = InputParser::new(CellMode::HalfBlock.cell_size());
+
+        parser.parse(b"\x1b[<0;3");
+        assert!(parser.events.is_empty());
+
+        parser.parse(b";2M");
+        assert_eq!(parser.events.drain(..).collect::<Vec<_>>(), [
+            BackendEvent::CursorMoved(IVec2::new(2, 3)),
+            BackendEvent::MouseInput { state: ElementState::Pressed, button: MouseButton::Left },
+        ]);
+    }
+
+    #[test]
+    fn flush_turns_a_trailing_escape_and_bracket_into_alt() {
+        let mut parser = InputParser::new(CellMode::HalfBlock.cell_size());
+
+        parser.parse(b"\x1b[");
+        assert!(parser.events.is_empty());
+
+        parser.flush();
+        assert_eq!(keys(&mut parser), [(KeyCode::BracketLeft, ModifiersState::ALT)]);
+    }
+
+    #[test]
+    fn flush_turns_a_lone_escape_into_the_escape_key() {
+        let mut parser = InputParser::new(CellMode::HalfBlock.cell_size());
+
+        parser.parse(b"\x1b");
+        assert!(parser.events.is_empty());
+
+        parser.flush();
+        assert_eq!(keys(&mut parser), [(KeyCode::Escape, ModifiersState::empty())]);
+        assert!(parser.pending.is_empty());
+    }
+
+    #[test]
+    fn escape_at_the_end_of_a_read_is_kept_for_the_next_one() {
+        let mut parser = InputParser::new(CellMode::HalfBlock.cell_size());
+
+        parser.parse(b"a\x1b");
+        assert_eq!(keys(&mut parser), [(KeyCode::KeyA, ModifiersState::empty())]);
+
+        parser.parse(b"[A");
+        assert_eq!(keys(&mut parser), [(KeyCode::ArrowUp, ModifiersState::empty())]);
+
+        parser.parse(b"\x1b");
+        parser.parse(b"x");
+        assert_eq!(keys(&mut parser), [(KeyCode::KeyX, ModifiersState::ALT)]);
+    }
+
+    #[test]
+    fn escape_followed_by_another_escape_is_the_escape_key() {
+        let mut parser = InputParser::new(CellMode::HalfBlock.cell_size());
+        parser.parse(b"\x1b\x1b[B");
+
+        assert_eq!(keys(&mut parser), [
+            (KeyCode::Escape, ModifiersState::empty()),
+            (KeyCode::ArrowDown, ModifiersState::empty()),
+        ]);
+    }
+
+    #[test]
+    fn broken_sequence_is_dropped_without_losing_what_follows() {
+        let mut parser = InputParser::new(CellMode::HalfBlock.cell_size());
+        parser.parse(b"\x1b[12\x1b[B");
+
+        assert_eq!(keys(&mut parser), [(KeyCode::ArrowDown, ModifiersState::empty())]);
+    }
+
+    #[test]
+    fn ctrl_c_interrupts() {
+        let mut parser = InputParser::new(CellMode::Braille.cell_size());
+        parser.parse(&[0x03]);
+
+        assert!(parser.interrupted);
+    }
+}
@@ -0,0 +1,1048 @@
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    io::{self, BufReader, Read, Write},
+    net::{Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, Sender},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use winit::{
+    event::{ElementState, Modifiers, MouseButton},
+    keyboard::{KeyCode, ModifiersState, PhysicalKey},
+};
+
+use crate::{
+    react::{EventSender, ProxyEvent},
+    util::{BoundingBox, IVec2, UVec2},
+};
+
+use super::{key_for_ascii, Backend, BackendError, BackendEvent, Frame};
+
+const ENCODING_RAW: i32 = 0;
+const ENCODING_COPY_RECT: i32 = 1;
+
+/// Past this many damaged regions, a client's damage is collapsed into one region.
+const MAX_CLIENT_DAMAGE: usize = 16;
+
+/// How long an update can take to send before the client is dropped,
+/// so that a viewer that stopped reading can't hold up the application.
+const WRITE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// How often the listener checks whether the backend was dropped while no viewer is connecting.
+const ACCEPT_POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// How a client wants pixels sent to it.
+///
+/// Only true-color formats are supported, so a client asking for a color map is disconnected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct PixelFormat {
+    bits_per_pixel: u8,
+    depth: u8,
+    big_endian: bool,
+    true_color: bool,
+    red_max: u16,
+    green_max: u16,
+    blue_max: u16,
+    red_shift: u8,
+    green_shift: u8,
+    blue_shift: u8,
+}
+
+impl Default for PixelFormat {
+    /// 32 bits per pixel holding `0x00RRGGBB`, the same as every present.
+    fn default() -> Self {
+        PixelFormat {
+            bits_per_pixel: 32,
+            depth: 24,
+            big_endian: false,
+            true_color: true,
+            red_max: 255,
+            green_max: 255,
+            blue_max: 255,
+            red_shift: 16,
+            green_shift: 8,
+            blue_shift: 0,
+        }
+    }
+}
+
+impl PixelFormat {
+    fn from_bytes(bytes: &[u8; 16]) -> Self {
+        PixelFormat {
+            bits_per_pixel: bytes[0],
+            depth: bytes[1],
+            big_endian: bytes[2] != 0,
+            true_color: bytes[3] != 0,
+            red_max: u16::from_be_bytes([bytes[4], bytes[5]]),
+            green_max: u16::from_be_bytes([bytes[6], bytes[7]]),
+            blue_max: u16::from_be_bytes([bytes[8], bytes[9]]),
+            red_shift: bytes[10],
+            green_shift: bytes[11],
+            blue_shift: bytes[12],
+        }
+    }
+
+    fn to_bytes(self) -> [u8; 16] {
+        let [red_high, red_low] = self.red_max.to_be_bytes();
+        let [green_high, green_low] = self.green_max.to_be_bytes();
+        let [blue_high, blue_low] = self.blue_max.to_be_bytes();
+
+        [
+            self.bits_per_pixel, self.depth, self.big_endian as u8, self.true_color as u8,
+            red_high, red_low, green_high, green_low, blue_high, blue_low,
+            self.red_shift, self.green_shift, self.blue_shift,
+            0, 0, 0,
+        ]
+    }
+
+    fn is_supported(&self) -> bool {
+        let shifts = [self.red_shift, self.green_shift, self.blue_shift];
+
+        self.true_color
+            && matches!(self.bits_per_pixel, 8 | 16 | 32)
+            && shifts.iter().all(|shift| *shift < self.bits_per_pixel)
+    }
+
+    /// Appends a `0x00RRGGBB` pixel in this format.
+    fn encode(&self, rgb: u32, out: &mut Vec<u8>) {
+        let channel = |value: u32, max: u16, shift: u8| ((value & 0xff) * max as u32 / 255).checked_shl(shift as u32).unwrap_or(0);
+
+        let value = channel(rgb >> 16, self.red_max, self.red_shift)
+            | channel(rgb >> 8, self.green_max, self.green_shift)
+            | channel(rgb, self.blue_max, self.blue_shift);
+
+        match (self.bits_per_pixel, self.big_endian) {
+            (8, _) => out.push(value as u8),
+            (16, true) => out.extend((value as u16).to_be_bytes()),
+            (16, false) => out.extend((value as u16).to_le_bytes()),
+            (_, true) => out.extend(value.to_be_bytes()),
+            (_, false) => out.extend(value.to_le_bytes()),
+        }
+    }
+}
+
+/// What the connection threads hand to the backend.
+enum Input {
+    Connected(u64, TcpStream),
+    Disconnected(u64),
+    PixelFormat(u64, PixelFormat),
+    Encodings(u64, Vec<i32>),
+    UpdateRequest { client: u64, incremental: bool, region: BoundingBox },
+    Key { down: bool, keysym: u32 },
+    Pointer { client: u64, buttons: u8, pos: IVec2 },
+    Proxy(ProxyEvent),
+}
+
+struct Client {
+    stream: TcpStream,
+    format: PixelFormat,
+    copy_rect: bool,
+    /// The latest update the client asked for and hasn't been sent yet.
+    request: Option<(bool, BoundingBox)>,
+    /// Regions that changed since they were last sent to the client.
+    damage: Vec<BoundingBox>,
+    /// What the client is showing, as far as the updates sent to it go.
+    shown: Vec<u32>,
+    buttons: u8,
+}
+
+/// A rectangle of a framebuffer update.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Update {
+    Raw(BoundingBox),
+    CopyRect { from: IVec2, to: BoundingBox },
+}
+
+/// A backend that serves the framebuffer to VNC viewers over the RFB protocol,
+/// so that an application can run on a machine without a display and be used from another.
+///
+/// Updates are sent with the raw and CopyRect encodings, without any authentication.
+/// Every connected viewer shares the same input,
+/// and a viewer that doesn't take an update within a second is disconnected.
+pub struct VncBackend {
+    size: UVec2,
+    /// The `0x00RRGGBB` value of every pixel presented so far.
+    pixels: Vec<u32>,
+    local_addr: SocketAddr,
+    sender: Sender<Input>,
+    input: Receiver<Input>,
+    /// The thread accepting viewers, which is stopped and joined when the backend is dropped
+    /// so that the port is freed.
+    acceptor: Option<JoinHandle<()>>,
+    stop_acceptor: Arc<AtomicBool>,
+    clients: HashMap<u64, Client>,
+    events: VecDeque<BackendEvent>,
+    modifiers: ModifiersState,
+    keys_down: HashSet<u32>,
+    mouse_position: IVec2,
+}
+
+impl VncBackend {
+    /// Starts listening for viewers, with a display of the given size in pixels.
+    pub fn bind<A: ToSocketAddrs>(addr: A, width: usize, height: usize) -> io::Result<VncBackend> {
+        Self::bind_with_name(addr, width, height, "pixel-ui")
+    }
+
+    /// Starts listening for viewers, which show `name` as the title of the desktop.
+    pub fn bind_with_name<A: ToSocketAddrs>(addr: A, width: usize, height: usize, name: &str) -> io::Result<VncBackend> {
+        let size = UVec2::new(width, height);
+
+        if width > u16::MAX as usize || height > u16::MAX as usize {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "display should fit in 65535x65535 pixels"));
+        }
+
+        let listener = TcpListener::bind(addr)?;
+        let local_addr = listener.local_addr()?;
+        // Accepting is polled so that the thread notices when the backend is dropped
+        listener.set_nonblocking(true)?;
+        let (sender, input) = mpsc::channel();
+
+        let stop_acceptor = Arc::new(AtomicBool::new(false));
+        let acceptor = {
+            let sender = sender.clone();
+            let stop = stop_acceptor.clone();
+            let name = name.to_string();
+            thread::spawn(move || accept_clients(listener, size, &name, &sender, &stop))
+        };
+
+        Ok(VncBackend {
+            size,
+            pixels: vec![0; size.area()],
+            local_addr,
+            sender,
+            input,
+            acceptor: Some(acceptor),
+            stop_acceptor,
+            clients: HashMap::new(),
+            events: VecDeque::from([BackendEvent::Resized(size)]),
+            modifiers: ModifiersState::empty(),
+            keys_down: HashSet::new(),
+            mouse_position: IVec2::default(),
+        })
+    }
+
+    /// The address viewers connect to, which is useful after binding to port 0.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    pub fn get_size(&self) -> UVec2 {
+        self.size
+    }
+
+    /// How many viewers are connected.
+    pub fn client_count(&self) -> usize {
+        self.clients.len()
+    }
+
+    fn screen(&self) -> BoundingBox {
+        BoundingBox::from_size(IVec2::default(), self.size)
+    }
+
+    fn handle_input(&mut self, input: Input) {
+        match input {
+            Input::Connected(id, stream) => {
+                self.clients.insert(id, Client {
+                    stream,
+                    format: PixelFormat::default(),
+                    copy_rect: false,
+                    request: None,
+                    damage: vec![self.screen()],
+                    // Nothing has been sent yet, and no pixel is ever presented as `u32::MAX`
+                    shown: vec![u32::MAX; self.size.area()],
+                    buttons: 0,
+                });
+            }
+            Input::Disconnected(id) => {
+                self.clients.remove(&id);
+            }
+            Input::PixelFormat(id, format) => {
+                let screen = self.screen();
+
+                if let Some(client) = self.clients.get_mut(&id) {
+                    client.format = format;
+                    // Everything the client has is in the old format
+                    client.shown.fill(u32::MAX);
+                    client.damage = vec![screen];
+                }
+            }
+            Input::Encodings(id, encodings) => {
+                if let Some(client) = self.clients.get_mut(&id) {
+                    client.copy_rect = encodings.contains(&ENCODING_COPY_RECT);
+                }
+            }
+            Input::UpdateRequest { client: id, incremental, region } => {
+                if let Some(region) = region.intersection(&self.screen()) {
+                    if let Some(client) = self.clients.get_mut(&id) {
+                        client.request = Some((incremental, region));
+                    }
+
+                    self.send_update(id);
+                }
+            }
+            Input::Key { down, keysym } => self.key(down, keysym),
+            Input::Pointer { client, buttons, pos } => self.pointer(client, buttons, pos),
+            Input::Proxy(event) => self.events.push_back(BackendEvent::Proxy(event)),
+        }
+    }
+
+    fn key(&mut self, down: bool, keysym: u32) {
+        let Some(code) = key_for_keysym(keysym) else {
+            return;
+        };
+
+        let modifier = match code {
+            KeyCode::ShiftLeft | KeyCode::ShiftRight => ModifiersState::SHIFT,
+            KeyCode::ControlLeft | KeyCode::ControlRight => ModifiersState::CONTROL,
+            KeyCode::AltLeft | KeyCode::AltRight => ModifiersState::ALT,
+            KeyCode::SuperLeft | KeyCode::SuperRight => ModifiersState::SUPER,
+            _ => ModifiersState::empty(),
+        };
+
+        if !modifier.is_empty() {
+            self.modifiers.set(modifier, down);
+            self.events.push_back(BackendEvent::Modifiers(Modifiers::from(self.modifiers)));
+        }
+
+        let (state, repeat) = match down {
+            // Viewers repeat a held key by sending it down again
+            true => (ElementState::Pressed, !self.keys_down.insert(keysym)),
+            false if self.keys_down.remove(&keysym) => (ElementState::Released, false),
+            false => return,
+        };
+
+        self.events.push_back(BackendEvent::Key {
+            key: PhysicalKey::Code(code),
+            state,
+            repeat,
+        });
+    }
+
+    fn pointer(&mut self, id: u64, buttons: u8, pos: IVec2) {
+        let Some(client) = self.clients.get_mut(&id) else {
+            return;
+        };
+
+        let previous = std::mem::replace(&mut client.buttons, buttons);
+
+        if pos != self.mouse_position {
+            self.mouse_position = pos;
+            self.events.push_back(BackendEvent::CursorMoved(pos));
+        }
+
+        // Bits 3 and 4 are the scroll wheel, which has no event yet
+        for (bit, button) in [(0, MouseButton::Left), (1, MouseButton::Middle), (2, MouseButton::Right)] {
+            let was_down = previous >> bit & 1 != 0;
+            let is_down = buttons >> bit & 1 != 0;
+
+            if was_down != is_down {
+                self.events.push_back(BackendEvent::MouseInput {
+                    state: if is_down { ElementState::Pressed } else { ElementState::Released },
+                    button,
+                });
+            }
+        }
+    }
+
+    /// Answers a client's update request if there is anything to send it.
+    fn send_update(&mut self, id: u64) {
+        let Some(client) = self.clients.get_mut(&id) else {
+            return;
+        };
+
+        if write_update(client, &self.pixels, self.size).is_err() {
+            // Whatever was written of the update leaves the stream unusable, and this also stops its thread
+            let _ = client.stream.shutdown(Shutdown::Both);
+            self.clients.remove(&id);
+        }
+    }
+}
+
+impl Drop for VncBackend {
+    fn drop(&mut self) {
+        self.stop_acceptor.store(true, Ordering::Relaxed);
+        if let Some(acceptor) = self.acceptor.take() {
+            let _ = acceptor.join();
+        }
+
+        // This also stops the thread reading from each client
+        for client in self.clients.values() {
+            let _ = client.stream.shutdown(Shutdown::Both);
+        }
+    }
+}
+
+impl Backend for VncBackend {
+    fn proxy(&self) -> EventSender {
+        let sender = self.sender.clone();
+
+        EventSender::new(move |event| {
+            sender.send(Input::Proxy(event)).expect("vnc backend should still be active");
+        })
+    }
+
+    fn wait_event(&mut self) -> Result<Option<BackendEvent>, BackendError> {
+        loop {
+            if let Some(event) = self.events.pop_front() {
+                return Ok(Some(event));
+            }
+
+            // The backend holds a sender itself, so this only fails if it is being dropped
+            match self.input.recv() {
+                Ok(input) => self.handle_input(input),
+                Err(_) => return Ok(None),
+            }
+        }
+    }
+
+    fn poll_event(&mut self) -> Result<Option<BackendEvent>, BackendError> {
+        if self.events.is_empty() {
+            while let Ok(input) = self.input.try_recv() {
+                self.handle_input(input);
+            }
+        }
+
+        Ok(self.events.pop_front())
+    }
+
+    fn present(&mut self, frame: &Frame) -> Result<(), BackendError> {
+        // The display has a fixed size, so anything outside of it is cut off
+        let damage: Vec<BoundingBox> = frame
+            .damage()
+            .iter()
+            .filter_map(|region| region.intersection(&self.screen()))
+            .collect();
+
+        let width = self.size.x;
+
+        for region in &damage {
+            for y in region.get_min().y as usize..region.get_max().y as usize {
+                for x in region.get_min().x as usize..region.get_max().x as usize {
+                    self.pixels[y * width + x] = frame.pixel(x, y);
+                }
+            }
+        }
+
+        for client in self.clients.values_mut() {
+            client.damage.extend(&damage);
+
+            if client.damage.len() > MAX_CLIENT_DAMAGE {
+                let first = client.damage[0];
+                client.damage = vec![client.damage.iter().fold(first, |all, region| all.union(region))];
+            }
+        }
+
+        let ids: Vec<u64> = self.clients.keys().copied().collect();
+        for id in ids {
+            self.send_update(id);
+        }
+
+        Ok(())
+    }
+}
+
+/// Sends a client the parts of its requested region that changed,
+/// using CopyRect for regions whose new contents the client is already showing elsewhere.
+fn write_update(client: &mut Client, pixels: &[u32], size: UVec2) -> io::Result<()> {
+    let Some((incremental, requested)) = client.request else {
+        return Ok(());
+    };
+
+    let mut regions: Vec<BoundingBox> = client.damage
+        .iter()
+        .filter_map(|region| region.intersection(&requested))
+        .collect();
+
+    if !incremental {
+        regions = vec![requested];
+    }
+
+    // Leave out regions that end up the same as what the client has, unless it asked for everything
+    let changed = |region: &BoundingBox| !incremental || region_pixels(pixels, size, *region) != region_pixels(&client.shown, size, *region);
+    let regions: Vec<BoundingBox> = regions.into_iter().filter(changed).collect();
+
+    client.damage.retain(|region| region.intersection(&requested) != Some(*region));
+
+    // An incremental request waits until there is something to send
+    if regions.is_empty() && incremental {
+        return Ok(());
+    }
+
+    let updates = plan_updates(&client.shown, client.copy_rect, pixels, size, &regions);
+    let mut out = vec![0, 0];
+    out.extend((updates.len() as u16).to_be_bytes());
+
+    for update in &updates {
+        let (to, encoding) = match update {
+            Update::Raw(to) => (to, ENCODING_RAW),
+            Update::CopyRect { to, .. } => (to, ENCODING_COPY_RECT),
+        };
+
+        out.extend((to.get_min().x as u16).to_be_bytes());
+        out.extend((to.get_min().y as u16).to_be_bytes());
+        out.extend((to.width() as u16).to_be_bytes());
+        out.extend((to.height() as u16).to_be_bytes());
+        out.extend(encoding.to_be_bytes());
+
+        match update {
+            Update::Raw(region) => {
+                for rgb in region_pixels(pixels, size, *region) {
+                    client.format.encode(rgb, &mut out);
+                }
+            }
+            Update::CopyRect { from, .. } => {
+                out.extend((from.x as u16).to_be_bytes());
+                out.extend((from.y as u16).to_be_bytes());
+            }
+        }
+    }
+
+    for update in &updates {
+        let region = match update {
+            Update::Raw(region) | Update::CopyRect { to: region, .. } => *region,
+        };
+
+        for y in region.get_min().y as usize..region.get_max().y as usize {
+            let row = y * size.x;
+            let x1 = row + region.get_min().x as usize;
+            let x2 = row + region.get_max().x as usize;
+
+            client.shown[x1..x2].copy_from_slice(&pixels[x1..x2]);
+        }
+    }
+
+    client.request = None;
+    client.stream.write_all(&out)?;
+    client.stream.flush()
+}
+
+/// Decides how each region is sent, in the order the client should apply them.
+///
+/// A region is copied from another damaged region the client still shows with the same contents,
+/// such as where an element moved from. Copies go first, before the regions they copy from are overwritten,
+/// and a copy never reads from a region an earlier copy wrote to.
+fn plan_updates(shown: &[u32], copy_rect: bool, pixels: &[u32], size: UVec2, regions: &[BoundingBox]) -> Vec<Update> {
+    let mut copies = Vec::new();
+    let mut raw = Vec::new();
+    let mut targets: Vec<BoundingBox> = Vec::new();
+
+    // A region of a single color is cheap to send either way,
+    // so regions with more detail get the first pick of what to copy from
+    let mut order: Vec<(BoundingBox, Vec<u32>)> = regions
+        .iter()
+        .map(|region| (*region, region_pixels(pixels, size, *region)))
+        .collect();
+    order.sort_by_key(|(_, contents)| contents.iter().all(|rgb| *rgb == contents[0]));
+
+    for (region, contents) in order {
+        let source = regions
+            .iter()
+            .filter(|from| copy_rect && **from != region)
+            .filter(|from| from.width() == region.width() && from.height() == region.height())
+            .filter(|from| targets.iter().all(|target| target.intersection(from).is_none()))
+            .find(|from| region_pixels(shown, size, **from) == contents);
+
+        match source {
+            Some(from) => {
+                targets.push(region);
+                copies.push(Update::CopyRect { from: from.get_min(), to: region });
+            }
+            None => raw.push(Update::Raw(region)),
+        }
+    }
+
+    copies.extend(raw);
+    copies
+}
+
+/// The pixels inside of a region, in row-major order.
+fn region_pixels(pixels: &[u32], size: UVec2, region: BoundingBox) -> Vec<u32> {
+    (region.get_min().y as usize..region.get_max().y as usize)
+        .flat_map(|y| {
+            let row = y * size.x;
+            pixels[row + region.get_min().x as usize..row + region.get_max().x as usize].iter().copied()
+        })
+        .collect()
+}
+
+/// Runs the handshake with a new client, then forwards its messages until it disconnects.
+/// Serves every viewer that connects to `listener` on a thread of its own until `stop` is set.
+fn accept_clients(listener: TcpListener, size: UVec2, name: &str, sender: &Sender<Input>, stop: &AtomicBool) {
+    let mut ids = 0u64..;
+
+    while !stop.load(Ordering::Relaxed) {
+        let stream = match listener.accept() {
+            Ok((stream, _)) => stream,
+            Err(err) if err.kind() == io::ErrorKind::WouldBlock => {
+                thread::sleep(ACCEPT_POLL_INTERVAL);
+                continue;
+            }
+            Err(_) => continue,
+        };
+
+        // Some platforms pass on the listener's non-blocking mode
+        if stream.set_nonblocking(false).is_err() {
+            continue;
+        }
+
+        let id = ids.next().expect("client ids should never run out");
+        let sender = sender.clone();
+        let name = name.to_string();
+        thread::spawn(move || {
+            // A client that fails the handshake or sends something malformed is dropped
+            let _ = serve_client(id, stream, size, &name, &sender);
+            let _ = sender.send(Input::Disconnected(id));
+        });
+    }
+}
+
+fn serve_client(id: u64, stream: TcpStream, size: UVec2, name: &str, sender: &Sender<Input>) -> io::Result<()> {
+    let mut writer = stream.try_clone()?;
+    writer.set_write_timeout(Some(WRITE_TIMEOUT))?;
+    let mut reader = BufReader::new(stream);
+
+    writer.write_all(b"RFB 003.008\n")?;
+
+    let mut version = [0; 12];
+    reader.read_exact(&mut version)?;
+
+    let minor = match &version {
+        b"RFB 003.003\n" => 3,
+        b"RFB 003.007\n" => 7,
+        _ if version.starts_with(b"RFB 003.") => 8,
+        _ => return Err(io::Error::new(io::ErrorKind::InvalidData, "client should speak RFB 3.x")),
+    };
+
+    // Only the "None" security type is offered
+    if minor == 3 {
+        writer.write_all(&1u32.to_be_bytes())?;
+    } else {
+        writer.write_all(&[1, 1])?;
+
+        let mut security = [0];
+        reader.read_exact(&mut security)?;
+
+        if security[0] != 1 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "client should pick the None security type"));
+        }
+
+        if minor == 8 {
+            writer.write_all(&0u32.to_be_bytes())?;
+        }
+    }
+
+    // Every client shares the display, so the shared flag doesn't matter
+    let mut shared = [0];
+    reader.read_exact(&mut shared)?;
+
+    let mut init = Vec::new();
+    init.extend((size.x as u16).to_be_bytes());
+    init.extend((size.y as u16).to_be_bytes());
+    init.extend(PixelFormat::default().to_bytes());
+    init.extend((name.len() as u32).to_be_bytes());
+    init.extend(name.as_bytes());
+    writer.write_all(&init)?;
+
+    let closed = || io::Error::new(io::ErrorKind::BrokenPipe, "backend should still be active");
+    sender.send(Input::Connected(id, writer)).map_err(|_| closed())?;
+
+    loop {
+        let mut kind = [0];
+        reader.read_exact(&mut kind)?;
+
+        let input = match kind[0] {
+            0 => {
+                let mut message = [0; 19];
+                reader.read_exact(&mut message)?;
+
+                let format = PixelFormat::from_bytes(message[3..].try_into().expect("pixel format should be 16 bytes"));
+                if !format.is_supported() {
+                    return Err(io::Error::new(io::ErrorKind::InvalidData, "client should ask for a true-color pixel format"));
+                }
+
+                Input::PixelFormat(id, format)
+            }
+            2 => {
+                let mut header = [0; 3];
+                reader.read_exact(&mut header)?;
+
+                let count = u16::from_be_bytes([header[1], header[2]]) as usize;
+                let mut encodings = vec![0; count * 4];
+                reader.read_exact(&mut encodings)?;
+
+                let encodings = encodings
+                    .chunks_exact(4)
+                    .map(|encoding| i32::from_be_bytes([encoding[0], encoding[1], encoding[2], encoding[3]]))
+                    .collect();
+
+                Input::Encodings(id, encodings)
+            }
+            3 => {
+                let mut message = [0; 9];
+                reader.read_exact(&mut message)?;
+
+                let field = |index: usize| u16::from_be_bytes([message[index], message[index + 1]]) as usize;
+
+                Input::UpdateRequest {
+                    client: id,
+                    incremental: message[0] != 0,
+                    region: BoundingBox::from_size(
+                        IVec2::new(field(1) as isize, field(3) as isize),
+                        UVec2::new(field(5), field(7)),
+                    ),
+                }
+            }
+            4 => {
+                let mut message = [0; 7];
+                reader.read_exact(&mut message)?;
+
+                Input::Key {
+                    down: message[0] != 0,
+                    keysym: u32::from_be_bytes([message[3], message[4], message[5], message[6]]),
+                }
+            }
+            5 => {
+                let mut message = [0; 5];
+                reader.read_exact(&mut message)?;
+
+                Input::Pointer {
+                    client: id,
+                    buttons: message[0],
+                    pos: IVec2::new(
+                        u16::from_be_bytes([message[1], message[2]]) as isize,
+                        u16::from_be_bytes([message[3], message[4]]) as isize,
+                    ),
+                }
+            }
+            6 => {
+                // Clipboard text isn't used, but still has to be read past
+                let mut header = [0; 7];
+                reader.read_exact(&mut header)?;
+
+                let len = u32::from_be_bytes([header[3], header[4], header[5], header[6]]) as u64;
+                io::copy(&mut (&mut reader).take(len), &mut io::sink())?;
+                continue;
+            }
+            _ => return Err(io::Error::new(io::ErrorKind::InvalidData, "client should send a known message type")),
+        };
+
+        sender.send(input).map_err(|_| closed())?;
+    }
+}
+
+/// The key an X11 keysym stands for.
+fn key_for_keysym(keysym: u32) -> Option<KeyCode> {
+    let code = match keysym {
+        0x20..=0x7e => return key_for_ascii(keysym as u8).map(|(code, _)| code),
+        0xff08 => KeyCode::Backspace,
+        0xff09 => KeyCode::Tab,
+        0xff0d => KeyCode::Enter,
+        0xff1b => KeyCode::Escape,
+        0xff50 => KeyCode::Home,
+        0xff51 => KeyCode::ArrowLeft,
+        0xff52 => KeyCode::ArrowUp,
+        0xff53 => KeyCode::ArrowRight,
+        0xff54 => KeyCode::ArrowDown,
+        0xff55 => KeyCode::PageUp,
+        0xff56 => KeyCode::PageDown,
+        0xff57 => KeyCode::End,
+        0xff63 => KeyCode::Insert,
+        0xffff => KeyCode::Delete,
+        0xffbe => KeyCode::F1,
+        0xffbf => KeyCode::F2,
+        0xffc0 => KeyCode::F3,
+        0xffc1 => KeyCode::F4,
+        0xffc2 => KeyCode::F5,
+        0xffc3 => KeyCode::F6,
+        0xffc4 => KeyCode::F7,
+        0xffc5 => KeyCode::F8,
+        0xffc6 => KeyCode::F9,
+        0xffc7 => KeyCode::F10,
+        0xffc8 => KeyCode::F11,
+        0xffc9 => KeyCode::F12,
+        0xffe1 => KeyCode::ShiftLeft,
+        0xffe2 => KeyCode::ShiftRight,
+        0xffe3 => KeyCode::ControlLeft,
+        0xffe4 => KeyCode::ControlRight,
+        0xffe9 => KeyCode::AltLeft,
+        0xffea => KeyCode::AltRight,
+        0xffeb => KeyCode::SuperLeft,
+        0xffec => KeyCode::SuperRight,
+        _ => return None,
+    };
+
+    Some(code)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use super::*;
+
+    /// Handles input until `done` holds, failing the test if it takes too long.
+    fn pump_until(backend: &mut VncBackend, done: impl Fn(&VncBackend) -> bool) -> Vec<BackendEvent> {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let mut events = Vec::new();
+
+        while !done(backend) {
+            assert!(Instant::now() < deadline, "backend should have handled the input by now");
+
+            match backend.poll_event().expect("polling should succeed") {
+                Some(event) => events.push(event),
+                None => thread::sleep(Duration::from_millis(1)),
+            }
+        }
+
+        events
+    }
+
+    /// Waits for `count` events, skipping the resize every backend starts with.
+    fn next_events(backend: &mut VncBackend, count: usize) -> Vec<BackendEvent> {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let mut events = Vec::new();
+
+        while events.len() < count {
+            assert!(Instant::now() < deadline, "backend should have handled the input by now");
+
+            match backend.poll_event().expect("polling should succeed") {
+                Some(BackendEvent::Resized(_)) => {}
+                Some(event) => events.push(event),
+                None => thread::sleep(Duration::from_millis(1)),
+            }
+        }
+
+        events
+    }
+
+    /// Connects a viewer and runs the RFB 3.8 handshake, returning the stream and the desktop name.
+    fn connect(backend: &mut VncBackend) -> (TcpStream, String) {
+        let mut stream = TcpStream::connect(backend.local_addr()).expect("should be able to connect");
+        stream.set_read_timeout(Some(Duration::from_secs(5))).expect("should be able to set a read timeout");
+
+        let mut version = [0; 12];
+        stream.read_exact(&mut version).expect("server should send its version");
+        assert_eq!(&version, b"RFB 003.008\n");
+        stream.write_all(b"RFB 003.008\n").expect("should be able to write");
+
+        let mut security = [0; 2];
+        stream.read_exact(&mut security).expect("server should offer security types");
+        assert_eq!(security, [1, 1]);
+        stream.write_all(&[1]).expect("should be able to write");
+
+        let mut result = [0; 4];
+        stream.read_exact(&mut result).expect("server should send the security result");
+        assert_eq!(result, [0; 4]);
+        stream.write_all(&[1]).expect("should be able to write");
+
+        let mut init = [0; 24];
+        stream.read_exact(&mut init).expect("server should send its init message");
+        assert_eq!(u16::from_be_bytes([init[0], init[1]]), backend.get_size().x as u16);
+        assert_eq!(u16::from_be_bytes([init[2], init[3]]), backend.get_size().y as u16);
+        assert_eq!(init[4..20], PixelFormat::default().to_bytes());
+
+        let mut name = vec![0; u32::from_be_bytes([init[20], init[21], init[22], init[23]]) as usize];
+        stream.read_exact(&mut name).expect("server should send the desktop name");
+
+        pump_until(backend, |backend| backend.client_count() == 1);
+
+        (stream, String::from_utf8(name).expect("desktop name should be utf-8"))
+    }
+
+    fn key_event(down: bool, keysym: u32) -> Vec<u8> {
+        [[4, down as u8, 0, 0].as_slice(), &keysym.to_be_bytes()].concat()
+    }
+
+    #[test]
+    fn dropping_the_backend_frees_the_port_and_disconnects_viewers() {
+        let mut backend = VncBackend::bind("127.0.0.1:0", 6, 4).expect("should be able to bind");
+        let addr = backend.local_addr();
+        let (mut stream, _) = connect(&mut backend);
+
+        drop(backend);
+
+        let mut buf = [0];
+        assert_eq!(stream.read(&mut buf).expect("viewer should see the connection close"), 0);
+
+        let mut backend = VncBackend::bind(addr, 6, 4).expect("port should be free again");
+        connect(&mut backend);
+    }
+
+    #[test]
+    fn handshake_sends_the_size_and_name() {
+        let mut backend = VncBackend::bind_with_name("127.0.0.1:0", 6, 4, "test").expect("should be able to bind");
+        let (_stream, name) = connect(&mut backend);
+
+        assert_eq!(name, "test");
+    }
+
+    #[test]
+    fn keys_are_mapped_from_their_keysyms() {
+        let mut backend = VncBackend::bind("127.0.0.1:0", 6, 4).expect("should be able to bind");
+        let (mut stream, _) = connect(&mut backend);
+
+        let message = [key_event(true, 0xffe1), key_event(true, b'a' as u32), key_event(true, b'a' as u32), key_event(false, b'a' as u32)].concat();
+        stream.write_all(&message).expect("should be able to write");
+
+        let key = |state, repeat| BackendEvent::Key { key: PhysicalKey::Code(KeyCode::KeyA), state, repeat };
+        assert_eq!(next_events(&mut backend, 5), [
+            BackendEvent::Modifiers(Modifiers::from(ModifiersState::SHIFT)),
+            BackendEvent::Key { key: PhysicalKey::Code(KeyCode::ShiftLeft), state: ElementState::Pressed, repeat: false },
+            key(ElementState::Pressed, false),
+            key(ElementState::Pressed, true),
+            key(ElementState::Released, false),
+        ]);
+    }
+
+    #[test]
+    fn pointer_events_move_and_click() {
+        let mut backend = VncBackend::bind("127.0.0.1:0", 6, 4).expect("should be able to bind");
+        let (mut stream, _) = connect(&mut backend);
+
+        stream.write_all(&[5, 1, 0, 3, 0, 2, 5, 0, 0, 3, 0, 2]).expect("should be able to write");
+
+        assert_eq!(next_events(&mut backend, 3), [
+            BackendEvent::CursorMoved(IVec2::new(3, 2)),
+            BackendEvent::MouseInput { state: ElementState::Pressed, button: MouseButton::Left },
+            BackendEvent::MouseInput { state: ElementState::Released, button: MouseButton::Left },
+        ]);
+    }
+
+    #[test]
+    fn update_request_is_answered_in_the_clients_pixel_format() {
+        let mut backend = VncBackend::bind("127.0.0.1:0", 2, 1).expect("should be able to bind");
+        let (mut stream, _) = connect(&mut backend);
+        backend.pixels = vec![0xFF0000, 0x0000FF];
+
+        // 16 bits per pixel, big-endian RGB565
+        let format = PixelFormat {
+            bits_per_pixel: 16,
+            depth: 16,
+            big_endian: true,
+            true_color: true,
+            red_max: 31,
+            green_max: 63,
+            blue_max: 31,
+            red_shift: 11,
+            green_shift: 5,
+            blue_shift: 0,
+        };
+
+        // Clipboard text is skipped over on the way
+        stream.write_all(&[[0, 0, 0, 0].as_slice(), &format.to_bytes()].concat()).expect("should be able to write");
+        stream.write_all(&[6, 0, 0, 0, 0, 0, 0, 2, b'h', b'i']).expect("should be able to write");
+        stream.write_all(&[3, 0, 0, 0, 0, 0, 0, 2, 0, 1]).expect("should be able to write");
+        stream.set_nonblocking(true).expect("should be able to stop blocking");
+        pump_until(&mut backend, |_| stream.peek(&mut [0]).is_ok());
+        stream.set_nonblocking(false).expect("should be able to block again");
+
+        let mut update = [0; 20];
+        stream.read_exact(&mut update).expect("server should send an update");
+        assert_eq!(update, [0, 0, 0, 1, 0, 0, 0, 0, 0, 2, 0, 1, 0, 0, 0, 0, 0xF8, 0x00, 0x00, 0x1F]);
+    }
+
+    #[test]
+    fn color_map_pixel_format_disconnects_the_client() {
+        let mut backend = VncBackend::bind("127.0.0.1:0", 2, 2).expect("should be able to bind");
+        let (mut stream, _) = connect(&mut backend);
+
+        let format = PixelFormat { true_color: false, ..PixelFormat::default() };
+        stream.write_all(&[[0, 0, 0, 0].as_slice(), &format.to_bytes()].concat()).expect("should be able to write");
+
+        pump_until(&mut backend, |backend| backend.client_count() == 0);
+    }
+
+    #[test]
+    fn unknown_message_disconnects_the_client() {
+        let mut backend = VncBackend::bind("127.0.0.1:0", 2, 2).expect("should be able to bind");
+        let (mut stream, _) = connect(&mut backend);
+
+        stream.write_all(&[200]).expect("should be able to write");
+
+        pump_until(&mut backend, |backend| backend.client_count() == 0);
+    }
+
+    #[test]
+    fn shifts_past_the_pixel_size_are_unsupported() {
+        let format = PixelFormat { bits_per_pixel: 8, depth: 8, red_shift: 8, ..PixelFormat::default() };
+        assert!(!format.is_supported());
+
+        let format = PixelFormat { red_shift: 200, ..PixelFormat::default() };
+        assert!(!format.is_supported());
+
+        let mut out = Vec::new();
+        format.encode(0xFF0000, &mut out);
+        assert_eq!(out, [0; 4]);
+    }
+
+    #[test]
+    fn encode_scales_and_shifts_every_channel() {
+        let format = PixelFormat {
+            bits_per_pixel: 8,
+            depth: 8,
+            red_max: 7,
+            green_max: 7,
+            blue_max: 3,
+            red_shift: 5,
+            green_shift: 2,
+            blue_shift: 0,
+            ..PixelFormat::default()
+        };
+        assert!(format.is_supported());
+
+        let mut out = Vec::new();
+        format.encode(0xFF00FF, &mut out);
+        format.encode(0x00FF00, &mut out);
+        // Red takes the top three bits and blue the bottom two, with green in the middle
+        assert_eq!(out, [0b1110_0011, 0b0001_1100]);
+    }
+
+    /// Plans updates for a 4x1 display with its left and right halves damaged.
+    fn plan(shown: &[u32], copy_rect: bool, pixels: &[u32]) -> Vec<Update> {
+        plan_updates(shown, copy_rect, pixels, UVec2::new(4, 1), &[region(0), region(2)])
+    }
+
+    fn region(x: isize) -> BoundingBox {
+        BoundingBox::from_size(IVec2::new(x, 0), UVec2::new(2, 1))
+    }
+
+    #[test]
+    fn moved_contents_are_copied_before_their_source_is_overwritten() {
+        let shown = [1, 2, 0, 0];
+        let pixels = [0, 0, 1, 2];
+
+        assert_eq!(plan(&shown, true, &pixels), [
+            Update::CopyRect { from: IVec2::new(0, 0), to: region(2) },
+            Update::Raw(region(0)),
+        ]);
+    }
+
+    #[test]
+    fn copy_never_reads_from_an_earlier_target() {
+        // Swapping two regions can only copy one of them
+        let shown = [1, 2, 3, 4];
+        let pixels = [3, 4, 1, 2];
+
+        assert_eq!(plan(&shown, true, &pixels), [
+            Update::CopyRect { from: IVec2::new(2, 0), to: region(0) },
+            Update::Raw(region(2)),
+        ]);
+    }
+
+    #[test]
+    fn everything_is_raw_without_copy_rect() {
+        let shown = [1, 2, 0, 0];
+        let pixels = [0, 0, 1, 2];
+
+        // Regions of a single color go last
+        assert_eq!(plan(&shown, false, &pixels), [
+            Update::Raw(region(2)),
+            Update::Raw(region(0)),
+        ]);
+    }
+}
@@ -0,0 +1,189 @@
+use std::{collections::VecDeque, num::NonZeroU32, rc::Rc, time::Duration};
+
+use softbuffer::Surface;
+use winit::{
+    application::ApplicationHandler,
+    error::EventLoopError,
+    event::WindowEvent,
+    event_loop::{ActiveEventLoop, ControlFlow, EventLoop},
+    platform::pump_events::{EventLoopExtPumpEvents, PumpStatus},
+    window::{Window, WindowAttributes, WindowId},
+};
+
+use crate::{
+    react::{EventSender, ProxyEvent},
+    util::{BoundingBox, IVec2, UVec2},
+};
+
+use super::{Backend, BackendError, BackendEvent, Frame};
+
+/// A backend that opens a window with `winit` and presents into it with `softbuffer`.
+///
+/// The event loop is pumped from [`wait_event`](`Backend::wait_event`),
+/// so this only exists on platforms that support pumping events:
+/// Windows, macOS, Linux, the BSDs and Android.
+pub struct WindowBackend {
+    event_loop: EventLoop<ProxyEvent>,
+    state: WindowState,
+}
+
+/// Everything the event loop hands events to while it is pumped.
+struct WindowState {
+    attributes: WindowAttributes,
+    surface: Option<Surface<Rc<Window>, Rc<Window>>>,
+    events: VecDeque<BackendEvent>,
+    closed: bool,
+}
+
+impl WindowBackend {
+    pub fn new() -> Result<WindowBackend, EventLoopError> {
+        let event_loop = EventLoop::with_user_event().build()?;
+        event_loop.set_control_flow(ControlFlow::Wait);
+
+        Ok(WindowBackend {
+            event_loop,
+            state: WindowState {
+                attributes: WindowAttributes::default(),
+                surface: None,
+                events: VecDeque::new(),
+                closed: false,
+            },
+        })
+    }
+
+    /// Runs the event loop until it has handled what is waiting,
+    /// blocking for up to `timeout` if nothing is, or forever if it is `None`.
+    fn pump(&mut self, timeout: Option<Duration>) {
+        if self.state.closed {
+            return;
+        }
+
+        if let PumpStatus::Exit(_) = self.event_loop.pump_app_events(timeout, &mut self.state) {
+            self.state.closed = true;
+        }
+    }
+}
+
+impl Backend for WindowBackend {
+    fn proxy(&self) -> EventSender {
+        EventSender::from(self.event_loop.create_proxy())
+    }
+
+    fn set_window_attributes(&mut self, attributes: WindowAttributes) {
+        self.state.attributes = attributes;
+    }
+
+    fn wait_event(&mut self) -> Result<Option<BackendEvent>, BackendError> {
+        loop {
+            if let Some(event) = self.state.events.pop_front() {
+                return Ok(Some(event));
+            }
+
+            if self.state.closed {
+                return Ok(None);
+            }
+
+            self.pump(None);
+        }
+    }
+
+    fn poll_event(&mut self) -> Result<Option<BackendEvent>, BackendError> {
+        if self.state.events.is_empty() {
+            self.pump(Some(Duration::ZERO));
+        }
+
+        Ok(self.state.events.pop_front())
+    }
+
+    fn present(&mut self, frame: &Frame) -> Result<(), BackendError> {
+        let Some(surface) = &mut self.state.surface else {
+            return Ok(());
+        };
+
+        let size = frame.get_size();
+        let window = surface.window().clone();
+
+        surface.resize(
+            NonZeroU32::new(size.x as u32).expect("window width should be greater than 0"),
+            NonZeroU32::new(size.y as u32).expect("window height should be greater than 0")
+        ).expect("should be able to resize draw buffer");
+
+        let mut buffer = surface.buffer_mut().expect("should be able to retrieve draw buffer");
+
+        // Any buffer other than the one presented last holds an older frame, or nothing at all
+        let damage = match buffer.age() {
+            1 => frame.damage().to_vec(),
+            _ => vec![BoundingBox::from_size(IVec2::default(), size)],
+        };
+
+        frame.write_regions(&mut buffer, &damage);
+
+        let rects: Vec<softbuffer::Rect> = damage
+            .iter()
+            .map(|region| softbuffer::Rect {
+                x: region.get_min().x as u32,
+                y: region.get_min().y as u32,
+                width: NonZeroU32::new(region.width() as u32).expect("damaged region should have a width"),
+                height: NonZeroU32::new(region.height() as u32).expect("damaged region should have a height"),
+            })
+            .collect();
+
+        if !rects.is_empty() {
+            window.pre_present_notify();
+            buffer.present_with_damage(&rects).expect("should be able to present buffer");
+        }
+
+        Ok(())
+    }
+}
+
+impl ApplicationHandler<ProxyEvent> for WindowState {
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        let window = Rc::new(event_loop.create_window(self.attributes.clone()).expect("should be able to create window"));
+        let dimensions = window.inner_size();
+
+        let context = softbuffer::Context::new(window.clone()).expect("should be able to create draw context");
+        let surface = Surface::new(&context, window).expect("should be able to create draw surface");
+
+        self.surface = Some(surface);
+        self.events.push_back(BackendEvent::Resized(UVec2::new(dimensions.width as usize, dimensions.height as usize)));
+        self.events.push_back(BackendEvent::Redraw);
+    }
+
+    fn suspended(&mut self, _event_loop: &ActiveEventLoop) {
+        self.surface = None;
+    }
+
+    fn window_event(&mut self, event_loop: &ActiveEventLoop, _window_id: WindowId, event: WindowEvent) {
+        let event = match event {
+            WindowEvent::CursorMoved { device_id: _, position } => {
+                BackendEvent::CursorMoved(IVec2::new(position.x as isize, position.y as isize))
+            }
+            WindowEvent::MouseInput { device_id: _, state, button } => {
+                BackendEvent::MouseInput { state, button }
+            }
+            WindowEvent::ModifiersChanged(modifiers) => BackendEvent::Modifiers(modifiers),
+            WindowEvent::KeyboardInput { device_id: _, event, is_synthetic: _ } => BackendEvent::Key {
+                key: event.physical_key,
+                state: event.state,
+                repeat: event.repeat,
+            },
+            WindowEvent::Resized(size) => {
+                BackendEvent::Resized(UVec2::new(size.width as usize, size.height as usize))
+            }
+            WindowEvent::RedrawRequested => BackendEvent::Redraw,
+            WindowEvent::CloseRequested => {
+                self.closed = true;
+                event_loop.exit();
+                return;
+            }
+            _ => return,
+        };
+
+        self.events.push_back(event);
+    }
+
+    fn user_event(&mut self, _event_loop: &ActiveEventLoop, event: ProxyEvent) {
+        self.events.push_back(BackendEvent::Proxy(event));
+    }
+}
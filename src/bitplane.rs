@@ -0,0 +1,360 @@
+use crate::{draw::RasterOp, util::{BoundingBox, IVec2, UVec2}};
+
+const WORD_BITS: usize = u64::BITS as usize;
+
+/// A grid of bits packed 64 to a word.
+///
+/// Every row starts on a new word, with the leftmost pixel in the lowest bit,
+/// so that rows can be filled and copied a whole word at a time.
+/// The bits past the end of a row are always zero.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bitplane {
+    size: UVec2,
+    /// The number of words in every row.
+    stride: usize,
+    words: Vec<u64>,
+}
+
+impl Bitplane {
+    pub fn new(size: UVec2, value: bool) -> Bitplane {
+        let stride = size.x.div_ceil(WORD_BITS);
+        let mut plane = Bitplane {
+            size,
+            stride,
+            words: vec![0; stride * size.y],
+        };
+
+        if value {
+            plane.fill(true);
+        }
+
+        plane
+    }
+
+    /// Packs row-major bits.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of bits doesn't match the size.
+    pub fn from_bools(size: UVec2, bits: &[bool]) -> Bitplane {
+        assert_eq!(bits.len(), size.area(), "bits should match the size");
+
+        let mut plane = Bitplane::new(size, false);
+
+        for (index, bit) in bits.iter().enumerate() {
+            if *bit {
+                plane.set_index(index, true);
+            }
+        }
+
+        plane
+    }
+
+    pub fn get_size(&self) -> UVec2 {
+        self.size
+    }
+
+    /// The words of a row, where pixel `x` is bit `x % 64` of word `x / 64`.
+    pub fn row(&self, y: usize) -> &[u64] {
+        &self.words[y * self.stride..(y + 1) * self.stride]
+    }
+
+    fn row_mut(&mut self, y: usize) -> &mut [u64] {
+        &mut self.words[y * self.stride..(y + 1) * self.stride]
+    }
+
+    /// The bit at a position, or `false` if it is out of bounds.
+    pub fn get(&self, x: usize, y: usize) -> bool {
+        if x >= self.size.x || y >= self.size.y {
+            return false;
+        }
+
+        self.row(y)[x / WORD_BITS] >> (x % WORD_BITS) & 1 != 0
+    }
+
+    /// The bit at a row-major index, or `false` if it is out of bounds.
+    pub fn get_index(&self, index: usize) -> bool {
+        match self.size.x {
+            0 => false,
+            width => self.get(index % width, index / width),
+        }
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, value: bool) {
+        if x >= self.size.x || y >= self.size.y {
+            return;
+        }
+
+        let word = &mut self.row_mut(y)[x / WORD_BITS];
+        let bit = 1 << (x % WORD_BITS);
+
+        if value {
+            *word |= bit;
+        } else {
+            *word &= !bit;
+        }
+    }
+
+    pub fn set_index(&mut self, index: usize, value: bool) {
+        if let Some(width) = std::num::NonZeroUsize::new(self.size.x) {
+            self.set(index % width, index / width, value);
+        }
+    }
+
+    /// Sets every bit.
+    pub fn fill(&mut self, value: bool) {
+        self.fill_rect(BoundingBox::from_size(IVec2::default(), self.size), value);
+    }
+
+    /// Sets every bit inside of a region, skipping the parts that are out of bounds.
+    pub fn fill_rect(&mut self, region: BoundingBox, value: bool) {
+        let bounds = BoundingBox::from_size(IVec2::default(), self.size);
+        let Some(region) = region.intersection(&bounds) else {
+            return;
+        };
+
+        let x1 = region.get_min().x as usize;
+        let x2 = region.get_max().x as usize;
+
+        for y in region.get_min().y as usize..region.get_max().y as usize {
+            for (index, word) in self.row_mut(y).iter_mut().enumerate().take(x2.div_ceil(WORD_BITS)).skip(x1 / WORD_BITS) {
+                let mask = span_mask(index, x1, x2);
+
+                if value {
+                    *word |= mask;
+                } else {
+                    *word &= !mask;
+                }
+            }
+        }
+    }
+
+    /// Combines another bitplane into this one with its top-left corner at `origin`,
+    /// only touching bits inside of `clip` and wherever `mask` is set.
+    pub fn blit(&mut self, origin: IVec2, source: &Bitplane, mask: Option<&Bitplane>, clip: BoundingBox, op: RasterOp) {
+        let bounds = BoundingBox::from_size(IVec2::default(), self.size);
+        let source_bounds = BoundingBox::from_size(origin, source.size);
+
+        let Some(visible) = source_bounds.intersection(&clip).and_then(|visible| visible.intersection(&bounds)) else {
+            return;
+        };
+
+        let x1 = visible.get_min().x as usize;
+        let x2 = visible.get_max().x as usize;
+
+        for y in visible.get_min().y..visible.get_max().y {
+            let source_y = (y - origin.y) as usize;
+            let source_row = source.row(source_y);
+            let mask_row = mask.map(|mask| mask.row(source_y));
+            let row = self.row_mut(y as usize);
+
+            for (index, word) in row.iter_mut().enumerate().take(x2.div_ceil(WORD_BITS)).skip(x1 / WORD_BITS) {
+                // The source bit that lines up with bit 0 of this word
+                let start = (index * WORD_BITS) as isize - origin.x;
+
+                let bits = read_bits(source_row, start);
+                let opaque = mask_row.map_or(u64::MAX, |mask_row| read_bits(mask_row, start));
+                let mask = opaque & span_mask(index, x1, x2);
+
+                *word = op.apply_word(*word, bits, mask);
+            }
+        }
+    }
+
+    /// The number of bits that are set.
+    pub fn count_ones(&self) -> usize {
+        self.words.iter().map(|word| word.count_ones() as usize).sum()
+    }
+
+    /// Unpacks the bits in row-major order.
+    pub fn to_bools(&self) -> Vec<bool> {
+        (0..self.size.y)
+            .flat_map(|y| (0..self.size.x).map(move |x| (x, y)))
+            .map(|(x, y)| self.get(x, y))
+            .collect()
+    }
+}
+
+/// The bits of word `index` that fall inside of the pixels `x1..x2`.
+fn span_mask(index: usize, x1: usize, x2: usize) -> u64 {
+    let start = index * WORD_BITS;
+    let end = start + WORD_BITS;
+
+    if x2 <= start || x1 >= end {
+        return 0;
+    }
+
+    let low = if x1 > start { u64::MAX << (x1 - start) } else { u64::MAX };
+    let high = if x2 < end { u64::MAX >> (end - x2) } else { u64::MAX };
+
+    low & high
+}
+
+/// The 64 bits of a row starting at bit `start`, with anything outside of the row read as zero.
+fn read_bits(row: &[u64], start: isize) -> u64 {
+    if start < 0 {
+        let shift = start.unsigned_abs();
+        return if shift >= WORD_BITS { 0 } else { read_bits(row, 0) << shift };
+    }
+
+    let start = start as usize;
+    let index = start / WORD_BITS;
+    let shift = start % WORD_BITS;
+
+    let low = row.get(index).map_or(0, |word| word >> shift);
+    let high = match shift {
+        0 => 0,
+        _ => row.get(index + 1).map_or(0, |word| word << (WORD_BITS - shift)),
+    };
+
+    low | high
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OPS: [RasterOp; 5] = [RasterOp::Replace, RasterOp::Or, RasterOp::And, RasterOp::Xor, RasterOp::Invert];
+
+    /// A bitplane of noise, so that every word holds a mix of bits.
+    fn noise(width: usize, height: usize, seed: u32) -> Bitplane {
+        let mut state = seed;
+        let bits: Vec<bool> = (0..width * height)
+            .map(|_| {
+                state = state.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+                state >> 16 & 1 != 0
+            })
+            .collect();
+
+        Bitplane::from_bools(UVec2::new(width, height), &bits)
+    }
+
+    /// Blits one pixel at a time, the slow way.
+    fn reference_blit(dst: &Bitplane, origin: IVec2, source: &Bitplane, mask: Option<&Bitplane>, clip: BoundingBox, op: RasterOp) -> Vec<bool> {
+        let size = dst.get_size();
+
+        (0..size.y)
+            .flat_map(|y| (0..size.x).map(move |x| (x, y)))
+            .map(|(x, y)| {
+                let before = dst.get(x, y);
+                let local = IVec2::new(x as isize, y as isize) - origin;
+
+                let inside = clip.contains(IVec2::new(x as isize, y as isize))
+                    && (0..source.get_size().x as isize).contains(&local.x)
+                    && (0..source.get_size().y as isize).contains(&local.y);
+
+                if !inside || !mask.is_none_or(|mask| mask.get(local.x as usize, local.y as usize)) {
+                    return before;
+                }
+
+                let bit = source.get(local.x as usize, local.y as usize);
+
+                match op {
+                    RasterOp::Replace => bit,
+                    RasterOp::Or => before | bit,
+                    RasterOp::And => before & bit,
+                    RasterOp::Xor => before ^ bit,
+                    RasterOp::Invert => !before,
+                }
+            })
+            .collect()
+    }
+
+    fn check_blit(origin: IVec2, source_size: UVec2, clip: BoundingBox) {
+        let dst = noise(200, 6, 1);
+        let source = noise(source_size.x, source_size.y, 2);
+        let mask = noise(source_size.x, source_size.y, 3);
+
+        for op in OPS {
+            for mask in [None, Some(&mask)] {
+                let mut actual = dst.clone();
+                actual.blit(origin, &source, mask, clip, op);
+
+                assert_eq!(
+                    actual.to_bools(),
+                    reference_blit(&dst, origin, &source, mask, clip, op),
+                    "{op:?} at {origin:?} with {} clipped to {clip:?}",
+                    if mask.is_some() { "a mask" } else { "no mask" },
+                );
+            }
+        }
+    }
+
+    fn everything() -> BoundingBox {
+        BoundingBox::new(-1000, -1000, 1000, 1000)
+    }
+
+    #[test]
+    fn blit_at_word_aligned_origins() {
+        check_blit(IVec2::new(0, 0), UVec2::new(64, 6), everything());
+        check_blit(IVec2::new(64, 1), UVec2::new(128, 3), everything());
+    }
+
+    #[test]
+    fn blit_at_unaligned_origins_straddling_words() {
+        check_blit(IVec2::new(3, 0), UVec2::new(70, 6), everything());
+        check_blit(IVec2::new(61, 2), UVec2::new(5, 2), everything());
+        check_blit(IVec2::new(127, 0), UVec2::new(130, 6), everything());
+        check_blit(IVec2::new(190, 0), UVec2::new(40, 6), everything());
+    }
+
+    #[test]
+    fn blit_at_negative_origins() {
+        check_blit(IVec2::new(-5, -2), UVec2::new(70, 6), everything());
+        check_blit(IVec2::new(-64, 0), UVec2::new(130, 6), everything());
+        check_blit(IVec2::new(-100, 0), UVec2::new(90, 6), everything());
+        check_blit(IVec2::new(-70, 0), UVec2::new(200, 6), everything());
+    }
+
+    #[test]
+    fn blit_inside_clips_that_cut_mid_word() {
+        check_blit(IVec2::new(3, 0), UVec2::new(190, 6), BoundingBox::new(10, 1, 75, 4));
+        check_blit(IVec2::new(-20, 0), UVec2::new(150, 6), BoundingBox::new(63, 0, 65, 6));
+        check_blit(IVec2::new(30, 0), UVec2::new(100, 6), BoundingBox::new(129, 0, 200, 6));
+    }
+
+    #[test]
+    fn blit_outside_of_the_clip_changes_nothing() {
+        check_blit(IVec2::new(0, 0), UVec2::new(50, 6), BoundingBox::new(60, 0, 100, 6));
+        check_blit(IVec2::new(250, 0), UVec2::new(50, 6), everything());
+    }
+
+    #[test]
+    fn fill_rect_matches_setting_every_bit() {
+        let regions = [
+            BoundingBox::new(0, 0, 200, 6),
+            BoundingBox::new(3, 1, 5, 2),
+            BoundingBox::new(60, 0, 70, 6),
+            BoundingBox::new(63, 2, 129, 5),
+            BoundingBox::new(-10, -3, 64, 2),
+            BoundingBox::new(150, 4, 300, 10),
+        ];
+
+        for region in regions {
+            for value in [false, true] {
+                let mut expected = noise(200, 6, 4);
+                for y in region.get_min().y..region.get_max().y {
+                    for x in region.get_min().x..region.get_max().x {
+                        if x >= 0 && y >= 0 {
+                            expected.set(x as usize, y as usize, value);
+                        }
+                    }
+                }
+
+                let mut actual = noise(200, 6, 4);
+                actual.fill_rect(region, value);
+
+                assert_eq!(actual, expected, "{value} in {region:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn bits_past_the_end_of_a_row_stay_clear() {
+        let mut plane = Bitplane::new(UVec2::new(70, 2), true);
+        plane.blit(IVec2::new(60, 0), &Bitplane::new(UVec2::new(20, 2), true), None, everything(), RasterOp::Invert);
+
+        assert_eq!(plane.row(0)[1] >> 6, 0);
+        assert_eq!(plane.count_ones(), 2 * 60);
+    }
+}
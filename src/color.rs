@@ -1,8 +1,8 @@
 pub use inner::*;
 
-#[cfg(all(feature = "single-color", not(any(feature = "grayscale", feature = "full-color"))))]
+#[cfg(all(feature = "single-color", not(any(feature = "grayscale", feature = "full-color", feature = "palette"))))]
 mod inner {
-    use std::ops::Not;
+    use std::ops::{BitAnd, BitOr, BitXor, Not};
 
     #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
     #[repr(transparent)]
@@ -15,11 +15,16 @@ mod inner {
         pub const fn new(value: bool) -> Color {
             Color(value)
         }
+
+        /// The perceived brightness of the color, from 0 to 255.
+        pub const fn luminance(&self) -> u8 {
+            if self.0 { u8::MAX } else { u8::MIN }
+        }
     }
 
-    impl Into<u32> for Color {
-        fn into(self) -> u32 {
-            if self.0 {
+    impl From<Color> for u32 {
+        fn from(value: Color) -> u32 {
+            if value.0 {
                 // max of every color
                 0x00FFFFFF
             } else {
@@ -35,23 +40,52 @@ mod inner {
             Color(!self.0)
         }
     }
+
+    impl BitOr for Color {
+        type Output = Color;
+
+        fn bitor(self, rhs: Self) -> Self::Output {
+            Color(self.0 | rhs.0)
+        }
+    }
+
+    impl BitAnd for Color {
+        type Output = Color;
+
+        fn bitand(self, rhs: Self) -> Self::Output {
+            Color(self.0 & rhs.0)
+        }
+    }
+
+    impl BitXor for Color {
+        type Output = Color;
+
+        fn bitxor(self, rhs: Self) -> Self::Output {
+            Color(self.0 ^ rhs.0)
+        }
+    }
 }
 
-#[cfg(all(feature = "grayscale"))]
+#[cfg(all(feature = "grayscale", not(any(feature = "full-color", feature = "palette"))))]
 mod inner {
-    use std::ops::Not;
+    use std::ops::{BitAnd, BitOr, BitXor, Not};
 
     #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
     #[repr(transparent)]
     pub struct Color(u8);
     
     impl Color {
-        pub const WHITE: Color = Color(std::u8::MAX);
-        pub const BLACK: Color = Color(std::u8::MIN);
+        pub const WHITE: Color = Color(u8::MAX);
+        pub const BLACK: Color = Color(u8::MIN);
 
         pub const fn new(value: u8) -> Color {
             Color(value)
         }
+
+        /// The perceived brightness of the color, from 0 to 255.
+        pub const fn luminance(&self) -> u8 {
+            self.0
+        }
     }
 
     impl From<u8> for Color {
@@ -60,10 +94,198 @@ mod inner {
         }
     }
 
-    impl Into<u32> for Color {
-        fn into(self) -> u32 {
-            let color = self.0 as u32;
-            0x00000000 | (color << 16) | (color << 8) | (color << 0)
+    impl From<Color> for u32 {
+        fn from(value: Color) -> u32 {
+            let color = value.0 as u32;
+            (color << 16) | (color << 8) | color
+        }
+    }
+
+    impl Not for Color {
+        type Output = Color;
+
+        fn not(self) -> Self::Output {
+            Color(!self.0)
+        }
+    }
+
+    impl BitOr for Color {
+        type Output = Color;
+
+        fn bitor(self, rhs: Self) -> Self::Output {
+            Color(self.0 | rhs.0)
+        }
+    }
+
+    impl BitAnd for Color {
+        type Output = Color;
+
+        fn bitand(self, rhs: Self) -> Self::Output {
+            Color(self.0 & rhs.0)
+        }
+    }
+
+    impl BitXor for Color {
+        type Output = Color;
+
+        fn bitxor(self, rhs: Self) -> Self::Output {
+            Color(self.0 ^ rhs.0)
+        }
+    }
+}
+
+#[cfg(feature = "full-color")]
+mod inner {
+    use std::ops::{BitAnd, BitOr, BitXor, Not};
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct Color {
+        r: u8,
+        g: u8,
+        b: u8,
+    }
+
+    impl Color {
+        pub const WHITE: Color = Color::new(u8::MAX, u8::MAX, u8::MAX);
+        pub const BLACK: Color = Color::new(u8::MIN, u8::MIN, u8::MIN);
+        pub const RED: Color = Color::new(u8::MAX, u8::MIN, u8::MIN);
+        pub const GREEN: Color = Color::new(u8::MIN, u8::MAX, u8::MIN);
+        pub const BLUE: Color = Color::new(u8::MIN, u8::MIN, u8::MAX);
+
+        pub const fn new(r: u8, g: u8, b: u8) -> Color {
+            Color { r, g, b }
+        }
+
+        /// Creates a color from a `0xRRGGBB` value, ignoring the upper byte.
+        pub const fn from_hex(hex: u32) -> Color {
+            Color::new((hex >> 16) as u8, (hex >> 8) as u8, hex as u8)
+        }
+
+        /// Creates a color from a grayscale level,
+        /// matching how the `grayscale` mode presents it.
+        pub const fn gray(value: u8) -> Color {
+            Color::new(value, value, value)
+        }
+
+        pub const fn r(&self) -> u8 {
+            self.r
+        }
+
+        pub const fn g(&self) -> u8 {
+            self.g
+        }
+
+        pub const fn b(&self) -> u8 {
+            self.b
+        }
+
+        /// The perceived brightness of the color, from 0 to 255.
+        pub const fn luminance(&self) -> u8 {
+            crate::dither::luminance(((self.r as u32) << 16) | ((self.g as u32) << 8) | (self.b as u32))
+        }
+    }
+
+    /// Converts a grayscale level.
+    impl From<u8> for Color {
+        fn from(value: u8) -> Self {
+            Color::gray(value)
+        }
+    }
+
+    /// Converts a single-color value, where `true` is white.
+    impl From<bool> for Color {
+        fn from(value: bool) -> Self {
+            if value { Color::WHITE } else { Color::BLACK }
+        }
+    }
+
+    impl From<(u8, u8, u8)> for Color {
+        fn from(value: (u8, u8, u8)) -> Self {
+            Color::new(value.0, value.1, value.2)
+        }
+    }
+
+    impl From<[u8; 3]> for Color {
+        fn from(value: [u8; 3]) -> Self {
+            Color::new(value[0], value[1], value[2])
+        }
+    }
+
+    impl From<Color> for u32 {
+        fn from(value: Color) -> u32 {
+            ((value.r as u32) << 16) | ((value.g as u32) << 8) | (value.b as u32)
+        }
+    }
+
+    impl Not for Color {
+        type Output = Color;
+
+        fn not(self) -> Self::Output {
+            Color::new(!self.r, !self.g, !self.b)
+        }
+    }
+
+    impl BitOr for Color {
+        type Output = Color;
+
+        fn bitor(self, rhs: Self) -> Self::Output {
+            Color::new(self.r | rhs.r, self.g | rhs.g, self.b | rhs.b)
+        }
+    }
+
+    impl BitAnd for Color {
+        type Output = Color;
+
+        fn bitand(self, rhs: Self) -> Self::Output {
+            Color::new(self.r & rhs.r, self.g & rhs.g, self.b & rhs.b)
+        }
+    }
+
+    impl BitXor for Color {
+        type Output = Color;
+
+        fn bitxor(self, rhs: Self) -> Self::Output {
+            Color::new(self.r ^ rhs.r, self.g ^ rhs.g, self.b ^ rhs.b)
+        }
+    }
+}
+
+#[cfg(all(feature = "palette", not(feature = "full-color")))]
+mod inner {
+    use std::ops::{BitAnd, BitOr, BitXor, Not};
+
+    /// An index into the active [`Palette`].
+    ///
+    /// Indices wrap to the palette's depth, so [`Color::WHITE`] is always
+    /// the last entry of the palette and [`Not`] mirrors an index
+    /// around the middle of the palette.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    #[repr(transparent)]
+    pub struct Color(u8);
+
+    impl Color {
+        pub const WHITE: Color = Color(u8::MAX);
+        pub const BLACK: Color = Color(u8::MIN);
+
+        pub const fn new(index: u8) -> Color {
+            Color(index)
+        }
+
+        pub const fn index(&self) -> u8 {
+            self.0
+        }
+
+        /// The index treated as a brightness from 0 to 255, which only matches
+        /// 8-bit palettes ordered from darkest to lightest like the default one.
+        /// Use [`Palette::get`] for the brightness of the presented color.
+        pub const fn luminance(&self) -> u8 {
+            self.0
+        }
+    }
+
+    impl From<u8> for Color {
+        fn from(value: u8) -> Self {
+            Color(value)
         }
     }
 
@@ -74,4 +296,228 @@ mod inner {
             Color(!self.0)
         }
     }
+
+    impl BitOr for Color {
+        type Output = Color;
+
+        fn bitor(self, rhs: Self) -> Self::Output {
+            Color(self.0 | rhs.0)
+        }
+    }
+
+    impl BitAnd for Color {
+        type Output = Color;
+
+        fn bitand(self, rhs: Self) -> Self::Output {
+            Color(self.0 & rhs.0)
+        }
+    }
+
+    impl BitXor for Color {
+        type Output = Color;
+
+        fn bitxor(self, rhs: Self) -> Self::Output {
+            Color(self.0 ^ rhs.0)
+        }
+    }
+
+    /// The number of bits of a [`Color`] index used to look up a [`Palette`] entry.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub enum PaletteDepth {
+        /// 4 colors.
+        Two,
+        /// 16 colors.
+        Four,
+        /// 256 colors.
+        Eight,
+    }
+
+    impl PaletteDepth {
+        pub const fn bits(&self) -> u32 {
+            match self {
+                PaletteDepth::Two => 2,
+                PaletteDepth::Four => 4,
+                PaletteDepth::Eight => 8,
+            }
+        }
+
+        pub const fn size(&self) -> usize {
+            1 << self.bits()
+        }
+    }
+
+    /// Maps [`Color`] indices to `0x00RRGGBB` values when a frame is presented.
+    #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+    pub struct Palette {
+        depth: PaletteDepth,
+        colors: Vec<u32>,
+    }
+
+    impl Palette {
+        /// Creates a palette from `0x00RRGGBB` values.
+        /// Missing entries are black, and entries past the depth are ignored.
+        pub fn new(depth: PaletteDepth, colors: &[u32]) -> Palette {
+            let mut colors = colors.to_vec();
+            colors.resize(depth.size(), 0x00000000);
+
+            Palette { depth, colors }
+        }
+
+        /// An evenly spaced ramp from black to white.
+        pub fn grayscale(depth: PaletteDepth) -> Palette {
+            let max = depth.size() as u32 - 1;
+            let colors: Vec<u32> = (0..=max)
+                .map(|index| {
+                    let level = index * 0xFF / max;
+                    (level << 16) | (level << 8) | level
+                })
+                .collect();
+
+            Palette { depth, colors }
+        }
+
+        /// The four shades of green of the original Game Boy, darkest first.
+        pub fn game_boy() -> Palette {
+            Palette::new(PaletteDepth::Two, &[0x000F380F, 0x00306230, 0x008BAC0F, 0x009BBC0F])
+        }
+
+        /// The 16 colors of the CGA text mode palette.
+        pub fn cga() -> Palette {
+            Palette::new(PaletteDepth::Four, &[
+                0x00000000, 0x000000AA, 0x0000AA00, 0x0000AAAA,
+                0x00AA0000, 0x00AA00AA, 0x00AA5500, 0x00AAAAAA,
+                0x00555555, 0x005555FF, 0x0055FF55, 0x0055FFFF,
+                0x00FF5555, 0x00FF55FF, 0x00FFFF55, 0x00FFFFFF,
+            ])
+        }
+
+        pub fn get_depth(&self) -> PaletteDepth {
+            self.depth
+        }
+
+        pub fn get(&self, color: Color) -> u32 {
+            self.colors[self.index_of(color)]
+        }
+
+        pub fn set(&mut self, color: Color, value: u32) {
+            let index = self.index_of(color);
+            self.colors[index] = value;
+        }
+
+        pub fn colors(&self) -> &[u32] {
+            &self.colors
+        }
+
+        fn index_of(&self, color: Color) -> usize {
+            color.0 as usize & (self.depth.size() - 1)
+        }
+    }
+
+    impl Default for Palette {
+        fn default() -> Self {
+            Palette::grayscale(PaletteDepth::Eight)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "full-color")]
+    mod full_color {
+        use crate::color::Color;
+
+        #[test]
+        fn from_hex_ignores_the_upper_byte() {
+            assert_eq!(Color::from_hex(0xAB123456), Color::new(0x12, 0x34, 0x56));
+            assert_eq!(u32::from(Color::from_hex(0x00123456)), 0x00123456);
+        }
+
+        #[test]
+        fn conversions_match_the_other_color_modes() {
+            assert_eq!(Color::from(0x80u8), Color::gray(0x80));
+            assert_eq!(Color::from(true), Color::WHITE);
+            assert_eq!(Color::from(false), Color::BLACK);
+            assert_eq!(Color::from((1, 2, 3)), Color::new(1, 2, 3));
+            assert_eq!(Color::from([1, 2, 3]), Color::new(1, 2, 3));
+        }
+
+        #[test]
+        fn bitwise_ops_apply_to_every_channel() {
+            let a = Color::new(0b1100, 0b1010, 0xF0);
+            let b = Color::new(0b1010, 0b0110, 0x0F);
+
+            assert_eq!(!a, Color::new(0xF3, 0xF5, 0x0F));
+            assert_eq!(a | b, Color::new(0b1110, 0b1110, 0xFF));
+            assert_eq!(a & b, Color::new(0b1000, 0b0010, 0x00));
+            assert_eq!(a ^ b, Color::new(0b0110, 0b1100, 0xFF));
+        }
+
+        #[test]
+        fn luminance_weighs_green_over_red_over_blue() {
+            assert_eq!(Color::WHITE.luminance(), 0xFF);
+            assert_eq!(Color::BLACK.luminance(), 0x00);
+            assert_eq!(Color::gray(0x80).luminance(), 0x80);
+            assert!(Color::GREEN.luminance() > Color::RED.luminance());
+            assert!(Color::RED.luminance() > Color::BLUE.luminance());
+        }
+    }
+
+    #[cfg(all(feature = "palette", not(feature = "full-color")))]
+    mod palette {
+        use crate::{color::{Color, Palette, PaletteDepth}, element::Element, headless::Headless};
+
+        #[test]
+        fn indices_wrap_to_the_depth() {
+            let palette = Palette::new(PaletteDepth::Two, &[0x000000, 0x111111, 0x222222, 0x333333]);
+
+            assert_eq!(palette.get(Color::new(5)), 0x111111);
+            assert_eq!(palette.get(Color::WHITE), 0x333333);
+            assert_eq!(palette.get(Color::BLACK), 0x000000);
+            assert_eq!(palette.get(!Color::new(1)), 0x222222);
+        }
+
+        #[test]
+        fn set_writes_the_wrapped_entry() {
+            let mut palette = Palette::new(PaletteDepth::Four, &[]);
+            palette.set(Color::new(0x13), 0xABCDEF);
+
+            assert_eq!(palette.get(Color::new(3)), 0xABCDEF);
+            assert_eq!(palette.colors().len(), 16);
+        }
+
+        #[test]
+        fn built_in_palettes_run_from_black_or_darkest_to_white_or_lightest() {
+            let gray = Palette::grayscale(PaletteDepth::Two);
+            assert_eq!(gray.colors(), [0x000000, 0x555555, 0xAAAAAA, 0xFFFFFF]);
+            assert_eq!(Palette::default().get(Color::new(0x80)), 0x808080);
+
+            let game_boy = Palette::game_boy();
+            assert_eq!(game_boy.get_depth(), PaletteDepth::Two);
+            assert_eq!(game_boy.get(Color::BLACK), 0x0F380F);
+            assert_eq!(game_boy.get(Color::WHITE), 0x9BBC0F);
+
+            let cga = Palette::cga();
+            assert_eq!(cga.get_depth(), PaletteDepth::Four);
+            assert_eq!(cga.get(Color::BLACK), 0x000000);
+            assert_eq!(cga.get(Color::WHITE), 0xFFFFFF);
+        }
+
+        #[test]
+        fn set_palette_re_themes_without_redrawing_elements() {
+            let mut app = Headless::new(4, 4).with_background(Color::BLACK);
+            let id = app.insert_element(Element::rect(2, 2, Color::WHITE));
+            app.set_palette(Palette::game_boy());
+            app.render();
+            assert_eq!(app.backend().pixel(0, 0), Some(0x9BBC0F));
+            assert_eq!(app.backend().pixel(3, 3), Some(0x0F380F));
+
+            app.set_palette(Palette::cga());
+            assert!(!app.get(id).is_changed());
+
+            app.render();
+            assert_eq!(app.backend().pixel(0, 0), Some(0xFFFFFF));
+            assert_eq!(app.backend().pixel(3, 3), Some(0x000000));
+            assert_eq!(app.backend().get_present_count(), 2);
+        }
+    }
 }
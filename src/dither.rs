@@ -0,0 +1,211 @@
+use crate::{
+    color::Color,
+    element::{canvas::Canvas, icon::Icon},
+    util::UVec2,
+};
+
+/// Converts grayscale content into two levels, for displays that can only show black and white.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Dither {
+    /// Every level at or above the threshold becomes white.
+    Threshold(u8),
+    /// Ordered dithering with a 2x2 Bayer matrix.
+    Bayer2,
+    /// Ordered dithering with a 4x4 Bayer matrix.
+    Bayer4,
+    /// Ordered dithering with an 8x8 Bayer matrix.
+    Bayer8,
+    /// Error diffusion that spreads all of the error to four neighbors.
+    FloydSteinberg,
+    /// Error diffusion that spreads 3/4 of the error to six neighbors,
+    /// giving higher contrast than Floyd-Steinberg.
+    Atkinson,
+}
+
+// (dx, dy, weight)
+const FLOYD_STEINBERG: &[(isize, usize, i32)] = &[(1, 0, 7), (-1, 1, 3), (0, 1, 5), (1, 1, 1)];
+const FLOYD_STEINBERG_DIVISOR: i32 = 16;
+
+const ATKINSON: &[(isize, usize, i32)] = &[(1, 0, 1), (2, 0, 1), (-1, 1, 1), (0, 1, 1), (1, 1, 1), (0, 2, 1)];
+const ATKINSON_DIVISOR: i32 = 8;
+
+impl Dither {
+    /// Dithers row-major grayscale levels, returning `true` for every white pixel.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of levels doesn't match the size.
+    pub fn dither_bits(&self, levels: &[u8], size: UVec2) -> Vec<bool> {
+        assert_eq!(levels.len(), size.area(), "levels should match the size");
+
+        match self {
+            Dither::Threshold(threshold) => levels.iter().map(|level| level >= threshold).collect(),
+            Dither::Bayer2 => ordered(levels, size, 2),
+            Dither::Bayer4 => ordered(levels, size, 4),
+            Dither::Bayer8 => ordered(levels, size, 8),
+            Dither::FloydSteinberg => diffuse(levels, size, FLOYD_STEINBERG, FLOYD_STEINBERG_DIVISOR),
+            Dither::Atkinson => diffuse(levels, size, ATKINSON, ATKINSON_DIVISOR),
+        }
+    }
+
+    /// Dithers row-major grayscale levels into [`Color::WHITE`] and [`Color::BLACK`].
+    pub fn dither(&self, levels: &[u8], size: UVec2) -> Vec<Color> {
+        self.dither_bits(levels, size)
+            .into_iter()
+            .map(|white| if white { Color::WHITE } else { Color::BLACK })
+            .collect()
+    }
+
+    /// Dithers colors by their [`luminance`](`Color::luminance`).
+    pub fn dither_colors(&self, colors: &[Color], size: UVec2) -> Vec<Color> {
+        let levels: Vec<u8> = colors.iter().map(Color::luminance).collect();
+        self.dither(&levels, size)
+    }
+
+    /// Creates a dithered copy of an icon, keeping its silhouette.
+    pub fn dither_icon(&self, icon: &Icon) -> Icon {
+        let size = icon.get_size();
+        let dithered = Icon::new(size.x, size.y, self.dither_colors(icon.get_content(), size));
+
+        match icon.get_mask() {
+            Some(mask) => dithered.with_mask(mask.to_vec()),
+            None => dithered,
+        }
+    }
+
+    /// Dithers the contents of a canvas in place, leaving erased pixels transparent.
+    pub fn dither_canvas(&self, canvas: &Canvas) {
+        let graphic = canvas.draw();
+        let size = graphic.get_size();
+        let dithered = self.dither_colors(&graphic.pixels().to_colors(), size);
+        let ctx = canvas.get_context();
+
+        for (index, color) in dithered.into_iter().enumerate() {
+            if graphic.is_opaque(index) {
+                ctx.pixel(UVec2::new(index % size.x, index / size.x), color);
+            }
+        }
+    }
+}
+
+/// The perceived brightness of a `0x00RRGGBB` color, using the ITU-R BT.601 weights.
+pub const fn luminance(rgb: u32) -> u8 {
+    let r = (rgb >> 16) & 0xFF;
+    let g = (rgb >> 8) & 0xFF;
+    let b = rgb & 0xFF;
+
+    ((r * 299 + g * 587 + b * 114) / 1000) as u8
+}
+
+/// Builds an `n`x`n` Bayer index matrix, where `n` is a power of two.
+fn bayer_matrix(n: usize) -> Vec<u32> {
+    let mut matrix = vec![0];
+    let mut size = 1;
+
+    while size < n {
+        let next_size = size * 2;
+        let mut next = vec![0; next_size * next_size];
+
+        for y in 0..size {
+            for x in 0..size {
+                let value = matrix[y * size + x] * 4;
+
+                next[y * next_size + x] = value;
+                next[y * next_size + x + size] = value + 2;
+                next[(y + size) * next_size + x] = value + 3;
+                next[(y + size) * next_size + x + size] = value + 1;
+            }
+        }
+
+        matrix = next;
+        size = next_size;
+    }
+
+    matrix
+}
+
+fn ordered(levels: &[u8], size: UVec2, n: usize) -> Vec<bool> {
+    let matrix = bayer_matrix(n);
+    let cells = (n * n) as u32;
+
+    levels
+        .iter()
+        .enumerate()
+        .map(|(index, level)| {
+            let x = index % size.x;
+            let y = index / size.x;
+            let rank = matrix[(y % n) * n + (x % n)];
+
+            // Compare against the center of the rank's share of the 0..=255 range
+            (*level as u32) * cells >= (2 * rank + 1) * 128
+        })
+        .collect()
+}
+
+fn diffuse(levels: &[u8], size: UVec2, kernel: &[(isize, usize, i32)], divisor: i32) -> Vec<bool> {
+    let mut error: Vec<i32> = levels.iter().map(|level| *level as i32).collect();
+    let mut out = Vec::with_capacity(levels.len());
+
+    for y in 0..size.y {
+        for x in 0..size.x {
+            let old = error[y * size.x + x];
+            let white = old >= 128;
+            let diff = old - if white { 255 } else { 0 };
+
+            out.push(white);
+
+            for &(dx, dy, weight) in kernel {
+                let nx = x as isize + dx;
+                let ny = y + dy;
+
+                if nx >= 0 && (nx as usize) < size.x && ny < size.y {
+                    error[ny * size.x + nx as usize] += diff * weight / divisor;
+                }
+            }
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Two rows of levels going from black on the left to white on the right.
+    fn gradient(width: usize) -> Vec<u8> {
+        let row: Vec<u8> = (0..width).map(|x| (x * 255 / (width - 1)) as u8).collect();
+        [row.clone(), row].concat()
+    }
+
+    #[test]
+    fn bayer_matrix_is_the_standard_index_matrix() {
+        assert_eq!(bayer_matrix(2), [0, 2, 3, 1]);
+        assert_eq!(bayer_matrix(4), [0, 8, 2, 10, 12, 4, 14, 6, 3, 11, 1, 9, 15, 7, 13, 5]);
+    }
+
+    #[test]
+    fn bayer_dithers_a_gradient() {
+        let bits = Dither::Bayer2.dither_bits(&gradient(8), UVec2::new(8, 2));
+
+        #[rustfmt::skip]
+        assert_eq!(bits, [
+            false, false, true, false, true, true, true, true,
+            false, false, false, true, false, true, false, true,
+        ]);
+    }
+
+    #[test]
+    fn floyd_steinberg_dithers_a_gradient() {
+        let bits = Dither::FloydSteinberg.dither_bits(&gradient(4), UVec2::new(4, 2));
+
+        assert_eq!(bits, [false, false, true, true, false, false, true, true]);
+    }
+
+    #[test]
+    fn floyd_steinberg_turns_middle_gray_into_a_checkerboard() {
+        let bits = Dither::FloydSteinberg.dither_bits(&[128; 4], UVec2::new(4, 1));
+
+        assert_eq!(bits, [true, false, true, false]);
+    }
+}
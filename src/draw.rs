@@ -1,4 +1,4 @@
-use crate::{color::Color, util::{BoundingBox, IVec2}};
+use crate::{bitplane::Bitplane, color::Color, util::{BoundingBox, IVec2, UVec2}};
 
 pub fn write(buf: &mut [bool], width: usize, height: usize, position: IVec2, input: bool) {
     if position.x >= 0 && position.y >= 0 {
@@ -6,50 +6,314 @@ pub fn write(buf: &mut [bool], width: usize, height: usize, position: IVec2, inp
         let y_pos = position.y as usize;
 
         if x_pos < width && y_pos < height {
-            buf[(x_pos + y_pos * width) as usize] = input;
+            buf[x_pos + y_pos * width] = input;
         }
     }
 }
 
-pub fn write_all(
-    buf: &mut [Color],
-    width: usize,
-    height: usize,
-    bounds: BoundingBox,
-    content: Vec<Color>,
-) {
-    // If either max is negative, we can just assume the whole contents are out of frame
-    if bounds.get_max().x < 0 || bounds.get_max().y < 0 {
-        return;
+/// A row-major grid of pixels.
+///
+/// Single-color pixels are packed into a [`Bitplane`],
+/// every other mode stores one [`Color`] per pixel.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Framebuffer {
+    #[cfg(all(feature = "single-color", not(any(feature = "grayscale", feature = "full-color", feature = "palette"))))]
+    pixels: Bitplane,
+    #[cfg(any(feature = "grayscale", feature = "full-color", feature = "palette"))]
+    size: UVec2,
+    #[cfg(any(feature = "grayscale", feature = "full-color", feature = "palette"))]
+    pixels: Vec<Color>,
+}
+
+#[cfg(all(feature = "single-color", not(any(feature = "grayscale", feature = "full-color", feature = "palette"))))]
+impl Framebuffer {
+    pub fn new(size: UVec2, color: Color) -> Framebuffer {
+        Framebuffer { pixels: Bitplane::new(size, color == Color::WHITE) }
     }
 
-    if bounds.get_min().x >= width as isize || bounds.get_min().y >= height as isize {
-        return;
+    /// # Panics
+    ///
+    /// Panics if the number of pixels doesn't match the size.
+    pub fn from_colors(size: UVec2, pixels: Vec<Color>) -> Framebuffer {
+        let bits: Vec<bool> = pixels.into_iter().map(|color| color == Color::WHITE).collect();
+
+        Framebuffer { pixels: Bitplane::from_bools(size, &bits) }
     }
 
-    if bounds.area() != content.len() {
-        return;
+    /// Uses a bitplane as the pixels, where set bits are white.
+    pub fn from_bitplane(pixels: Bitplane) -> Framebuffer {
+        Framebuffer { pixels }
+    }
+
+    pub fn as_bitplane(&self) -> &Bitplane {
+        &self.pixels
+    }
+
+    pub fn get_size(&self) -> UVec2 {
+        self.pixels.get_size()
+    }
+
+    /// The pixel at a row-major index.
+    pub fn get_index(&self, index: usize) -> Color {
+        Color::new(self.pixels.get_index(index))
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, color: Color) {
+        self.pixels.set(x, y, color == Color::WHITE);
+    }
+
+    /// Sets every pixel inside of a region, skipping the parts that are out of bounds.
+    pub fn fill_rect(&mut self, region: BoundingBox, color: Color) {
+        self.pixels.fill_rect(region, color == Color::WHITE);
+    }
+
+    /// Writes the opaque pixels of a graphic with its top-left corner at `origin`,
+    /// skipping every pixel outside of `clip`.
+    fn blit(&mut self, origin: IVec2, content: &Graphic, clip: BoundingBox, op: RasterOp) {
+        self.pixels.blit(origin, &content.pixels.pixels, content.mask.as_ref(), clip, op);
+    }
+}
+
+#[cfg(any(feature = "grayscale", feature = "full-color", feature = "palette"))]
+impl Framebuffer {
+    pub fn new(size: UVec2, color: Color) -> Framebuffer {
+        Framebuffer { size, pixels: vec![color; size.area()] }
+    }
+
+    /// # Panics
+    ///
+    /// Panics if the number of pixels doesn't match the size.
+    pub fn from_colors(size: UVec2, pixels: Vec<Color>) -> Framebuffer {
+        assert_eq!(pixels.len(), size.area(), "pixels should match the size");
+
+        Framebuffer { size, pixels }
+    }
+
+    pub fn as_slice(&self) -> &[Color] {
+        &self.pixels
+    }
+
+    pub fn get_size(&self) -> UVec2 {
+        self.size
+    }
+
+    /// The pixel at a row-major index.
+    pub fn get_index(&self, index: usize) -> Color {
+        self.pixels[index]
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, color: Color) {
+        if x < self.size.x && y < self.size.y {
+            self.pixels[y * self.size.x + x] = color;
+        }
+    }
+
+    /// Sets every pixel inside of a region, skipping the parts that are out of bounds.
+    pub fn fill_rect(&mut self, region: BoundingBox, color: Color) {
+        let bounds = BoundingBox::from_size(IVec2::default(), self.size);
+        let Some(region) = region.intersection(&bounds) else {
+            return;
+        };
+
+        for y in region.get_min().y..region.get_max().y {
+            let row = y as usize * self.size.x;
+            self.pixels[(row + region.get_min().x as usize)..(row + region.get_max().x as usize)].fill(color);
+        }
     }
 
-    let min_x = bounds.get_min().x.max(0) as usize;
-    let min_y = bounds.get_min().y.max(0) as usize;
-    let blank_x = (-bounds.get_min().x).max(0) as usize;
-    let blank_y = (-bounds.get_min().y).max(0) as usize;
+    /// Writes the opaque pixels of a graphic with its top-left corner at `origin`,
+    /// skipping every pixel outside of `clip`.
+    fn blit(&mut self, origin: IVec2, content: &Graphic, clip: BoundingBox, op: RasterOp) {
+        let size = content.get_size();
+        let screen = BoundingBox::from_size(IVec2::default(), self.size);
+        let bounds = BoundingBox::from_size(origin, size);
+
+        // Nothing is left once the content is clipped to the buffer
+        let Some(visible) = bounds.intersection(&clip).and_then(|visible| visible.intersection(&screen)) else {
+            return;
+        };
 
-    let max_x = ((bounds.get_max().x) as usize).min(width-1);
-    let max_y = ((bounds.get_max().y) as usize).min(height-1);
+        for y in visible.get_min().y..visible.get_max().y {
+            let content_y = (y - origin.y) as usize;
 
-    for x in 0..(max_x-min_x) {
-        for y in 0..(max_y-min_y) {
+            for x in visible.get_min().x..visible.get_max().x {
+                let content_x = (x - origin.x) as usize;
 
-            let buffer_idx = (y+min_y)*width + (x+min_x);
-            let content_idx = (y+blank_y)*bounds.width() + (x+blank_x);
+                let buffer_idx = y as usize * self.size.x + x as usize;
+                let content_idx = content_y * size.x + content_x;
 
-            buf[buffer_idx] = content[content_idx];
+                if content.is_opaque(content_idx) {
+                    self.pixels[buffer_idx] = op.apply(self.pixels[buffer_idx], content.pixels.pixels[content_idx]);
+                }
+            }
         }
     }
 }
 
+impl Framebuffer {
+    /// The pixel at a position, or `None` if it is out of bounds.
+    pub fn get(&self, x: usize, y: usize) -> Option<Color> {
+        let size = self.get_size();
+        (x < size.x && y < size.y).then(|| self.get_index(y * size.x + x))
+    }
+
+    /// Every pixel in row-major order.
+    pub fn iter(&self) -> impl Iterator<Item = Color> + '_ {
+        (0..self.get_size().area()).map(|index| self.get_index(index))
+    }
+
+    pub fn to_colors(&self) -> Vec<Color> {
+        self.iter().collect()
+    }
+}
+
+/// The rendered pixels of an element, with an optional transparency mask.
+///
+/// Pixels where the mask is `false` are transparent and leave
+/// whatever is underneath them untouched when written.
+/// A graphic without a mask is fully opaque.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Graphic {
+    pixels: Framebuffer,
+    mask: Option<Bitplane>,
+}
+
+impl Graphic {
+    /// # Panics
+    ///
+    /// Panics if the number of pixels doesn't match the size.
+    pub fn new(size: UVec2, pixels: Vec<Color>) -> Graphic {
+        Graphic::from_framebuffer(Framebuffer::from_colors(size, pixels))
+    }
+
+    pub fn from_framebuffer(pixels: Framebuffer) -> Graphic {
+        Graphic {
+            pixels,
+            mask: None,
+        }
+    }
+
+    pub fn filled(size: UVec2, color: Color) -> Graphic {
+        Graphic::from_framebuffer(Framebuffer::new(size, color))
+    }
+
+    /// Creates a graphic where `None` pixels are transparent.
+    pub fn from_optional(size: UVec2, pixels: &[Option<Color>], background: Color) -> Graphic {
+        let mask: Vec<bool> = pixels.iter().map(Option::is_some).collect();
+        let pixels = pixels.iter().map(|pixel| pixel.unwrap_or(background)).collect();
+
+        Graphic::new(size, pixels).with_mask(mask)
+    }
+
+    pub fn with_mask(mut self, mask: Vec<bool>) -> Self {
+        self.set_mask(mask);
+        self
+    }
+
+    /// # Panics
+    ///
+    /// Panics if the mask doesn't match the size of the graphic.
+    pub fn set_mask(&mut self, mask: Vec<bool>) {
+        self.mask = Some(Bitplane::from_bools(self.get_size(), &mask));
+    }
+
+    /// Sets a mask that is already packed, where set bits are opaque.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the mask doesn't match the size of the graphic.
+    pub fn with_bitplane_mask(mut self, mask: Bitplane) -> Self {
+        assert_eq!(mask.get_size(), self.get_size(), "mask should match the size of the graphic");
+
+        self.mask = Some(mask);
+        self
+    }
+
+    pub fn get_size(&self) -> UVec2 {
+        self.pixels.get_size()
+    }
+
+    pub fn pixels(&self) -> &Framebuffer {
+        &self.pixels
+    }
+
+    /// The transparency mask, where set bits are opaque.
+    pub fn mask(&self) -> Option<&Bitplane> {
+        self.mask.as_ref()
+    }
+
+    pub fn is_opaque(&self, index: usize) -> bool {
+        self.mask.as_ref().is_none_or(|mask| mask.get_index(index))
+    }
+
+    /// The pixel at a position, or `None` if it is transparent or out of bounds.
+    pub fn get(&self, x: usize, y: usize) -> Option<Color> {
+        let size = self.get_size();
+
+        if x >= size.x || y >= size.y {
+            return None;
+        }
+
+        let index = y * size.x + x;
+        self.is_opaque(index).then(|| self.pixels.get_index(index))
+    }
+}
+
+/// How the opaque pixels of a [`Graphic`] are combined with the pixels already in the buffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum RasterOp {
+    /// Overwrite the buffer with the content.
+    #[default]
+    Replace,
+    /// Bitwise OR the content into the buffer.
+    Or,
+    /// Bitwise AND the content into the buffer.
+    And,
+    /// Bitwise XOR the content into the buffer,
+    /// so that drawing the same content twice restores the original pixels.
+    Xor,
+    /// Invert the buffer wherever the content is opaque, ignoring the content's color.
+    Invert,
+}
+
+impl RasterOp {
+    pub fn apply(&self, dst: Color, src: Color) -> Color {
+        match self {
+            RasterOp::Replace => src,
+            RasterOp::Or => dst | src,
+            RasterOp::And => dst & src,
+            RasterOp::Xor => dst ^ src,
+            RasterOp::Invert => !dst,
+        }
+    }
+
+    /// Applies the operation to 64 packed pixels at once, leaving the bits outside of `mask` untouched.
+    pub fn apply_word(&self, dst: u64, src: u64, mask: u64) -> u64 {
+        match self {
+            RasterOp::Replace => (dst & !mask) | (src & mask),
+            RasterOp::Or => dst | (src & mask),
+            RasterOp::And => dst & (src | !mask),
+            RasterOp::Xor => dst ^ (src & mask),
+            RasterOp::Invert => dst ^ mask,
+        }
+    }
+}
+
+/// Writes content into the buffer with its top-left corner at the top-left of `bounds`.
+pub fn write_all(buf: &mut Framebuffer, bounds: BoundingBox, content: &Graphic, op: RasterOp) {
+    let clip = BoundingBox::from_size(IVec2::default(), buf.get_size());
+    write_clipped(buf, bounds, clip, content, op);
+}
+
+/// Writes content into the buffer, skipping every pixel outside of `clip`.
+pub fn write_clipped(buf: &mut Framebuffer, bounds: BoundingBox, clip: BoundingBox, content: &Graphic, op: RasterOp) {
+    if bounds.size() != content.get_size() {
+        return;
+    }
+
+    buf.blit(bounds.get_min(), content, clip, op);
+}
+
 pub struct Line {
     start: IVec2,
     end: IVec2,
@@ -75,3 +339,143 @@ impl Line {
         self
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OPS: [RasterOp; 5] = [RasterOp::Replace, RasterOp::Or, RasterOp::And, RasterOp::Xor, RasterOp::Invert];
+
+    /// A color for a brightness, as close as the color mode gets to it.
+    #[cfg(all(feature = "single-color", not(any(feature = "grayscale", feature = "full-color", feature = "palette"))))]
+    fn shade(level: u8) -> Color {
+        Color::new(level >= 128)
+    }
+
+    #[cfg(all(any(feature = "grayscale", feature = "palette"), not(feature = "full-color")))]
+    fn shade(level: u8) -> Color {
+        Color::new(level)
+    }
+
+    #[cfg(feature = "full-color")]
+    fn shade(level: u8) -> Color {
+        Color::new(level, level / 2, !level)
+    }
+
+    /// A pattern of colors that differs from pixel to pixel.
+    fn pattern(size: UVec2, seed: usize) -> Vec<Color> {
+        (0..size.area()).map(|index| shade(((index * 37 + seed * 101) % 256) as u8)).collect()
+    }
+
+    /// A 70x3 framebuffer, so that single-color rows cross a word boundary.
+    fn destination() -> Framebuffer {
+        let size = UVec2::new(70, 3);
+        Framebuffer::from_colors(size, pattern(size, 1))
+    }
+
+    fn masked_graphic(size: UVec2) -> (Graphic, Vec<bool>) {
+        let mask: Vec<bool> = (0..size.area()).map(|index| index % 3 != 0).collect();
+
+        (Graphic::new(size, pattern(size, 2)).with_mask(mask.clone()), mask)
+    }
+
+    #[test]
+    fn masked_out_pixels_leave_the_destination_alone() {
+        let size = UVec2::new(66, 2);
+        let origin = IVec2::new(3, 1);
+        let (graphic, mask) = masked_graphic(size);
+
+        for op in OPS {
+            let before = destination();
+            let mut after = before.clone();
+            write_all(&mut after, BoundingBox::from_size(origin, size), &graphic, op);
+
+            for y in 0..3 {
+                for x in 0..70 {
+                    let local = IVec2::new(x as isize, y as isize) - origin;
+                    let inside = (0..size.x as isize).contains(&local.x) && (0..size.y as isize).contains(&local.y);
+                    let index = local.y * size.x as isize + local.x;
+
+                    let expected = match inside && mask[index as usize] {
+                        true => op.apply(before.get(x, y).unwrap(), graphic.pixels().get_index(index as usize)),
+                        false => before.get(x, y).unwrap(),
+                    };
+
+                    assert_eq!(after.get(x, y), Some(expected), "{op:?} at ({x}, {y})");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn xor_twice_restores_the_destination() {
+        let size = UVec2::new(66, 2);
+        let bounds = BoundingBox::from_size(IVec2::new(2, 0), size);
+        let (masked, _) = masked_graphic(size);
+        let opaque = Graphic::new(size, pattern(size, 3));
+
+        for graphic in [masked, opaque] {
+            let mut buf = destination();
+            write_all(&mut buf, bounds, &graphic, RasterOp::Xor);
+            assert_ne!(buf, destination());
+
+            write_all(&mut buf, bounds, &graphic, RasterOp::Xor);
+            assert_eq!(buf, destination());
+        }
+    }
+
+    #[test]
+    fn invert_ignores_the_source() {
+        let size = UVec2::new(70, 3);
+        let bounds = BoundingBox::from_size(IVec2::default(), size);
+
+        let mut black = destination();
+        write_all(&mut black, bounds, &Graphic::filled(size, Color::BLACK), RasterOp::Invert);
+
+        let mut patterned = destination();
+        write_all(&mut patterned, bounds, &Graphic::new(size, pattern(size, 4)), RasterOp::Invert);
+
+        let inverted: Vec<Color> = destination().iter().map(|color| !color).collect();
+        assert_eq!(black.to_colors(), inverted);
+        assert_eq!(patterned.to_colors(), inverted);
+    }
+
+    #[test]
+    fn ops_combine_with_black_and_white_as_identities() {
+        let size = UVec2::new(70, 3);
+        let bounds = BoundingBox::from_size(IVec2::default(), size);
+
+        let combine = |color: Color, op: RasterOp| {
+            let mut buf = destination();
+            write_all(&mut buf, bounds, &Graphic::filled(size, color), op);
+            buf
+        };
+
+        assert_eq!(combine(Color::BLACK, RasterOp::Or), destination());
+        assert_eq!(combine(Color::WHITE, RasterOp::And), destination());
+        assert_eq!(combine(Color::BLACK, RasterOp::Xor), destination());
+        assert_eq!(combine(Color::WHITE, RasterOp::Replace), Framebuffer::new(size, Color::WHITE));
+        assert_eq!(combine(Color::WHITE, RasterOp::Or), Framebuffer::new(size, Color::WHITE));
+        assert_eq!(combine(Color::BLACK, RasterOp::And), Framebuffer::new(size, Color::BLACK));
+    }
+
+    #[test]
+    fn clip_limits_every_op() {
+        let size = UVec2::new(70, 3);
+        let bounds = BoundingBox::from_size(IVec2::default(), size);
+        let clip = BoundingBox::new(10, 1, 65, 2);
+
+        for op in OPS {
+            let mut buf = destination();
+            write_clipped(&mut buf, bounds, clip, &Graphic::new(size, pattern(size, 5)), op);
+
+            for (index, (after, before)) in buf.iter().zip(destination().iter()).enumerate() {
+                let pos = IVec2::new((index % 70) as isize, (index / 70) as isize);
+
+                if !clip.contains(pos) {
+                    assert_eq!(after, before, "{op:?} at {pos:?}");
+                }
+            }
+        }
+    }
+}
@@ -1,20 +1,25 @@
 pub mod button;
 pub mod canvas;
+pub mod container;
 pub mod icon;
 pub mod text;
 pub mod rect;
 
+use std::cell::{Cell, Ref, RefCell};
 use std::rc::Rc;
 use button::Button;
 use canvas::Canvas;
+use container::Container;
 use icon::Icon;
 use rect::Rect;
-use slotmap::{new_key_type, SlotMap};
+use text::Text;
+use slotmap::SlotMap;
 
 use crate::app::{Application, ElementId, KeyId, MouseId, MouseMoveId, RehydrateId, ResizeId};
-use crate::draw;
+use crate::draw::{self, Framebuffer, Graphic, RasterOp};
 use crate::color::Color;
 use crate::event::{Event, KeyEvent, MouseEvent, MouseMoveEvent};
+use crate::layout::{Anchor, Layout};
 use crate::prelude::ResizeEvent;
 use crate::util::{BoundingBox, IVec2, UVec2};
 
@@ -24,7 +29,20 @@ pub struct Element {
     hidden: bool,
     offset: IVec2,
     z_index: usize,
+    raster_op: RasterOp,
+    expand: bool,
+    /// The size the element had before a layout stretched it.
+    natural_size: Option<UVec2>,
+    anchor: Option<(Anchor, usize)>,
+    pub(crate) parent: Option<ElementId>,
+    pub(crate) children: Vec<ElementId>,
     pub(crate) handlers: Handlers,
+    /// The last rendered graphic along with the content version it was rendered from.
+    cache: RefCell<Option<(Option<Graphic>, u64)>>,
+    /// Whether anything that affects the composed frame changed since it was last composed.
+    changed: Cell<bool>,
+    /// Where the element was last composed in window coordinates, after clipping.
+    drawn: Cell<Option<BoundingBox>>,
 }
 
 impl Element {
@@ -35,7 +53,16 @@ impl Element {
             hidden: false,
             offset: IVec2::default(),
             z_index: 0,
+            raster_op: RasterOp::default(),
+            expand: false,
+            natural_size: None,
+            anchor: None,
+            parent: None,
+            children: Vec::new(),
             handlers: Handlers::default(),
+            cache: RefCell::new(None),
+            changed: Cell::new(true),
+            drawn: Cell::new(None),
         }
     }
 
@@ -47,6 +74,14 @@ impl Element {
         Element::new(Rect::new(width, height, color))
     }
 
+    pub fn container(width: usize, height: usize, layout: Layout) -> Element {
+        Element::new(Container::new(width, height, layout))
+    }
+
+    pub fn text(text: impl Into<String>, color: Color) -> Element {
+        Element::new(Text::new(text, color))
+    }
+
     pub fn append_class(&mut self, class: String) {
         self.classes.push(class);
     }
@@ -65,33 +100,249 @@ impl Element {
     }
 
     pub fn set_z_index(&mut self, z_index: usize) {
-        self.z_index = z_index;
+        if self.z_index != z_index {
+            self.z_index = z_index;
+            self.changed.set(true);
+        }
+    }
+
+    pub fn get_raster_op(&self) -> RasterOp {
+        self.raster_op
+    }
+
+    /// Sets how the element is combined with whatever is drawn underneath it.
+    pub fn set_raster_op(&mut self, op: RasterOp) {
+        if self.raster_op != op {
+            self.raster_op = op;
+            self.changed.set(true);
+        }
+    }
+
+    pub fn with_raster_op(mut self, op: RasterOp) -> Self {
+        self.set_raster_op(op);
+        self
+    }
+
+    /// The element this element is positioned relative to, if any.
+    pub fn get_parent(&self) -> Option<ElementId> {
+        self.parent
+    }
+
+    /// Every child of this element, in insertion order.
+    pub fn get_children(&self) -> &[ElementId] {
+        &self.children
+    }
+
+    /// The rendered graphic of the element, or `None` if it draws nothing,
+    /// which is only redrawn when its content has changed.
+    pub fn graphic(&self) -> Option<Ref<'_, Graphic>> {
+        let version = self.inner.version();
+        let stale = self.cache
+            .borrow()
+            .as_ref()
+            .is_none_or(|(_, cached)| *cached != version);
+
+        if stale {
+            *self.cache.borrow_mut() = Some((self.inner.draw(), version));
+        }
+
+        Ref::filter_map(self.cache.borrow(), |cache| cache.as_ref().expect("graphic should be cached").0.as_ref()).ok()
     }
 
-    pub(crate) fn draw(&self, buf: &mut [Color], width: usize, height: usize) {
-        if !self.hidden {
-            let (graphic, size) = self.inner.draw();
+    /// Throws away the cached graphic after the content of the element changed.
+    fn invalidate(&mut self) {
+        *self.cache.get_mut() = None;
+        self.changed.set(true);
+    }
 
-            draw::write_all(
-                buf,
-                width,
-                height,
-                BoundingBox::from_size(self.offset, size),
-                graphic,
-            );
+    /// Whether the element changed in a way that affects the composed frame
+    /// since the last call to [`clear_changed`](`Element::clear_changed`).
+    pub(crate) fn is_changed(&self) -> bool {
+        if self.changed.get() {
+            return true;
+        }
+
+        // Canvases are drawn to through a shared context,
+        // so their content can change without going through the element.
+        self.cache
+            .borrow()
+            .as_ref()
+            .is_some_and(|(_, cached)| *cached != self.inner.version())
+    }
+
+    pub(crate) fn clear_changed(&self) {
+        self.changed.set(false);
+    }
+
+    /// Records where the element is being composed, returning where it was composed before.
+    pub(crate) fn replace_drawn(&self, bounds: Option<BoundingBox>) -> Option<BoundingBox> {
+        self.drawn.replace(bounds)
+    }
+
+    /// Draws the element with its top-left corner at `origin`,
+    /// skipping anything outside of its ancestors' `clip`.
+    pub(crate) fn draw(&self, buf: &mut Framebuffer, origin: IVec2, clip: Option<BoundingBox>) {
+        if self.hidden {
+            return;
+        }
+
+        if let Some(graphic) = self.graphic() {
+            let bounds = BoundingBox::from_size(origin, graphic.get_size());
+
+            match clip {
+                Some(clip) => draw::write_clipped(buf, bounds, clip, &graphic, self.raster_op),
+                None => draw::write_all(buf, bounds, &graphic, self.raster_op),
+            }
         }
     }
 
     pub(crate) fn update(&mut self, event: Event) {
-        self.inner.update(&event);
+        if self.inner.update(&event) {
+            self.invalidate();
+        }
     }
 
     pub fn set_hidden(&mut self, hidden: bool) {
-        self.hidden = hidden;
+        if self.hidden != hidden {
+            self.hidden = hidden;
+            self.changed.set(true);
+        }
     }
 
+    pub fn is_hidden(&self) -> bool {
+        self.hidden
+    }
+
+    /// Sets the position of the element,
+    /// relative to its parent or to the window if it has none.
     pub fn set_offset(&mut self, x: isize, y: isize) {
-        self.offset = IVec2::new(x, y);
+        let offset = IVec2::new(x, y);
+
+        if self.offset != offset {
+            self.offset = offset;
+            self.changed.set(true);
+        }
+    }
+
+    pub fn get_offset(&self) -> IVec2 {
+        self.offset
+    }
+
+    /// Keeps the element attached to a point of the window, or of its parent,
+    /// with `margin` pixels between it and the edges it is anchored to.
+    ///
+    /// The offset is recomputed whenever the window is resized,
+    /// before any `on_resize` handlers run.
+    pub fn set_anchor(&mut self, anchor: Anchor, margin: usize) {
+        if self.anchor != Some((anchor, margin)) {
+            self.anchor = Some((anchor, margin));
+            self.changed.set(true);
+        }
+    }
+
+    pub fn with_anchor(mut self, anchor: Anchor, margin: usize) -> Self {
+        self.set_anchor(anchor, margin);
+        self
+    }
+
+    pub fn clear_anchor(&mut self) {
+        if self.anchor.take().is_some() {
+            self.changed.set(true);
+        }
+    }
+
+    pub fn get_anchor(&self) -> Option<(Anchor, usize)> {
+        self.anchor
+    }
+
+    pub fn get_size(&self) -> UVec2 {
+        self.inner.get_size()
+    }
+
+    /// Resizes the element. Only rects, buttons and containers can be resized,
+    /// every other element keeps the size of its content.
+    pub fn set_size(&mut self, width: usize, height: usize) {
+        self.natural_size = None;
+        self.resize_inner(UVec2::new(width, height));
+    }
+
+    fn resize_inner(&mut self, size: UVec2) {
+        let before = self.inner.get_size();
+        self.inner.set_size(size);
+
+        // Elements that keep the size of their content ignore the new size
+        if self.inner.get_size() != before {
+            self.invalidate();
+        }
+    }
+
+    /// The size the element asks a layout for, ignoring any stretching.
+    pub(crate) fn get_natural_size(&self) -> UVec2 {
+        self.natural_size.unwrap_or_else(|| self.get_size())
+    }
+
+    /// Stretches the element while remembering the size it asked for.
+    pub(crate) fn stretch(&mut self, size: UVec2) {
+        self.natural_size = Some(self.get_natural_size());
+        self.resize_inner(size);
+    }
+
+    /// Whether a parent layout gives this element a share of any leftover space.
+    pub fn get_expand(&self) -> bool {
+        self.expand
+    }
+
+    pub fn set_expand(&mut self, expand: bool) {
+        if self.expand != expand {
+            self.expand = expand;
+            self.changed.set(true);
+        }
+    }
+
+    pub fn with_expand(mut self, expand: bool) -> Self {
+        self.set_expand(expand);
+        self
+    }
+
+    pub fn as_container(&self) -> Option<&Container> {
+        match &self.inner {
+            ElementInner::Container(container) => Some(container),
+            _ => None,
+        }
+    }
+
+    pub fn as_button(&self) -> Option<&Button> {
+        match &self.inner {
+            ElementInner::Button(button) => Some(button),
+            _ => None,
+        }
+    }
+
+    pub fn as_text(&self) -> Option<&Text> {
+        match &self.inner {
+            ElementInner::Text(text) => Some(text),
+            _ => None,
+        }
+    }
+
+    pub fn as_container_mut(&mut self) -> Option<&mut Container> {
+        // The container might be changed through the reference
+        self.invalidate();
+
+        match &mut self.inner {
+            ElementInner::Container(container) => Some(container),
+            _ => None,
+        }
+    }
+
+    pub fn as_text_mut(&mut self) -> Option<&mut Text> {
+        // The text might be changed through the reference
+        self.invalidate();
+
+        match &mut self.inner {
+            ElementInner::Text(text) => Some(text),
+            _ => None,
+        }
     }
 
     pub fn with_offset(mut self, x: isize, y: isize) -> Self {
@@ -106,7 +357,7 @@ impl Element {
             }
         }
 
-        return false;
+        false
     }
 
     pub fn get_bounding_box(&self) -> BoundingBox {
@@ -117,10 +368,21 @@ impl Element {
         self.handlers.remove_handler(id);
     }
 
+    /// Whether a position, relative to the element's parent,
+    /// lands on an opaque pixel of the element.
     pub fn intersects(&self, target: IVec2) -> bool {
-        match self.inner {
-            _ => self.get_bounding_box().intersects(target),
+        self.intersects_local(target - self.offset)
+    }
+
+    /// Whether a position relative to the element's top-left corner
+    /// lands on an opaque pixel of the element.
+    pub(crate) fn intersects_local(&self, local: IVec2) -> bool {
+        let size = self.inner.get_size();
+        if !BoundingBox::from_size(IVec2::default(), size).contains(local) {
+            return false;
         }
+
+        self.inner.is_opaque_at(local.y as usize * size.x + local.x as usize)
     }
 
     pub fn on_click(&mut self, f: impl Fn(&mut Application, ElementId, MouseEvent) + 'static) -> HandlerId {
@@ -128,6 +390,17 @@ impl Element {
         HandlerId::Mouse(id)
     }
 
+    pub fn on_mouse_move(&mut self, f: impl Fn(&mut Application, ElementId, MouseMoveEvent) + 'static) -> HandlerId {
+        let id = self.handlers.mouse_move_handlers.insert(Rc::new(f));
+        HandlerId::MouseMove(id)
+    }
+
+    /// Handles key events while this element or one of its descendants has focus.
+    pub fn on_key(&mut self, f: impl Fn(&mut Application, ElementId, KeyEvent) + 'static) -> HandlerId {
+        let id = self.handlers.key_handlers.insert(Rc::new(f));
+        HandlerId::Key(id)
+    }
+
     pub fn on_rehydrate(&mut self, f: impl Fn(&mut Application, ElementId) + 'static) -> HandlerId {
         let id = self.handlers.rehydrate_handlers.insert(Rc::new(f));
         HandlerId::Rehydrate(id)
@@ -155,20 +428,36 @@ impl From<Button> for Element {
     }
 }
 
+impl From<Container> for Element {
+    fn from(value: Container) -> Self {
+        Element::new(value)
+    }
+}
+
+impl From<Text> for Element {
+    fn from(value: Text) -> Self {
+        Element::new(value)
+    }
+}
+
 pub enum ElementInner {
     Icon(Icon),
     Canvas(Canvas),
     Button(Button),
     Rect(Rect),
+    Text(Text),
+    Container(Container),
 }
 
 impl ElementInner {
-    fn draw(&self) -> (Vec<Color>, UVec2) {
+    fn draw(&self) -> Option<Graphic> {
         match self { 
-            ElementInner::Icon(ico) => ico.draw(),
-            ElementInner::Canvas(cv) => cv.draw(),
-            ElementInner::Button(but) => but.draw(),
-            ElementInner::Rect(rec) => rec.draw(),
+            ElementInner::Icon(ico) => Some(ico.draw()),
+            ElementInner::Canvas(cv) => Some(cv.draw()),
+            ElementInner::Button(but) => Some(but.draw()),
+            ElementInner::Rect(rec) => Some(rec.draw()),
+            ElementInner::Text(txt) => Some(txt.draw()),
+            ElementInner::Container(con) => con.draw(),
         }
     }
 
@@ -180,15 +469,52 @@ impl ElementInner {
             EI::Canvas(cv) => cv.get_size(),
             EI::Button(but) => but.get_size(),
             EI::Rect(rec) => rec.get_size(),
+            EI::Text(txt) => txt.get_size(),
+            EI::Container(con) => con.get_size(),
         }
     }
 
-    fn update(&mut self, ev: &Event) {
+    fn set_size(&mut self, size: UVec2) {
+        use ElementInner as EI;
+
+        match self {
+            EI::Button(but) => but.set_size(size),
+            EI::Rect(rec) => rec.set_size(size),
+            EI::Container(con) => con.set_size(size),
+            EI::Icon(_) | EI::Canvas(_) | EI::Text(_) => {}
+        }
+    }
+
+    fn is_opaque_at(&self, index: usize) -> bool {
+        use ElementInner as EI;
+
+        match self {
+            EI::Icon(ico) => ico.is_opaque_at(index),
+            EI::Canvas(cv) => cv.is_opaque_at(index),
+            EI::Button(but) => but.is_opaque_at(index),
+            EI::Rect(_) => true,
+            EI::Text(txt) => txt.is_opaque_at(index),
+            EI::Container(con) => con.is_opaque_at(index),
+        }
+    }
+
+    /// Passes an event to the element, returning whether its content changed.
+    fn update(&mut self, ev: &Event) -> bool {
         use ElementInner as EI;
 
         match self {
             EI::Button(but) => but.update(ev),
-            _ => {}
+            _ => false,
+        }
+    }
+
+    /// A counter that changes whenever the content is changed from outside of the element.
+    fn version(&self) -> u64 {
+        use ElementInner as EI;
+
+        match self {
+            EI::Canvas(cv) => cv.version(),
+            _ => 0,
         }
     }
 }
@@ -211,12 +537,24 @@ impl From<Button> for ElementInner {
     }
 }
 
+impl From<Container> for ElementInner {
+    fn from(value: Container) -> Self {
+        ElementInner::Container(value)
+    }
+}
+
 impl From<Rect> for ElementInner {
     fn from(value: Rect) -> Self {
         ElementInner::Rect(value)
     }
 }
 
+impl From<Text> for ElementInner {
+    fn from(value: Text) -> Self {
+        ElementInner::Text(value)
+    }
+}
+
 // The key types stay private to avoid undefined behavior,
 // since [`Key`](`slotmap::Key`) types can be crafted from unknown [`u64`]'s.
 #[allow(private_interfaces)]
@@ -230,12 +568,18 @@ pub enum HandlerId {
     Resize(ResizeId)
 }
 
+pub(crate) type MouseHandler = Rc<dyn Fn(&mut Application, ElementId, MouseEvent)>;
+pub(crate) type MouseMoveHandler = Rc<dyn Fn(&mut Application, ElementId, MouseMoveEvent)>;
+pub(crate) type KeyHandler = Rc<dyn Fn(&mut Application, ElementId, KeyEvent)>;
+pub(crate) type RehydrateHandler = Rc<dyn Fn(&mut Application, ElementId)>;
+pub(crate) type ResizeHandler = Rc<dyn Fn(&mut Application, ElementId, ResizeEvent)>;
+
 pub(crate) struct Handlers {
-    pub(crate) mouse_handlers: SlotMap<MouseId, Rc<dyn Fn(&mut Application, ElementId, MouseEvent)>>,
-    pub(crate) mouse_move_handlers: SlotMap<MouseMoveId, Rc<dyn Fn(&mut Application, ElementId, MouseMoveEvent)>>,
-    pub(crate) key_handlers: SlotMap<KeyId, Rc<dyn Fn(&mut Application, ElementId, KeyEvent)>>,
-    pub(crate) rehydrate_handlers: SlotMap<RehydrateId, Rc<dyn Fn(&mut Application, ElementId)>>,
-    pub(crate) resize_handlers: SlotMap<ResizeId, Rc<dyn Fn(&mut Application, ElementId, ResizeEvent)>>,
+    pub(crate) mouse_handlers: SlotMap<MouseId, MouseHandler>,
+    pub(crate) mouse_move_handlers: SlotMap<MouseMoveId, MouseMoveHandler>,
+    pub(crate) key_handlers: SlotMap<KeyId, KeyHandler>,
+    pub(crate) rehydrate_handlers: SlotMap<RehydrateId, RehydrateHandler>,
+    pub(crate) resize_handlers: SlotMap<ResizeId, ResizeHandler>,
 }
 
 impl Handlers {
@@ -272,3 +616,65 @@ impl Default for Handlers {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::headless::Headless;
+
+    fn is_cached(app: &Headless, id: ElementId) -> bool {
+        app.get(id).cache.borrow().is_some()
+    }
+
+    /// Where the cached pixels of an element live, which moves whenever the graphic is redrawn.
+    #[cfg(all(feature = "single-color", not(any(feature = "grayscale", feature = "full-color", feature = "palette"))))]
+    fn cached_pixels(app: &Headless, id: ElementId) -> *const u64 {
+        app.get(id).graphic().expect("element should draw something").pixels().as_bitplane().row(0).as_ptr()
+    }
+
+    #[cfg(any(feature = "grayscale", feature = "full-color", feature = "palette"))]
+    fn cached_pixels(app: &Headless, id: ElementId) -> *const Color {
+        app.get(id).graphic().expect("element should draw something").pixels().as_slice().as_ptr()
+    }
+
+    #[test]
+    fn only_the_changed_element_is_redrawn() {
+        let mut app = Headless::new(40, 10);
+        let changed = app.insert_element(Element::text("a", Color::WHITE));
+        let unchanged = app.insert_element(Element::text("b", Color::WHITE).with_offset(20, 0));
+
+        app.render();
+        let pixels = cached_pixels(&app, unchanged);
+
+        app.get_mut(changed).as_text_mut().expect("element should be text").set_text("c");
+        assert!(!is_cached(&app, changed));
+        assert!(is_cached(&app, unchanged));
+
+        app.render();
+        assert!(is_cached(&app, changed));
+        assert_eq!(cached_pixels(&app, unchanged), pixels);
+    }
+
+    #[test]
+    fn stretching_an_element_that_cannot_resize_changes_nothing() {
+        let mut text = Element::text("a", Color::WHITE);
+        let size = text.get_size();
+        text.graphic();
+        text.clear_changed();
+
+        text.stretch(size + UVec2::new(10, 10));
+        assert_eq!(text.get_size(), size);
+        assert!(!text.is_changed());
+        assert!(text.cache.borrow().is_some());
+    }
+
+    #[test]
+    fn button_mask_is_stretched_with_the_button() {
+        let mut button = Element::new(Button::new(2, 1, Color::WHITE).with_mask(vec![true, false]));
+
+        button.stretch(UVec2::new(4, 2));
+        let button = button.as_button().expect("element should be a button");
+        let opaque: Vec<bool> = (0..8).map(|index| button.is_opaque_at(index)).collect();
+        assert_eq!(opaque, [true, true, false, false, true, true, false, false]);
+    }
+}
@@ -1,11 +1,14 @@
 use winit::event::{ElementState, MouseButton};
 
-use crate::{color::Color, event::Event, util::UVec2};
+use crate::{color::Color, draw::Graphic, event::Event, util::UVec2};
 
 pub struct Button {
     size: UVec2,
     depressed: bool,
     color: Color,
+    /// The mask along with the size it was made for.
+    mask: Option<(UVec2, Vec<bool>)>,
+    rounded: bool,
 }
 
 impl Button {
@@ -14,21 +17,87 @@ impl Button {
             size: UVec2::new(width, height),
             depressed: false,
             color,
+            mask: None,
+            rounded: false,
         }
     }
 
+    /// Gives the button a silhouette, where `false` pixels are transparent
+    /// and ignore clicks.
+    pub fn with_mask(mut self, mask: Vec<bool>) -> Self {
+        self.set_mask(mask);
+        self
+    }
+
+    /// # Panics
+    ///
+    /// Panics if the length of the mask doesn't match the size.
+    /// The mask is stretched along with the button when it is resized.
+    pub fn set_mask(&mut self, mask: Vec<bool>) {
+        assert_eq!(mask.len(), self.size.area(), "button mask should match its size");
+        self.mask = Some((self.size, mask));
+    }
+
+    /// Cuts the corners off of the button so that it appears rounded.
+    pub fn rounded(mut self) -> Self {
+        self.set_rounded(true);
+        self
+    }
+
+    pub fn set_rounded(&mut self, rounded: bool) {
+        self.rounded = rounded;
+    }
+
+    /// Whether the left mouse button is being held down on the button.
+    pub fn is_depressed(&self) -> bool {
+        self.depressed
+    }
+
     pub fn get_size(&self) -> UVec2 {
         self.size
     }
 
-    pub(crate) fn draw(&self) -> (Vec<Color>, UVec2) {
+    /// Resizes the button, stretching a mask set with [`set_mask`](`Button::set_mask`)
+    /// to the new size.
+    pub fn set_size(&mut self, size: UVec2) {
+        self.size = size;
+    }
+
+    pub(crate) fn is_opaque_at(&self, index: usize) -> bool {
+        let x = index % self.size.x.max(1);
+        let y = index / self.size.x.max(1);
+
+        let edge_x = x == 0 || x + 1 == self.size.x;
+        let edge_y = y == 0 || y + 1 == self.size.y;
+
+        if self.rounded && edge_x && edge_y {
+            return false;
+        }
+
+        self.mask.as_ref().is_none_or(|(size, mask)| {
+            let x = x * size.x / self.size.x.max(1);
+            let y = y * size.y / self.size.y.max(1);
+            mask.get(y * size.x + x).copied().unwrap_or(false)
+        })
+    }
+
+    pub(crate) fn draw(&self) -> Graphic {
+        let graphic = self.draw_pixels();
+
+        if self.mask.is_none() && !self.rounded {
+            return graphic;
+        }
+
+        let mask = (0..self.size.area()).map(|index| self.is_opaque_at(index)).collect();
+        graphic.with_mask(mask)
+    }
+
+    fn draw_pixels(&self) -> Graphic {
         // If the size is less than or equal to 4, the content will just be
         // the border, so we can just fill the content with the border color
         if self.size.x <= 4 || self.size.y <= 4 {
             let color = if self.depressed { self.color } else { !self.color };
-            let buf = vec![color; self.size.area()];
-
-            return (buf, self.size);
+            return Graphic::filled(self.size, color);
         }
 
         let mut buf = vec![self.color; self.size.area()];
@@ -59,18 +128,22 @@ impl Button {
             buf[x + (self.size.y-2)*self.size.x] = !self.color;
         }
 
-        (buf, self.size)
+        Graphic::new(self.size, buf)
     }
 
-    pub fn update(&mut self, ev: &Event) {
-        match ev {
-            // Update button depressed state
-            Event::Mouse(event) => {
-                if event.button == MouseButton::Left {
-                    self.depressed = event.state == ElementState::Pressed
-                }
-            },
-            _ => {}
+    /// Updates the button with an event, returning whether its appearance changed.
+    pub fn update(&mut self, ev: &Event) -> bool {
+        // Update button depressed state
+        if let Event::Mouse(event) = ev {
+            if event.button == MouseButton::Left {
+                let depressed = event.state == ElementState::Pressed;
+                let changed = self.depressed != depressed;
+
+                self.depressed = depressed;
+                return changed;
+            }
         }
+
+        false
     }
 }
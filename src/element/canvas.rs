@@ -1,40 +1,78 @@
-use std::{cell::RefCell, rc::Rc};
+use std::{cell::{Cell, RefCell}, rc::Rc};
 
-use crate::{color::Color, util::{self, BoundingBox, UVec2}};
+#[cfg(all(feature = "single-color", not(any(feature = "grayscale", feature = "full-color", feature = "palette"))))]
+use crate::draw::Framebuffer;
+use crate::{bitplane::Bitplane, color::Color, draw::Graphic, util::{BoundingBox, IVec2, UVec2}};
+
+// Single-color canvases pack their pixels into a bitplane,
+// every other mode stores one `Color` per pixel.
+#[cfg(all(feature = "single-color", not(any(feature = "grayscale", feature = "full-color", feature = "palette"))))]
+type Storage = Bitplane;
+#[cfg(any(feature = "grayscale", feature = "full-color", feature = "palette"))]
+type Storage = Vec<Color>;
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Canvas {
     size: UVec2,
-    content: Rc<RefCell<Vec<u8>>>,
+    content: Rc<RefCell<Storage>>,
+    // Cleared for every pixel that has been erased to transparent
+    mask: Rc<RefCell<Bitplane>>,
+    // Bumped on every write so that cached graphics know to redraw
+    version: Rc<Cell<u64>>,
 }
 
 impl Canvas {
-    pub(crate) fn draw(&self) -> (Vec<Color>, UVec2) {
-        #[cfg(all(feature = "single-color", not(feature = "grayscale")))]
-        let buf = {
-            let buf = util::u8_to_bool_vec(&self.content.borrow());
-            // SAFETY: layout must be the same with repr(transparent)
-            unsafe { std::mem::transmute(buf) }
-        };
+    pub fn new(width: usize, height: usize) -> Self {
+        let size = UVec2::new(width, height);
 
-        #[cfg(feature = "grayscale")]
-        let buf = {
-            let buf = self.content.borrow().clone();
-            // SAFETY: layout must be the same with repr(transparent)
-            unsafe { std::mem::transmute(buf) }
-        };
+        #[cfg(all(feature = "single-color", not(any(feature = "grayscale", feature = "full-color", feature = "palette"))))]
+        let content = Bitplane::new(size, false);
+
+        #[cfg(any(feature = "grayscale", feature = "full-color", feature = "palette"))]
+        let content = vec![Color::BLACK; size.area()];
+
+        Self {
+            size,
+            content: Rc::new(RefCell::new(content)),
+            mask: Rc::new(RefCell::new(Bitplane::new(size, true))),
+            version: Rc::new(Cell::new(0)),
+        }
+    }
+
+    pub(crate) fn draw(&self) -> Graphic {
+        #[cfg(all(feature = "single-color", not(any(feature = "grayscale", feature = "full-color", feature = "palette"))))]
+        let graphic = Graphic::from_framebuffer(Framebuffer::from_bitplane(self.content.borrow().clone()));
 
-        (buf, self.size)
+        #[cfg(any(feature = "grayscale", feature = "full-color", feature = "palette"))]
+        let graphic = Graphic::new(self.size, self.content.borrow().clone());
+
+        let mask = self.mask.borrow();
+
+        if mask.count_ones() == self.size.area() {
+            graphic
+        } else {
+            graphic.with_bitplane_mask(mask.clone())
+        }
     }
 
     pub fn get_size(&self) -> UVec2 {
         self.size
     }
 
+    pub(crate) fn version(&self) -> u64 {
+        self.version.get()
+    }
+
+    pub(crate) fn is_opaque_at(&self, index: usize) -> bool {
+        self.mask.borrow().get_index(index)
+    }
+
     pub fn get_context(&self) -> DrawContext {
         DrawContext {
             size: self.size,
             content: self.content.clone(),
+            mask: self.mask.clone(),
+            version: self.version.clone(),
         }
     }
 }
@@ -42,51 +80,161 @@ impl Canvas {
 #[derive(Debug, Clone, PartialEq)]
 pub struct DrawContext {
     size: UVec2,
-    content: Rc<RefCell<Vec<u8>>>,
+    content: Rc<RefCell<Storage>>,
+    mask: Rc<RefCell<Bitplane>>,
+    version: Rc<Cell<u64>>,
 }
 
 impl DrawContext {
-    pub fn pixel(&self, position: UVec2, value: bool) {
+    fn touch(&self) {
+        self.version.set(self.version.get().wrapping_add(1));
+    }
+
+    pub fn pixel(&self, position: UVec2, value: Color) {
         if position.x >= self.size.x || position.y >= self.size.y {
             return;
         }
 
         let pixel = position.y * self.size.x + position.x;
-        let index = pixel / 8;
-        let bit = pixel % 8;
+        set_pixel(&mut self.content.borrow_mut(), pixel, value);
+        self.mask.borrow_mut().set_index(pixel, true);
+        self.touch();
+    }
 
-        if value {
-            self.content.borrow_mut()[index] |= 1 << bit;
-        } else {
-            self.content.borrow_mut()[index] &= !(1 << bit);
-        };
+    /// Makes a pixel transparent, so that whatever is under the canvas shows through.
+    pub fn erase(&self, position: UVec2) {
+        if position.x >= self.size.x || position.y >= self.size.y {
+            return;
+        }
+
+        self.mask.borrow_mut().set(position.x, position.y, false);
+        self.touch();
     }
 
-    pub fn rect(&self, bounds: BoundingBox, value: bool) {
-        let x1 = bounds.get_min().x.max(0) as usize;
-        let y1 = bounds.get_min().y.max(0) as usize;
-        let x2 = (bounds.get_max().x.max(0) as usize).min(self.size.x-1);
-        let y2 = (bounds.get_max().y.max(0) as usize).min(self.size.y-1);
+    /// Fills the bounds, including their far edges, with a color.
+    pub fn rect(&self, bounds: BoundingBox, value: Color) {
+        let Some(region) = self.clip(bounds) else {
+            return;
+        };
+
+        self.mask.borrow_mut().fill_rect(region, true);
 
-        let mut content = self.content.borrow_mut();
+        #[cfg(all(feature = "single-color", not(any(feature = "grayscale", feature = "full-color", feature = "palette"))))]
+        self.content.borrow_mut().fill_rect(region, value == Color::WHITE);
 
-        for y in y1..=y2 {
-            let y_offset = y * self.size.x;
-            for x in x1..=x2 {
-                let pixel = y_offset + x;
-                let index = pixel / 8;
-                let bit = pixel % 8;
+        #[cfg(any(feature = "grayscale", feature = "full-color", feature = "palette"))]
+        {
+            let mut content = self.content.borrow_mut();
 
-                if value {
-                    content[index] |= 1 << bit;
-                } else {
-                    content[index] &= !(1 << bit);
-                };
+            for y in region.get_min().y as usize..region.get_max().y as usize {
+                let y_offset = y * self.size.x;
+                content[(y_offset + region.get_min().x as usize)..(y_offset + region.get_max().x as usize)].fill(value);
             }
         }
+
+        self.touch();
     }
 
-    pub fn fill(&self, value: bool) {
-        self.content.borrow_mut().fill(if value { 0xFF } else { 0x00 });
+    /// Makes every pixel in the bounds, including their far edges, transparent.
+    pub fn erase_rect(&self, bounds: BoundingBox) {
+        let Some(region) = self.clip(bounds) else {
+            return;
+        };
+
+        self.mask.borrow_mut().fill_rect(region, false);
+        self.touch();
+    }
+
+    /// The part of the canvas that bounds with inclusive far edges cover, or `None` if they miss it.
+    fn clip(&self, bounds: BoundingBox) -> Option<BoundingBox> {
+        let min = bounds.get_min();
+        let max = bounds.get_max();
+        let region = BoundingBox::new(min.x, min.y, max.x + 1, max.y + 1);
+
+        region.intersection(&BoundingBox::from_size(IVec2::default(), self.size))
+    }
+
+    /// Makes the whole canvas transparent.
+    pub fn clear(&self) {
+        self.mask.borrow_mut().fill(false);
+        self.touch();
+    }
+
+    pub fn fill(&self, value: Color) {
+        self.mask.borrow_mut().fill(true);
+
+        #[cfg(all(feature = "single-color", not(any(feature = "grayscale", feature = "full-color", feature = "palette"))))]
+        self.content.borrow_mut().fill(value == Color::WHITE);
+
+        #[cfg(any(feature = "grayscale", feature = "full-color", feature = "palette"))]
+        self.content.borrow_mut().fill(value);
+
+        self.touch();
+    }
+}
+
+#[cfg(all(feature = "single-color", not(any(feature = "grayscale", feature = "full-color", feature = "palette"))))]
+fn set_pixel(content: &mut Storage, pixel: usize, value: Color) {
+    content.set_index(pixel, value == Color::WHITE);
+}
+
+#[cfg(any(feature = "grayscale", feature = "full-color", feature = "palette"))]
+fn set_pixel(content: &mut Storage, pixel: usize, value: Color) {
+    content[pixel] = value;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Which pixels of the canvas are white, row by row.
+    fn white(canvas: &Canvas) -> Vec<bool> {
+        canvas.draw().pixels().iter().map(|color| color == Color::WHITE).collect()
+    }
+
+    #[test]
+    fn rect_includes_its_far_edges() {
+        let canvas = Canvas::new(4, 3);
+        canvas.get_context().rect(BoundingBox::new(1, 0, 2, 1), Color::WHITE);
+
+        #[rustfmt::skip]
+        assert_eq!(white(&canvas), [
+            false, true, true, false,
+            false, true, true, false,
+            false, false, false, false,
+        ]);
+    }
+
+    #[test]
+    fn rect_is_clipped_to_the_canvas() {
+        let canvas = Canvas::new(3, 2);
+        canvas.get_context().rect(BoundingBox::new(-5, 1, 10, 10), Color::WHITE);
+
+        assert_eq!(white(&canvas), [false, false, false, true, true, true]);
+    }
+
+    #[test]
+    fn rect_outside_of_the_canvas_draws_nothing() {
+        let canvas = Canvas::new(3, 2);
+        let ctx = canvas.get_context();
+
+        ctx.rect(BoundingBox::new(5, 0, 8, 1), Color::WHITE);
+        ctx.rect(BoundingBox::new(-4, -4, -2, -2), Color::WHITE);
+        Canvas::new(0, 0).get_context().rect(BoundingBox::new(0, 0, 1, 1), Color::WHITE);
+
+        assert_eq!(white(&canvas), [false; 6]);
+    }
+
+    #[test]
+    fn erase_rect_is_clipped_to_the_canvas() {
+        let canvas = Canvas::new(3, 2);
+        let ctx = canvas.get_context();
+
+        ctx.erase_rect(BoundingBox::new(2, -1, 9, 9));
+        ctx.erase_rect(BoundingBox::new(7, 7, 9, 9));
+
+        let graphic = canvas.draw();
+        let opaque: Vec<bool> = (0..6).map(|index| graphic.is_opaque(index)).collect();
+        assert_eq!(opaque, [true, true, false, true, true, false]);
     }
 }
@@ -0,0 +1,90 @@
+use crate::{color::Color, draw::Graphic, layout::Layout, util::UVec2};
+
+/// How a [`Container`] decides its own size before laying out its children.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Sizing {
+    /// Keep the size it was given, unless a parent layout fills or expands it.
+    Fixed,
+    /// Match the window whenever it is resized. Only applies to top-level containers.
+    Window,
+    /// Shrink to fit the children, plus padding and spacing.
+    Fit,
+}
+
+/// An element that positions its children with a [`Layout`].
+///
+/// Containers are transparent unless they are given a background.
+pub struct Container {
+    size: UVec2,
+    sizing: Sizing,
+    layout: Layout,
+    background: Option<Color>,
+}
+
+impl Container {
+    pub fn new(width: usize, height: usize, layout: Layout) -> Self {
+        Self {
+            size: UVec2::new(width, height),
+            sizing: Sizing::Fixed,
+            layout,
+            background: None,
+        }
+    }
+
+    /// A container that always covers the whole window.
+    pub fn window(layout: Layout) -> Self {
+        Self::new(0, 0, layout).with_sizing(Sizing::Window)
+    }
+
+    /// A container that shrinks to fit its children.
+    pub fn fit(layout: Layout) -> Self {
+        Self::new(0, 0, layout).with_sizing(Sizing::Fit)
+    }
+
+    pub fn with_sizing(mut self, sizing: Sizing) -> Self {
+        self.set_sizing(sizing);
+        self
+    }
+
+    pub fn set_sizing(&mut self, sizing: Sizing) {
+        self.sizing = sizing;
+    }
+
+    pub fn get_sizing(&self) -> Sizing {
+        self.sizing
+    }
+
+    pub fn with_background(mut self, background: Color) -> Self {
+        self.set_background(Some(background));
+        self
+    }
+
+    pub fn set_background(&mut self, background: Option<Color>) {
+        self.background = background;
+    }
+
+    pub fn get_layout(&self) -> Layout {
+        self.layout
+    }
+
+    pub fn set_layout(&mut self, layout: Layout) {
+        self.layout = layout;
+    }
+
+    pub fn get_size(&self) -> UVec2 {
+        self.size
+    }
+
+    pub fn set_size(&mut self, size: UVec2) {
+        self.size = size;
+    }
+
+    pub(crate) fn is_opaque_at(&self, _index: usize) -> bool {
+        self.background.is_some()
+    }
+
+    /// Fills the container with its background, or returns `None` if it has none.
+    pub fn draw(&self) -> Option<Graphic> {
+        self.background.map(|background| Graphic::filled(self.size, background))
+    }
+}
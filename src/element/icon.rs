@@ -1,22 +1,70 @@
 use std::fs::File;
-use std::io::{Cursor, Seek, SeekFrom};
+use std::io::{Seek, SeekFrom};
 use std::path::Path;
 
 use crate::color::Color;
+use crate::draw::Graphic;
 use crate::util::UVec2;
 
 const DIB_HEADER_OFFSET: u64 = 14;
 
 pub struct Icon {
     size: UVec2,
-    content: Vec<u8>,
+    content: Vec<Color>,
+    mask: Option<Vec<bool>>,
 }
 
 impl Icon {
+    /// Creates a fully opaque icon from row-major pixels.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of pixels doesn't match the size.
+    pub fn new(width: usize, height: usize, content: Vec<Color>) -> Icon {
+        let size = UVec2::new(width, height);
+        assert_eq!(content.len(), size.area(), "icon content should match its size");
+
+        Icon {
+            size,
+            content,
+            mask: None,
+        }
+    }
+
+    /// Creates an icon where `None` pixels are transparent.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of pixels doesn't match the size.
+    pub fn from_optional(width: usize, height: usize, pixels: &[Option<Color>]) -> Icon {
+        let size = UVec2::new(width, height);
+        assert_eq!(pixels.len(), size.area(), "icon content should match its size");
+
+        Icon {
+            size,
+            content: pixels.iter().map(|pixel| pixel.unwrap_or(Color::BLACK)).collect(),
+            mask: Some(pixels.iter().map(Option::is_some).collect()),
+        }
+    }
+
+    /// Sets the silhouette of the icon, where `false` pixels are transparent.
+    pub fn with_mask(mut self, mask: Vec<bool>) -> Self {
+        self.set_mask(mask);
+        self
+    }
+
+    /// # Panics
+    ///
+    /// Panics if the length of the mask doesn't match the size.
+    pub fn set_mask(&mut self, mask: Vec<bool>) {
+        assert_eq!(mask.len(), self.size.area(), "icon mask should match its size");
+        self.mask = Some(mask);
+    }
+
     pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Icon, BadIcon> {
         let mut file = File::open(path)?;
 
-        file.seek(SeekFrom::Start(DIB_HEADER_OFFSET));
+        file.seek(SeekFrom::Start(DIB_HEADER_OFFSET))?;
 
         todo!()
     }
@@ -25,8 +73,25 @@ impl Icon {
         self.size
     }
 
-    pub fn draw(&self) -> (Vec<Color>, UVec2) {
-        todo!();
+    pub fn get_content(&self) -> &[Color] {
+        &self.content
+    }
+
+    pub fn get_mask(&self) -> Option<&[bool]> {
+        self.mask.as_deref()
+    }
+
+    pub(crate) fn is_opaque_at(&self, index: usize) -> bool {
+        self.mask.as_ref().is_none_or(|mask| mask.get(index).copied().unwrap_or(false))
+    }
+
+    pub fn draw(&self) -> Graphic {
+        let graphic = Graphic::new(self.size, self.content.clone());
+
+        match &self.mask {
+            Some(mask) => graphic.with_mask(mask.clone()),
+            None => graphic,
+        }
     }
 }
 
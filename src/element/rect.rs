@@ -1,4 +1,4 @@
-use crate::{color::Color, util::UVec2};
+use crate::{color::Color, draw::Graphic, util::UVec2};
 
 pub struct Rect {
     size: UVec2,
@@ -13,12 +13,15 @@ impl Rect {
         }
     }
 
-    pub fn draw(&self) -> (Vec<Color>, UVec2) {
-        let buf = vec![self.color; self.size.area()];
-        (buf, self.size)
+    pub fn draw(&self) -> Graphic {
+        Graphic::filled(self.size, self.color)
     }
 
     pub fn get_size(&self) -> UVec2 {
         self.size
     }
+
+    pub fn set_size(&mut self, size: UVec2) {
+        self.size = size;
+    }
 }
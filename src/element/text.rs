@@ -1,3 +1,87 @@
+use crate::{color::Color, draw::Graphic, font::Font, util::UVec2};
+
+/// A block of text drawn with a bitmap font, sized to fit its content.
+/// Lines are separated by `'\n'`.
 pub struct Text {
     text: String,
+    font: Font,
+    color: Color,
+    /// The color behind the glyphs, or `None` to leave it transparent.
+    background: Option<Color>,
+}
+
+impl Text {
+    /// Creates text in the built-in 5x7 font with a transparent background.
+    pub fn new(text: impl Into<String>, color: Color) -> Self {
+        Self {
+            text: text.into(),
+            font: Font::default(),
+            color,
+            background: None,
+        }
+    }
+
+    pub fn get_text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn set_text(&mut self, text: impl Into<String>) {
+        self.text = text.into();
+    }
+
+    pub fn get_font(&self) -> &Font {
+        &self.font
+    }
+
+    pub fn with_font(mut self, font: Font) -> Self {
+        self.set_font(font);
+        self
+    }
+
+    pub fn set_font(&mut self, font: Font) {
+        self.font = font;
+    }
+
+    pub fn get_color(&self) -> Color {
+        self.color
+    }
+
+    pub fn set_color(&mut self, color: Color) {
+        self.color = color;
+    }
+
+    pub fn get_background(&self) -> Option<Color> {
+        self.background
+    }
+
+    /// Fills the space around the glyphs, instead of leaving it transparent.
+    pub fn with_background(mut self, background: Color) -> Self {
+        self.set_background(Some(background));
+        self
+    }
+
+    pub fn set_background(&mut self, background: Option<Color>) {
+        self.background = background;
+    }
+
+    pub fn get_size(&self) -> UVec2 {
+        self.font.measure(&self.text)
+    }
+
+    pub(crate) fn is_opaque_at(&self, index: usize) -> bool {
+        let width = self.get_size().x.max(1);
+
+        self.background.is_some() || self.font.is_set(&self.text, index % width, index / width)
+    }
+
+    pub(crate) fn draw(&self) -> Graphic {
+        let (size, pixels) = self.font.render(&self.text);
+
+        match self.background {
+            Some(background) => {
+                Graphic::new(size, pixels.iter().map(|set| if *set { self.color } else { background }).collect())
+            }
+            None => Graphic::filled(size, self.color).with_mask(pixels),
+        }
+    }
 }
@@ -0,0 +1,307 @@
+pub mod bdf;
+mod builtin;
+
+use std::{collections::HashMap, fs, path::Path, rc::Rc};
+
+use bdf::BdfError;
+
+use crate::util::{IVec2, UVec2};
+
+/// The character drawn in place of any the font doesn't have, unless the font names its own.
+const REPLACEMENT: char = '?';
+
+/// A bitmap font with proportional glyphs, such as one of the built-in fonts
+/// or one loaded from a BDF file.
+///
+/// Fonts are cheap to clone, since clones share their glyphs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Font {
+    /// How far glyphs reach above the baseline.
+    ascent: usize,
+    /// How far glyphs reach below the baseline.
+    descent: usize,
+    /// How far apart the baselines of neighboring lines are.
+    line_height: usize,
+    /// The character drawn in place of any the font doesn't have.
+    default_char: Option<char>,
+    glyphs: Rc<HashMap<char, Glyph>>,
+}
+
+/// The bitmap and metrics of a single character.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Glyph {
+    /// The size of the bounding box around the bitmap.
+    size: UVec2,
+    /// From the origin on the baseline to the bottom-left corner of the bounding box, with y going up.
+    offset: IVec2,
+    /// How far the origin moves along the line for the next glyph.
+    advance: usize,
+    /// Row-major pixels inside of the bounding box, where `true` is drawn in the text color.
+    pixels: Vec<bool>,
+}
+
+impl Glyph {
+    pub(crate) fn new(size: UVec2, offset: IVec2, advance: usize, pixels: Vec<bool>) -> Glyph {
+        assert_eq!(pixels.len(), size.area(), "glyph pixels should match its size");
+
+        Glyph { size, offset, advance, pixels }
+    }
+
+    pub fn get_size(&self) -> UVec2 {
+        self.size
+    }
+
+    /// From the origin on the baseline to the bottom-left corner of the bitmap, with y going up.
+    pub fn get_offset(&self) -> IVec2 {
+        self.offset
+    }
+
+    pub fn get_advance(&self) -> usize {
+        self.advance
+    }
+
+    pub fn pixels(&self) -> &[bool] {
+        &self.pixels
+    }
+}
+
+impl Font {
+    pub(crate) fn new(ascent: usize, descent: usize, line_height: usize, default_char: Option<char>, glyphs: HashMap<char, Glyph>) -> Font {
+        Font {
+            ascent,
+            descent,
+            line_height,
+            default_char,
+            glyphs: Rc::new(glyphs),
+        }
+    }
+
+    /// The built-in 5x7 ASCII font, with a pixel of spacing between glyphs and lines.
+    pub fn font_5x7() -> Font {
+        Font::from_rows(UVec2::new(5, 7), IVec2::new(0, 0), 6, 8, &builtin::GLYPHS_5X7)
+    }
+
+    /// The built-in 8x8 ASCII font. Its glyphs include their own spacing and a row for descenders.
+    pub fn font_8x8() -> Font {
+        Font::from_rows(UVec2::new(8, 8), IVec2::new(0, -1), 8, 8, &builtin::GLYPHS_8X8)
+    }
+
+    /// Unpacks fixed-size glyphs stored as one byte per row, starting at [`builtin::FIRST`].
+    fn from_rows<const H: usize>(size: UVec2, offset: IVec2, advance: usize, line_height: usize, rows: &[[u8; H]]) -> Font {
+        let glyphs = rows
+            .iter()
+            .enumerate()
+            .map(|(index, rows)| {
+                let c = char::from_u32(builtin::FIRST as u32 + index as u32).expect("built-in glyphs should be ASCII");
+                let pixels = rows
+                    .iter()
+                    .flat_map(|row| (0..size.x).map(move |x| row & (0x80 >> (x + 8 - size.x)) != 0))
+                    .collect();
+
+                (c, Glyph::new(size, offset, advance, pixels))
+            })
+            .collect();
+
+        let ascent = (offset.y + size.y as isize) as usize;
+        let descent = (-offset.y) as usize;
+
+        Font::new(ascent, descent, line_height, Some(REPLACEMENT), glyphs)
+    }
+
+    /// Parses a font in the X11 Bitmap Distribution Format.
+    ///
+    /// Glyphs are looked up by their encoding as a Unicode code point,
+    /// and glyphs without an encoding are left out.
+    pub fn from_bdf(source: &str) -> Result<Font, BdfError> {
+        bdf::parse(source)
+    }
+
+    /// Loads a BDF font from a file.
+    ///
+    /// Only the ASCII parts of a BDF are read, so anything else in it,
+    /// such as Latin-1 in a comment or the copyright, is replaced rather than failing the load.
+    pub fn load_bdf<P: AsRef<Path>>(path: P) -> Result<Font, BdfError> {
+        Font::from_bdf(&String::from_utf8_lossy(&fs::read(path)?))
+    }
+
+    pub fn get_ascent(&self) -> usize {
+        self.ascent
+    }
+
+    pub fn get_descent(&self) -> usize {
+        self.descent
+    }
+
+    /// How far apart the baselines of neighboring lines are.
+    pub fn get_line_height(&self) -> usize {
+        self.line_height
+    }
+
+    pub fn get_default_char(&self) -> Option<char> {
+        self.default_char
+    }
+
+    /// The glyph for a character, or `None` if the font doesn't have it.
+    pub fn glyph(&self, c: char) -> Option<&Glyph> {
+        self.glyphs.get(&c)
+    }
+
+    /// The glyph drawn for a character, which is the default character's for any the font doesn't have.
+    fn glyph_or_default(&self, c: char) -> Option<&Glyph> {
+        self.glyph(c).or_else(|| self.default_char.and_then(|c| self.glyph(c)))
+    }
+
+    /// Where the top-left corner of every glyph in a block of text goes,
+    /// relative to the top of the first line and the start of every line.
+    fn layout<'a>(&'a self, text: &'a str) -> impl Iterator<Item = (IVec2, &'a Glyph)> + 'a {
+        text.split('\n').enumerate().flat_map(move |(line, content)| {
+            let baseline = (line * self.line_height + self.ascent) as isize;
+            let mut pen = 0;
+
+            content.chars().filter_map(move |c| {
+                let glyph = self.glyph_or_default(c)?;
+                let corner = IVec2::new(
+                    pen + glyph.offset.x,
+                    baseline - glyph.offset.y - glyph.size.y as isize,
+                );
+                pen += glyph.advance as isize;
+
+                Some((corner, glyph))
+            })
+        })
+    }
+
+    /// The size of a block of text, with lines separated by `'\n'`.
+    /// Lines are as wide as the rightmost edge of their glyphs,
+    /// and the spacing below the last line isn't counted.
+    pub fn measure(&self, text: &str) -> UVec2 {
+        let width = self
+            .layout(text)
+            .map(|(corner, glyph)| corner.x + glyph.size.x as isize)
+            .max()
+            .unwrap_or(0)
+            .max(0) as usize;
+
+        let lines = text.split('\n').count();
+
+        UVec2::new(width, (lines - 1) * self.line_height + self.ascent + self.descent)
+    }
+
+    /// Whether the pixel at a position in a block of text is part of a glyph.
+    pub(crate) fn is_set(&self, text: &str, x: usize, y: usize) -> bool {
+        let target = IVec2::new(x as isize, y as isize);
+
+        self.layout(text).any(|(corner, glyph)| {
+            let local = target - corner;
+            let inside = (0..glyph.size.x as isize).contains(&local.x) && (0..glyph.size.y as isize).contains(&local.y);
+
+            inside && glyph.pixels[local.y as usize * glyph.size.x + local.x as usize]
+        })
+    }
+
+    /// Lays out a block of text, returning its size and which of its pixels are part of a glyph.
+    /// Anything that reaches outside of the measured size is cut off.
+    pub(crate) fn render(&self, text: &str) -> (UVec2, Vec<bool>) {
+        let size = self.measure(text);
+        let mut pixels = vec![false; size.area()];
+
+        for (corner, glyph) in self.layout(text) {
+            for (index, _) in glyph.pixels.iter().enumerate().filter(|(_, set)| **set) {
+                let x = corner.x + (index % glyph.size.x) as isize;
+                let y = corner.y + (index / glyph.size.x) as isize;
+
+                if (0..size.x as isize).contains(&x) && (0..size.y as isize).contains(&y) {
+                    pixels[y as usize * size.x + x as usize] = true;
+                }
+            }
+        }
+
+        (size, pixels)
+    }
+}
+
+impl Default for Font {
+    fn default() -> Self {
+        Font::font_5x7()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn corners(font: &Font, text: &str) -> Vec<IVec2> {
+        font.layout(text).map(|(corner, _)| corner).collect()
+    }
+
+    /// Draws rendered text as `#` and `.`, with a line per row.
+    fn ascii(font: &Font, text: &str) -> String {
+        let (size, pixels) = font.render(text);
+
+        pixels
+            .chunks(size.x.max(1))
+            .map(|row| row.iter().map(|set| if *set { '#' } else { '.' }).collect::<String>() + "\n")
+            .collect()
+    }
+
+    #[test]
+    fn builtin_rows_are_read_from_their_low_bits() {
+        let font = Font::font_5x7();
+        let glyph = font.glyph('1').expect("font should have digits");
+
+        // 0x04 and 0x0c, the first two rows of '1'
+        assert_eq!(glyph.pixels()[..10], [false, false, true, false, false, false, true, true, false, false]);
+    }
+
+    #[test]
+    fn glyphs_are_placed_along_the_baseline() {
+        assert_eq!(corners(&Font::font_5x7(), "ab"), [IVec2::new(0, 0), IVec2::new(6, 0)]);
+
+        // The 8x8 glyphs hang a row below the baseline, which the ascent makes room for
+        let font = Font::font_8x8();
+        assert_eq!(font.get_ascent(), 7);
+        assert_eq!(corners(&font, "ab"), [IVec2::new(0, 0), IVec2::new(8, 0)]);
+    }
+
+    #[test]
+    fn lines_are_a_line_height_apart() {
+        let font = Font::font_5x7();
+
+        assert_eq!(corners(&font, "a\nbc\n\nd"), [
+            IVec2::new(0, 0),
+            IVec2::new(0, 8),
+            IVec2::new(6, 8),
+            IVec2::new(0, 24),
+        ]);
+    }
+
+    #[test]
+    fn measure_leaves_out_the_trailing_spacing() {
+        let font = Font::font_5x7();
+
+        assert_eq!(font.measure("ab"), UVec2::new(11, 7));
+        assert_eq!(font.measure("a\nbc"), UVec2::new(11, 15));
+        assert_eq!(font.measure(""), UVec2::new(0, 7));
+        assert_eq!(font.measure("\n"), UVec2::new(0, 15));
+    }
+
+    #[test]
+    fn missing_characters_are_drawn_as_the_default() {
+        let font = Font::font_5x7();
+
+        assert_eq!(font.render("\u{e9}"), font.render("?"));
+    }
+
+    #[test]
+    fn render_draws_every_line() {
+        let font = Font::font_5x7();
+
+        assert_eq!(ascii(&font, "-\n-"), concat!(
+            ".....\n", ".....\n", ".....\n", "#####\n", ".....\n", ".....\n", ".....\n",
+            ".....\n",
+            ".....\n", ".....\n", ".....\n", "#####\n", ".....\n", ".....\n", ".....\n",
+        ));
+        assert!(font.is_set("-\n-", 0, 11));
+        assert!(!font.is_set("-\n-", 0, 10));
+    }
+}
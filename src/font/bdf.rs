@@ -0,0 +1,384 @@
+use std::{collections::HashMap, error::Error, fmt, io};
+
+use crate::util::{IVec2, UVec2};
+
+use super::{Font, Glyph, REPLACEMENT};
+
+/// Why a BDF font couldn't be loaded.
+#[derive(Debug)]
+pub enum BdfError {
+    Io(io::Error),
+    /// A statement couldn't be understood. Lines count from 1.
+    Malformed {
+        line: usize,
+        reason: &'static str,
+    },
+    /// The file ended without a statement every font needs.
+    Missing(&'static str),
+}
+
+impl fmt::Display for BdfError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BdfError::Io(err) => write!(f, "io error: {err}"),
+            BdfError::Malformed { line, reason } => write!(f, "malformed BDF on line {line}: {reason}"),
+            BdfError::Missing(keyword) => write!(f, "BDF font has no {keyword}"),
+        }
+    }
+}
+
+impl Error for BdfError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            BdfError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for BdfError {
+    fn from(err: io::Error) -> Self {
+        BdfError::Io(err)
+    }
+}
+
+/// A bounding box as BDF writes it: a size, then the offset of its bottom-left corner from the origin.
+#[derive(Debug, Clone, Copy)]
+struct Bounds {
+    size: UVec2,
+    offset: IVec2,
+}
+
+/// The statements of a BDF file with comments and blank lines skipped,
+/// split into their line number, keyword and arguments.
+struct Statements<'a> {
+    lines: std::iter::Enumerate<std::str::Lines<'a>>,
+}
+
+impl<'a> Iterator for Statements<'a> {
+    type Item = (usize, &'a str, &'a str);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (index, line) = self.lines.next()?;
+            let line = line.trim();
+            let (keyword, args) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+
+            if !keyword.is_empty() && keyword != "COMMENT" {
+                return Some((index + 1, keyword, args.trim()));
+            }
+        }
+    }
+}
+
+pub(crate) fn parse(source: &str) -> Result<Font, BdfError> {
+    let mut statements = Statements { lines: source.lines().enumerate() };
+
+    match statements.next() {
+        Some((_, "STARTFONT", _)) => {}
+        Some((line, _, _)) => return Err(BdfError::Malformed { line, reason: "file should start with STARTFONT" }),
+        None => return Err(BdfError::Missing("STARTFONT")),
+    }
+
+    let mut bounds = None;
+    let mut advance = None;
+    let mut ascent = None;
+    let mut descent = None;
+    let mut default_char = None;
+
+    // Everything before CHARS describes the font as a whole
+    loop {
+        let Some((line, keyword, args)) = statements.next() else {
+            return Err(BdfError::Missing("CHARS"));
+        };
+
+        match keyword {
+            "FONTBOUNDINGBOX" => bounds = Some(parse_bounds(line, args)?),
+            "DWIDTH" => advance = Some(parse_advance(line, args)?),
+            "FONT_ASCENT" => ascent = Some(parse_metric(line, args)?),
+            "FONT_DESCENT" => descent = Some(parse_metric(line, args)?),
+            "DEFAULT_CHAR" => default_char = Some(numbers::<1>(line, args)?[0]),
+            "CHARS" => break,
+            _ => {}
+        }
+    }
+
+    let bounds = bounds.ok_or(BdfError::Missing("FONTBOUNDINGBOX"))?;
+    let mut glyphs = HashMap::new();
+
+    loop {
+        match statements.next() {
+            Some((_, "STARTCHAR", _)) => {
+                if let Some((c, glyph)) = parse_glyph(&mut statements, bounds, advance)? {
+                    glyphs.insert(c, glyph);
+                }
+            }
+            Some((_, "ENDFONT", _)) => break,
+            Some(_) => {}
+            None => return Err(BdfError::Missing("ENDFONT")),
+        }
+    }
+
+    // Without the properties, the font's bounding box is the best guess at how far glyphs reach
+    let ascent = ascent.unwrap_or((bounds.offset.y + bounds.size.y as isize).max(0) as usize);
+    let descent = descent.unwrap_or((-bounds.offset.y).max(0) as usize);
+
+    let default_char = default_char
+        .and_then(|encoding| u32::try_from(encoding).ok())
+        .and_then(char::from_u32)
+        .filter(|c| glyphs.contains_key(c))
+        .or_else(|| glyphs.contains_key(&REPLACEMENT).then_some(REPLACEMENT));
+
+    Ok(Font::new(ascent, descent, ascent + descent, default_char, glyphs))
+}
+
+/// Parses the statements from just after a STARTCHAR up to its ENDCHAR,
+/// returning `None` for glyphs that don't map to a character.
+fn parse_glyph(statements: &mut Statements, font_bounds: Bounds, font_advance: Option<usize>) -> Result<Option<(char, Glyph)>, BdfError> {
+    let mut encoding = None;
+    let mut advance = font_advance;
+    let mut bounds = font_bounds;
+    let mut pixels = None;
+
+    loop {
+        let Some((line, keyword, args)) = statements.next() else {
+            return Err(BdfError::Missing("ENDCHAR"));
+        };
+
+        match keyword {
+            // The second number, if there is one, is the code in some other encoding
+            "ENCODING" => encoding = Some(numbers::<1>(line, args)?[0]),
+            "DWIDTH" => advance = Some(parse_advance(line, args)?),
+            "BBX" => bounds = parse_bounds(line, args)?,
+            "BITMAP" => pixels = Some(parse_bitmap(statements, line, bounds.size, font_bounds.size)?),
+            "ENDCHAR" => {
+                let encoding = encoding.ok_or(BdfError::Malformed { line, reason: "glyph should have an ENCODING" })?;
+                let advance = advance.ok_or(BdfError::Malformed { line, reason: "glyph should have a DWIDTH" })?;
+                let pixels = pixels.ok_or(BdfError::Malformed { line, reason: "glyph should have a BITMAP" })?;
+
+                // Unencoded glyphs are -1, and surrogates aren't characters
+                let c = u32::try_from(encoding).ok().and_then(char::from_u32);
+
+                return Ok(c.map(|c| (c, Glyph::new(bounds.size, bounds.offset, advance, pixels))));
+            }
+            "STARTCHAR" | "ENDFONT" => return Err(BdfError::Malformed { line, reason: "glyph should end with ENDCHAR" }),
+            _ => {}
+        }
+    }
+}
+
+/// Reads one hexadecimal row per line of the bounding box, which has to fit in the font's.
+/// Every row is padded to a whole byte, with the leftmost pixel in the highest bit.
+fn parse_bitmap(statements: &mut Statements, line: usize, size: UVec2, font_size: UVec2) -> Result<Vec<bool>, BdfError> {
+    let area = size.x
+        .checked_mul(size.y)
+        .filter(|_| size.x <= font_size.x && size.y <= font_size.y)
+        .ok_or(BdfError::Malformed { line, reason: "glyph should fit in the font's bounding box" })?;
+
+    let digits = size.x.div_ceil(8) * 2;
+    let mut pixels = Vec::with_capacity(area);
+
+    for _ in 0..size.y {
+        let Some((line, row, _)) = statements.next() else {
+            return Err(BdfError::Missing("ENDCHAR"));
+        };
+
+        if !row.is_ascii() {
+            return Err(BdfError::Malformed { line, reason: "bitmap row should be hexadecimal" });
+        }
+
+        let row = row.get(..digits).ok_or(BdfError::Malformed { line, reason: "bitmap row should cover the glyph's width" })?;
+        let bytes = (0..digits)
+            .step_by(2)
+            .map(|at| u8::from_str_radix(&row[at..at + 2], 16))
+            .collect::<Result<Vec<u8>, _>>()
+            .map_err(|_| BdfError::Malformed { line, reason: "bitmap row should be hexadecimal" })?;
+
+        pixels.extend((0..size.x).map(|x| bytes[x / 8] & (0x80 >> (x % 8)) != 0));
+    }
+
+    Ok(pixels)
+}
+
+fn parse_bounds(line: usize, args: &str) -> Result<Bounds, BdfError> {
+    let [width, height, x, y] = numbers::<4>(line, args)?;
+
+    if width < 0 || height < 0 {
+        return Err(BdfError::Malformed { line, reason: "bounding box should not have a negative size" });
+    }
+
+    Ok(Bounds {
+        size: UVec2::new(width as usize, height as usize),
+        offset: IVec2::new(x, y),
+    })
+}
+
+/// Reads the horizontal part of a DWIDTH. Fonts that are only written vertically aren't supported.
+fn parse_advance(line: usize, args: &str) -> Result<usize, BdfError> {
+    usize::try_from(numbers::<1>(line, args)?[0])
+        .map_err(|_| BdfError::Malformed { line, reason: "DWIDTH should not be negative" })
+}
+
+fn parse_metric(line: usize, args: &str) -> Result<usize, BdfError> {
+    usize::try_from(numbers::<1>(line, args)?[0])
+        .map_err(|_| BdfError::Malformed { line, reason: "font ascent and descent should not be negative" })
+}
+
+/// Reads the first `N` whitespace-separated integers of a statement, ignoring any after them.
+fn numbers<const N: usize>(line: usize, args: &str) -> Result<[isize; N], BdfError> {
+    let mut values = [0; N];
+    let mut words = args.split_whitespace();
+
+    for value in &mut values {
+        *value = words
+            .next()
+            .ok_or(BdfError::Malformed { line, reason: "statement is missing a number" })?
+            .parse()
+            .map_err(|_| BdfError::Malformed { line, reason: "statement should only have integers" })?;
+    }
+
+    Ok(values)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, process};
+
+    use super::*;
+
+    /// A font with a 4x6 bounding box that hangs a row below the baseline,
+    /// and every statement after CHARS supplied by the test.
+    fn font(chars: &str) -> Result<Font, BdfError> {
+        parse(&format!("STARTFONT 2.1\nCOMMENT test\nFONTBOUNDINGBOX 4 6 0 -1\nDWIDTH 5 0\nCHARS 1\n{chars}ENDFONT\n"))
+    }
+
+    fn malformed_line(result: Result<Font, BdfError>) -> usize {
+        match result {
+            Err(BdfError::Malformed { line, .. }) => line,
+            other => panic!("font should be malformed, was {other:?}"),
+        }
+    }
+
+    fn missing(result: Result<Font, BdfError>) -> &'static str {
+        match result {
+            Err(BdfError::Missing(keyword)) => keyword,
+            other => panic!("font should be missing a statement, was {other:?}"),
+        }
+    }
+
+    const BAR: &str = "STARTCHAR bar\nENCODING 124\nBBX 2 3 1 0\nBITMAP\n80\n40\nC0\nENDCHAR\n";
+
+    #[test]
+    fn parses_a_minimal_font() {
+        let font = font(BAR).expect("font should parse");
+        let glyph = font.glyph('|').expect("font should have the glyph");
+
+        assert_eq!(glyph.get_size(), UVec2::new(2, 3));
+        assert_eq!(glyph.get_offset(), IVec2::new(1, 0));
+        assert_eq!(glyph.pixels(), [true, false, false, true, true, true]);
+
+        // Without FONT_ASCENT and FONT_DESCENT, the bounding box is used
+        assert_eq!((font.get_ascent(), font.get_descent(), font.get_line_height()), (5, 1, 6));
+        assert_eq!(font.get_default_char(), None);
+    }
+
+    #[test]
+    fn glyphs_inherit_the_fonts_dwidth() {
+        let font = font(&format!("{BAR}STARTCHAR a\nENCODING 97\nDWIDTH 3 0\nBBX 1 1 0 0\nBITMAP\n80\nENDCHAR\n")).expect("font should parse");
+
+        assert_eq!(font.glyph('|').map(Glyph::get_advance), Some(5));
+        assert_eq!(font.glyph('a').map(Glyph::get_advance), Some(3));
+    }
+
+    #[test]
+    fn glyph_without_any_dwidth_is_malformed() {
+        let source = "STARTFONT 2.1\nFONTBOUNDINGBOX 1 1 0 0\nCHARS 1\nSTARTCHAR a\nENCODING 97\nBBX 1 1 0 0\nBITMAP\n80\nENDCHAR\nENDFONT\n";
+
+        assert_eq!(malformed_line(parse(source)), 9);
+    }
+
+    #[test]
+    fn negative_offsets_hang_below_the_baseline() {
+        let font = font("STARTCHAR g\nENCODING 103\nBBX 1 3 -1 -1\nBITMAP\n80\n00\n80\nENDCHAR\n").expect("font should parse");
+        let glyph = font.glyph('g').expect("font should have the glyph");
+
+        assert_eq!(glyph.get_offset(), IVec2::new(-1, -1));
+
+        // The glyph starts a pixel left of the origin, so the measured width leaves it out
+        assert_eq!(font.measure("g"), UVec2::new(0, 6));
+        assert_eq!(font.layout("g").map(|(corner, _)| corner).collect::<Vec<_>>(), [IVec2::new(-1, 3)]);
+    }
+
+    #[test]
+    fn default_char_falls_back_to_the_replacement_character() {
+        let question = "STARTCHAR question\nENCODING 63\nBBX 1 1 0 0\nBITMAP\n80\nENDCHAR\n";
+        let with_default = |default: u32, chars: &str| {
+            parse(&format!("STARTFONT 2.1\nFONTBOUNDINGBOX 4 6 0 -1\nDWIDTH 5 0\nDEFAULT_CHAR {default}\nCHARS 2\n{chars}ENDFONT\n"))
+                .expect("font should parse")
+        };
+
+        assert_eq!(with_default(124, &format!("{BAR}{question}")).get_default_char(), Some('|'));
+        assert_eq!(with_default(120, &format!("{BAR}{question}")).get_default_char(), Some('?'));
+        assert_eq!(with_default(120, BAR).get_default_char(), None);
+
+        // Missing characters are drawn with the default
+        let font = with_default(124, &format!("{BAR}{question}"));
+        assert_eq!(font.render("x"), font.render("|"));
+    }
+
+    #[test]
+    fn unencoded_glyphs_are_left_out() {
+        let font = font("STARTCHAR none\nENCODING -1\nBBX 1 1 0 0\nBITMAP\n80\nENDCHAR\n").expect("font should parse");
+
+        assert_eq!(font, Font::new(5, 1, 6, None, HashMap::new()));
+    }
+
+    #[test]
+    fn statements_every_font_needs_are_missing() {
+        assert_eq!(missing(parse("")), "STARTFONT");
+        assert_eq!(missing(parse("STARTFONT 2.1\nFONTBOUNDINGBOX 1 1 0 0\n")), "CHARS");
+        assert_eq!(missing(parse("STARTFONT 2.1\nCHARS 0\nENDFONT\n")), "FONTBOUNDINGBOX");
+        assert_eq!(missing(parse("STARTFONT 2.1\nFONTBOUNDINGBOX 1 1 0 0\nCHARS 0\n")), "ENDFONT");
+        assert_eq!(missing(parse("STARTFONT 2.1\nFONTBOUNDINGBOX 1 1 0 0\nCHARS 1\nSTARTCHAR a\nENCODING 97\n")), "ENDCHAR");
+        assert_eq!(missing(parse("STARTFONT 2.1\nFONTBOUNDINGBOX 1 2 0 0\nCHARS 1\nSTARTCHAR a\nBBX 1 2 0 0\nBITMAP\n80\n")), "ENDCHAR");
+    }
+
+    #[test]
+    fn malformed_statements_report_their_line() {
+        assert_eq!(malformed_line(parse("FONT test\n")), 1);
+        assert_eq!(malformed_line(parse("STARTFONT 2.1\nFONTBOUNDINGBOX 1 x 0 0\n")), 2);
+        assert_eq!(malformed_line(parse("STARTFONT 2.1\nFONTBOUNDINGBOX 1 -1 0 0\n")), 2);
+        assert_eq!(malformed_line(parse("STARTFONT 2.1\nFONT_ASCENT -2\n")), 2);
+        assert_eq!(malformed_line(font("STARTCHAR a\nENCODING 97\nBBX 1 1 0 0\nBITMAP\nZZ\nENDCHAR\n")), 10);
+        assert_eq!(malformed_line(font("STARTCHAR a\nENCODING 97\nBBX 9 1 0 0\nBITMAP\n80\nENDCHAR\n")), 9);
+        assert_eq!(malformed_line(font("STARTCHAR a\nENCODING 97\nBBX 1 1 0 0\nENDCHAR\n")), 9);
+        assert_eq!(malformed_line(font("STARTCHAR a\nENCODING 97\nSTARTCHAR b\n")), 8);
+    }
+
+    #[test]
+    fn glyphs_larger_than_the_font_are_malformed() {
+        assert_eq!(malformed_line(font("STARTCHAR a\nENCODING 97\nBBX 5 1 0 0\nBITMAP\nF8\nENDCHAR\n")), 9);
+
+        let huge = format!("STARTCHAR a\nENCODING 97\nBBX {} {} 0 0\nBITMAP\nENDCHAR\n", isize::MAX, isize::MAX);
+        assert_eq!(malformed_line(font(&huge)), 9);
+    }
+
+    #[test]
+    fn load_reports_io_errors() {
+        let result = Font::load_bdf(env::temp_dir().join("pixel-ui-missing-font.bdf"));
+
+        assert!(matches!(result, Err(BdfError::Io(_))), "{result:?}");
+    }
+
+    #[test]
+    fn load_accepts_bytes_that_arent_utf8() {
+        let path = env::temp_dir().join(format!("pixel-ui-latin1-{}.bdf", process::id()));
+        let source = [b"STARTFONT 2.1\nCOMMENT caf\xe9\nFONTBOUNDINGBOX 4 6 0 -1\nDWIDTH 5 0\nCHARS 1\n".as_slice(), BAR.as_bytes(), b"ENDFONT\n"].concat();
+        fs::write(&path, source).expect("should be able to write the font");
+
+        let result = Font::load_bdf(&path);
+        let _ = fs::remove_file(&path);
+
+        assert_eq!(result.expect("font should load").glyph('|').map(Glyph::get_advance), Some(5));
+    }
+}
@@ -0,0 +1,199 @@
+use std::ops::{Deref, DerefMut};
+
+use chrono::TimeDelta;
+
+use crate::{
+    app::Application,
+    backend::{memory::MemoryBackend, Backend, BackendEvent},
+    color::Color,
+    draw::Framebuffer,
+    react::Context,
+    util::{BoundingBox, UVec2},
+};
+
+/// An [`Application`] that presents to a [`MemoryBackend`]
+/// instead of a window, so that it can run without a display.
+///
+/// `Headless` dereferences to the [`Application`] it owns,
+/// so elements, signals and handlers are set up the same way as a windowed app.
+pub struct Headless {
+    app: Application,
+    backend: MemoryBackend,
+    damage: Vec<BoundingBox>,
+}
+
+impl Headless {
+    /// Creates an app with a framebuffer of the given size.
+    ///
+    /// Timeouts and intervals run in real time, on a timer thread that is started
+    /// when the first one is set, unless the app uses a [manual clock](`Headless::with_manual_clock`).
+    pub fn new(width: usize, height: usize) -> Headless {
+        let backend = MemoryBackend::new();
+        let mut app = Application::with_context(Context::new(backend.proxy()));
+        app.resize(width, height);
+
+        Headless {
+            damage: Vec::new(),
+            app,
+            backend,
+        }
+    }
+
+    /// Runs timeouts and intervals on a clock that only moves forward with [`advance`](`Headless::advance`),
+    /// so that they fire at the same points on every run.
+    ///
+    /// Timeouts and intervals that were already set keep running in real time.
+    pub fn with_manual_clock(mut self) -> Self {
+        self.app.use_manual_clock();
+        self
+    }
+
+    /// Moves the manual clock forward like [`Application::advance_clock`],
+    /// and runs pending input and signal updates before and after every timeout and interval.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the app isn't using a [manual clock](`Headless::with_manual_clock`).
+    pub fn advance(&mut self, delta: TimeDelta) {
+        let backend = &mut self.backend;
+        self.app.advance_clock_with(delta, |app| drain_events(backend, app));
+    }
+
+    /// How far the manual clock has been advanced.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the app isn't using a [manual clock](`Headless::with_manual_clock`).
+    pub fn manual_clock_time(&self) -> TimeDelta {
+        assert!(self.app.is_clock_manual(), "headless app should use a manual clock");
+        self.app.get_clock_time()
+    }
+
+    pub fn with_background(mut self, background: Color) -> Self {
+        self.app.set_background(background);
+        self
+    }
+
+    /// The size of the framebuffer, which is the size given to [`resize`](`Headless::resize`)
+    /// unless a [resolution](`Application::set_resolution`) is set.
+    /// Its width and height are swapped while the display is turned sideways.
+    pub fn get_size(&self) -> UVec2 {
+        self.app.get_size()
+    }
+
+    /// Changes the size of the framebuffer and notifies every resize handler,
+    /// the same as a window being resized.
+    pub fn resize(&mut self, width: usize, height: usize) {
+        self.app.resize(width, height);
+    }
+
+    /// The backend that frames are presented to.
+    pub fn backend(&self) -> &MemoryBackend {
+        &self.backend
+    }
+
+    /// Queues input for the next call to [`process_events`](`Headless::process_events`),
+    /// the same as it would arrive from a window.
+    pub fn push_event(&mut self, event: BackendEvent) {
+        self.backend.push_event(event);
+    }
+
+    /// Runs every pending input event, signal update, timeout and interval callback.
+    pub fn process_events(&mut self) {
+        drain_events(&mut self.backend, &mut self.app);
+    }
+
+    /// Processes pending events, composes every element into the framebuffer
+    /// and presents it to the backend.
+    ///
+    /// Only the regions that changed since the last frame are recomposited.
+    pub fn render(&mut self) -> &Framebuffer {
+        self.process_events();
+        self.damage = self.app
+            .present(&mut self.backend)
+            .expect("memory backend should be able to present");
+
+        self.app.frame()
+    }
+
+    /// The framebuffer produced by the last call to [`render`](`Headless::render`).
+    pub fn framebuffer(&self) -> &Framebuffer {
+        self.app.frame()
+    }
+
+    /// The regions of the framebuffer that changed in the last call to [`render`](`Headless::render`),
+    /// for targets that can refresh part of the display.
+    pub fn damage(&self) -> &[BoundingBox] {
+        &self.damage
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> Option<Color> {
+        self.app.frame().get(x, y)
+    }
+}
+
+fn drain_events(backend: &mut MemoryBackend, app: &mut Application) {
+    while let Ok(Some(event)) = backend.poll_event() {
+        app.handle_backend_event(event);
+    }
+}
+
+impl Deref for Headless {
+    type Target = Application;
+
+    fn deref(&self) -> &Self::Target {
+        &self.app
+    }
+}
+
+impl DerefMut for Headless {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.app
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc, thread, time::{Duration, Instant}};
+
+    use super::*;
+
+    #[test]
+    fn advance_fires_timeouts_scheduled_by_callbacks() {
+        let mut app = Headless::new(1, 1).with_manual_clock();
+        let fired = Rc::new(RefCell::new(Vec::new()));
+
+        let seen = fired.clone();
+        app.set_timeout(TimeDelta::milliseconds(10), move |app| {
+            seen.borrow_mut().push(app.get_clock_time());
+
+            let seen = seen.clone();
+            app.set_timeout(TimeDelta::milliseconds(5), move |app| seen.borrow_mut().push(app.get_clock_time()));
+        });
+
+        app.advance(TimeDelta::milliseconds(12));
+        assert_eq!(*fired.borrow(), [TimeDelta::milliseconds(10)]);
+        assert_eq!(app.manual_clock_time(), TimeDelta::milliseconds(12));
+
+        app.advance(TimeDelta::milliseconds(3));
+        assert_eq!(*fired.borrow(), [TimeDelta::milliseconds(10), TimeDelta::milliseconds(15)]);
+    }
+
+    #[test]
+    fn timeouts_set_before_the_manual_clock_keep_running_in_real_time() {
+        let mut app = Headless::new(1, 1);
+        let fired = Rc::new(RefCell::new(false));
+
+        let seen = fired.clone();
+        app.set_timeout(TimeDelta::milliseconds(1), move |_| *seen.borrow_mut() = true);
+        app.use_manual_clock();
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !*fired.borrow() && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(1));
+            app.process_events();
+        }
+
+        assert!(*fired.borrow(), "timeout should fire on the timer it was set on");
+    }
+}
@@ -0,0 +1,560 @@
+use crate::{
+    app::{Application, ElementId},
+    element::container::Sizing,
+    util::UVec2,
+};
+
+/// How a [`Container`](`crate::element::container::Container`) arranges its children.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LayoutKind {
+    /// Children are placed left to right.
+    Row,
+    /// Children are placed top to bottom.
+    Column,
+    /// Children are placed on top of each other. Children that expand cover the whole container.
+    Stack,
+    /// Children fill a grid with a fixed number of columns, left to right and then top to bottom.
+    /// Every cell is the same size, and children that expand cover their whole cell.
+    Grid { columns: usize },
+}
+
+/// Where children sit along an axis when there is more room than they need.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Align {
+    #[default]
+    Start,
+    Center,
+    End,
+    /// Stretch children across the whole axis.
+    /// Only elements that can be resized are stretched, which are rects, buttons and containers.
+    Fill,
+}
+
+/// Space left empty on each side of a container's content.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Padding {
+    pub left: usize,
+    pub top: usize,
+    pub right: usize,
+    pub bottom: usize,
+}
+
+impl Padding {
+    pub fn new(left: usize, top: usize, right: usize, bottom: usize) -> Padding {
+        Padding { left, top, right, bottom }
+    }
+
+    pub fn uniform(padding: usize) -> Padding {
+        Padding::new(padding, padding, padding, padding)
+    }
+
+    fn horizontal(&self) -> usize {
+        self.left + self.right
+    }
+
+    fn vertical(&self) -> usize {
+        self.top + self.bottom
+    }
+}
+
+/// The rules a [`Container`](`crate::element::container::Container`) uses
+/// to compute the offsets of its children from their sizes.
+///
+/// `justify` applies along the direction children are placed in,
+/// and `align` applies across it. Stacks and grids use `justify` horizontally
+/// and `align` vertically.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Layout {
+    kind: LayoutKind,
+    padding: Padding,
+    spacing: usize,
+    justify: Align,
+    align: Align,
+}
+
+impl Layout {
+    pub fn new(kind: LayoutKind) -> Layout {
+        Layout {
+            kind,
+            padding: Padding::default(),
+            spacing: 0,
+            justify: Align::Start,
+            align: Align::Start,
+        }
+    }
+
+    pub fn row() -> Layout {
+        Layout::new(LayoutKind::Row)
+    }
+
+    pub fn column() -> Layout {
+        Layout::new(LayoutKind::Column)
+    }
+
+    pub fn stack() -> Layout {
+        Layout::new(LayoutKind::Stack)
+    }
+
+    pub fn grid(columns: usize) -> Layout {
+        Layout::new(LayoutKind::Grid { columns: columns.max(1) })
+    }
+
+    pub fn with_padding(mut self, padding: Padding) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    /// Sets the gap between neighboring children.
+    pub fn with_spacing(mut self, spacing: usize) -> Self {
+        self.spacing = spacing;
+        self
+    }
+
+    pub fn with_justify(mut self, justify: Align) -> Self {
+        self.justify = justify;
+        self
+    }
+
+    pub fn with_align(mut self, align: Align) -> Self {
+        self.align = align;
+        self
+    }
+
+    pub fn get_kind(&self) -> LayoutKind {
+        self.kind
+    }
+
+    pub fn get_padding(&self) -> Padding {
+        self.padding
+    }
+
+    pub fn get_spacing(&self) -> usize {
+        self.spacing
+    }
+
+    pub fn get_justify(&self) -> Align {
+        self.justify
+    }
+
+    pub fn get_align(&self) -> Align {
+        self.align
+    }
+
+    /// The size needed to fit children of the given sizes.
+    fn measure(&self, sizes: &[UVec2]) -> UVec2 {
+        let count = sizes.len();
+        let gaps = self.spacing * count.saturating_sub(1);
+        let max_x = sizes.iter().map(|size| size.x).max().unwrap_or(0);
+        let max_y = sizes.iter().map(|size| size.y).max().unwrap_or(0);
+
+        let content = match self.kind {
+            LayoutKind::Row => UVec2::new(sizes.iter().map(|size| size.x).sum::<usize>() + gaps, max_y),
+            LayoutKind::Column => UVec2::new(max_x, sizes.iter().map(|size| size.y).sum::<usize>() + gaps),
+            LayoutKind::Stack => UVec2::new(max_x, max_y),
+            LayoutKind::Grid { columns } => {
+                let columns = columns.min(count).max(1);
+                let rows = count.div_ceil(columns);
+
+                UVec2::new(
+                    max_x * columns + self.spacing * (columns - 1),
+                    max_y * rows + self.spacing * rows.saturating_sub(1),
+                )
+            }
+        };
+
+        UVec2::new(content.x + self.padding.horizontal(), content.y + self.padding.vertical())
+    }
+
+    /// Computes the offset and size of every child inside a container of the given size.
+    fn arrange(&self, size: UVec2, children: &[(UVec2, bool)]) -> Vec<(isize, isize, UVec2)> {
+        let inner = UVec2::new(
+            size.x.saturating_sub(self.padding.horizontal()),
+            size.y.saturating_sub(self.padding.vertical()),
+        );
+        let left = self.padding.left as isize;
+        let top = self.padding.top as isize;
+
+        match self.kind {
+            LayoutKind::Row => self.arrange_line(inner, children, |size| (size.x, size.y), UVec2::new)
+                .into_iter()
+                .map(|(main, cross, size)| (left + main, top + cross, size))
+                .collect(),
+            LayoutKind::Column => self.arrange_line(inner, children, |size| (size.y, size.x), |main, cross| UVec2::new(cross, main))
+                .into_iter()
+                .map(|(main, cross, size)| (left + cross, top + main, size))
+                .collect(),
+            LayoutKind::Stack => children
+                .iter()
+                .map(|(child, expand)| {
+                    let (x, width) = place(fill_if(*expand, self.justify), inner.x, child.x);
+                    let (y, height) = place(fill_if(*expand, self.align), inner.y, child.y);
+
+                    (left + x, top + y, UVec2::new(width, height))
+                })
+                .collect(),
+            LayoutKind::Grid { columns } => {
+                let count = children.len();
+                let columns = columns.min(count).max(1);
+                let rows = count.div_ceil(columns).max(1);
+
+                let cell = UVec2::new(
+                    inner.x.saturating_sub(self.spacing * (columns - 1)) / columns,
+                    inner.y.saturating_sub(self.spacing * (rows - 1)) / rows,
+                );
+
+                children
+                    .iter()
+                    .enumerate()
+                    .map(|(index, (child, expand))| {
+                        let column = index % columns;
+                        let row = index / columns;
+
+                        let (x, width) = place(fill_if(*expand, self.justify), cell.x, child.x);
+                        let (y, height) = place(fill_if(*expand, self.align), cell.y, child.y);
+
+                        let cell_x = (column * (cell.x + self.spacing)) as isize;
+                        let cell_y = (row * (cell.y + self.spacing)) as isize;
+
+                        (left + cell_x + x, top + cell_y + y, UVec2::new(width, height))
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    /// Arranges children along a main axis, returning `(main, cross, size)` for each child.
+    fn arrange_line(
+        &self,
+        inner: UVec2,
+        children: &[(UVec2, bool)],
+        axes: impl Fn(UVec2) -> (usize, usize),
+        size_of: impl Fn(usize, usize) -> UVec2,
+    ) -> Vec<(isize, isize, UVec2)> {
+        let (inner_main, inner_cross) = axes(inner);
+
+        let mut mains: Vec<usize> = children.iter().map(|(child, _)| axes(*child).0).collect();
+        let gaps = self.spacing * children.len().saturating_sub(1);
+        let used = mains.iter().sum::<usize>() + gaps;
+        let mut extra = inner_main.saturating_sub(used);
+
+        // Hand out the leftover space to the children that expand,
+        // or to every child if the line is justified to fill.
+        let expanding: Vec<usize> = children
+            .iter()
+            .enumerate()
+            .filter(|(_, (_, expand))| *expand || self.justify == Align::Fill)
+            .map(|(index, _)| index)
+            .collect();
+
+        if !expanding.is_empty() && extra > 0 {
+            let share = extra / expanding.len();
+            let remainder = extra % expanding.len();
+
+            for (nth, index) in expanding.into_iter().enumerate() {
+                mains[index] += share + usize::from(nth < remainder);
+            }
+
+            extra = 0;
+        }
+
+        let mut cursor = match self.justify {
+            Align::Start | Align::Fill => 0,
+            Align::Center => extra / 2,
+            Align::End => extra,
+        } as isize;
+
+        children
+            .iter()
+            .zip(mains)
+            .map(|((child, _), main)| {
+                let (cross, cross_size) = place(self.align, inner_cross, axes(*child).1);
+                let placed = (cursor, cross, size_of(main, cross_size));
+
+                cursor += (main + self.spacing) as isize;
+                placed
+            })
+            .collect()
+    }
+}
+
+/// Stacked and grid children that expand fill the whole space they are given,
+/// since there is no leftover space along a line to share out.
+fn fill_if(expand: bool, align: Align) -> Align {
+    if expand {
+        Align::Fill
+    } else {
+        align
+    }
+}
+
+/// Positions a child of length `child` in a space of length `space`,
+/// returning its offset and its possibly stretched length.
+fn place(align: Align, space: usize, child: usize) -> (isize, usize) {
+    let free = space as isize - child as isize;
+
+    match align {
+        Align::Start => (0, child),
+        Align::Center => (free / 2, child),
+        Align::End => (free, child),
+        Align::Fill => (0, space),
+    }
+}
+
+/// A point of the window, or of the parent element, that an element stays attached to.
+///
+/// The margin pushes the element away from the edges it is anchored to,
+/// and is ignored along any axis where it is centered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Anchor {
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    Center,
+    Right,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+}
+
+impl Anchor {
+    /// Computes the offset of an element of size `size`
+    /// anchored inside an area of size `space`.
+    pub fn offset(&self, space: UVec2, size: UVec2, margin: usize) -> (isize, isize) {
+        let (horizontal, vertical) = match self {
+            Anchor::TopLeft => (Align::Start, Align::Start),
+            Anchor::Top => (Align::Center, Align::Start),
+            Anchor::TopRight => (Align::End, Align::Start),
+            Anchor::Left => (Align::Start, Align::Center),
+            Anchor::Center => (Align::Center, Align::Center),
+            Anchor::Right => (Align::End, Align::Center),
+            Anchor::BottomLeft => (Align::Start, Align::End),
+            Anchor::Bottom => (Align::Center, Align::End),
+            Anchor::BottomRight => (Align::End, Align::End),
+        };
+
+        let margin = margin as isize;
+        let along = |align: Align, space: usize, size: usize| match align {
+            Align::Start | Align::Fill => margin,
+            Align::Center => (space as isize - size as isize) / 2,
+            Align::End => space as isize - size as isize - margin,
+        };
+
+        (along(horizontal, space.x, size.x), along(vertical, space.y, size.y))
+    }
+}
+
+/// Lays out every container in the application, top-level elements first.
+pub(crate) fn update(app: &mut Application) {
+    for root in app.root_elements().to_vec() {
+        layout_subtree(app, root, true);
+    }
+}
+
+/// Moves an anchored element into place inside its parent, or the window if it has none.
+fn apply_anchor(app: &mut Application, id: ElementId) {
+    let el = app.get(id);
+
+    let Some((anchor, margin)) = el.get_anchor() else {
+        return;
+    };
+
+    let space = match el.get_parent() {
+        Some(parent) => app.get(parent).get_size(),
+        None => app.get_size(),
+    };
+
+    let (x, y) = anchor.offset(space, el.get_size(), margin);
+    app.get_mut(id).set_offset(x, y);
+}
+
+fn layout_subtree(app: &mut Application, id: ElementId, top_level: bool) {
+    // Layout containers position their own children, so anchors are only applied
+    // to elements outside of one. The anchor is resolved before the element
+    // lays out its children, since they only depend on its size.
+    let in_container = app.get(id).get_parent().is_some_and(|parent| app.get(parent).as_container().is_some());
+    if !in_container {
+        apply_anchor(app, id);
+    }
+
+    let children = app.get(id).get_children().to_vec();
+
+    let Some((layout, sizing)) = app.get(id).as_container().map(|container| (container.get_layout(), container.get_sizing())) else {
+        for child in children {
+            layout_subtree(app, child, false);
+        }
+
+        return;
+    };
+
+    if sizing == Sizing::Window && top_level {
+        let window = app.get_size();
+        app.get_mut(id).set_size(window.x, window.y);
+        apply_anchor(app, id);
+    }
+
+    // Containers that fit their content need their own children laid out
+    // before their size is known.
+    let fits = |app: &Application, child: ElementId| {
+        app.get(child).as_container().is_some_and(|container| container.get_sizing() == Sizing::Fit)
+    };
+
+    for child in children.iter().copied() {
+        if fits(app, child) {
+            layout_subtree(app, child, false);
+        }
+    }
+
+    let visible: Vec<ElementId> = children
+        .iter()
+        .copied()
+        .filter(|child| !app.get(*child).is_hidden())
+        .collect();
+
+    let sizes: Vec<(UVec2, bool)> = visible
+        .iter()
+        .map(|child| {
+            let el = app.get(*child);
+            (el.get_natural_size(), el.get_expand())
+        })
+        .collect();
+
+    if sizing == Sizing::Fit {
+        let measured = layout.measure(&sizes.iter().map(|(size, _)| *size).collect::<Vec<_>>());
+        app.get_mut(id).set_size(measured.x, measured.y);
+    }
+
+    let size = app.get(id).get_size();
+
+    for (child, (x, y, child_size)) in visible.iter().zip(layout.arrange(size, &sizes)) {
+        let el = app.get_mut(*child);
+        el.set_offset(x, y);
+
+        if el.get_size() != child_size && !fits(app, *child) {
+            app.get_mut(*child).stretch(child_size);
+        }
+    }
+
+    for child in children {
+        if !fits(app, child) {
+            layout_subtree(app, child, false);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{color::Color, element::Element, headless::Headless, util::IVec2};
+
+    #[test]
+    fn row_places_children_left_to_right_and_shares_space_with_expanding_children() {
+        let layout = Layout::row().with_spacing(2).with_padding(Padding::uniform(1));
+        let children = [(UVec2::new(3, 4), false), (UVec2::new(5, 2), true)];
+
+        assert_eq!(
+            layout.arrange(UVec2::new(20, 10), &children),
+            [(1, 1, UVec2::new(3, 4)), (6, 1, UVec2::new(13, 2))],
+        );
+        assert_eq!(layout.measure(&[UVec2::new(3, 4), UVec2::new(5, 2)]), UVec2::new(12, 6));
+    }
+
+    #[test]
+    fn row_justified_to_fill_stretches_every_child() {
+        let layout = Layout::row().with_justify(Align::Fill).with_align(Align::Fill);
+        let children = [(UVec2::new(2, 2), false), (UVec2::new(2, 2), false)];
+
+        assert_eq!(
+            layout.arrange(UVec2::new(9, 5), &children),
+            [(0, 0, UVec2::new(5, 5)), (5, 0, UVec2::new(4, 5))],
+        );
+    }
+
+    #[test]
+    fn column_places_children_top_to_bottom() {
+        let children = [(UVec2::new(4, 2), false), (UVec2::new(6, 3), false)];
+
+        assert_eq!(
+            Layout::column().with_align(Align::Center).arrange(UVec2::new(10, 10), &children),
+            [(3, 0, UVec2::new(4, 2)), (2, 2, UVec2::new(6, 3))],
+        );
+        assert_eq!(
+            Layout::column().with_justify(Align::End).arrange(UVec2::new(10, 10), &children),
+            [(0, 5, UVec2::new(4, 2)), (0, 7, UVec2::new(6, 3))],
+        );
+    }
+
+    #[test]
+    fn stack_aligns_children_on_top_of_each_other() {
+        let layout = Layout::stack().with_justify(Align::End).with_align(Align::Center);
+        let children = [(UVec2::new(4, 2), false), (UVec2::new(3, 3), true)];
+
+        assert_eq!(
+            layout.arrange(UVec2::new(10, 8), &children),
+            [(6, 3, UVec2::new(4, 2)), (0, 0, UVec2::new(10, 8))],
+        );
+    }
+
+    #[test]
+    fn grid_places_children_in_equal_cells() {
+        let layout = Layout::grid(2).with_spacing(1);
+        let children = [(UVec2::new(2, 2), false), (UVec2::new(3, 1), false), (UVec2::new(1, 1), true)];
+
+        assert_eq!(
+            layout.arrange(UVec2::new(11, 9), &children),
+            [(0, 0, UVec2::new(2, 2)), (6, 0, UVec2::new(3, 1)), (0, 5, UVec2::new(5, 4))],
+        );
+        assert_eq!(layout.measure(&children.map(|(size, _)| size)), UVec2::new(7, 5));
+    }
+
+    #[test]
+    fn every_anchor_keeps_its_margin_from_the_edges_it_is_anchored_to() {
+        let anchors = [
+            (Anchor::TopLeft, (2, 2)),
+            (Anchor::Top, (8, 2)),
+            (Anchor::TopRight, (14, 2)),
+            (Anchor::Left, (2, 4)),
+            (Anchor::Center, (8, 4)),
+            (Anchor::Right, (14, 4)),
+            (Anchor::BottomLeft, (2, 6)),
+            (Anchor::Bottom, (8, 6)),
+            (Anchor::BottomRight, (14, 6)),
+        ];
+
+        for (anchor, offset) in anchors {
+            assert_eq!(anchor.offset(UVec2::new(20, 10), UVec2::new(4, 2), 2), offset, "{anchor:?}");
+        }
+    }
+
+    #[test]
+    fn anchored_elements_follow_the_window_and_their_parent_when_resized() {
+        let mut app = Headless::new(20, 10);
+        let corner = app.insert_element(Element::rect(4, 2, Color::WHITE).with_anchor(Anchor::BottomRight, 1));
+        let parent = app.insert_element(Element::rect(10, 6, Color::BLACK));
+        let child = app.insert_child(parent, Element::rect(2, 2, Color::WHITE).with_anchor(Anchor::Center, 0));
+
+        app.render();
+        assert_eq!(app.get(corner).get_offset(), IVec2::new(15, 7));
+        assert_eq!(app.get(child).get_offset(), IVec2::new(4, 2));
+
+        app.resize(30, 20);
+        app.get_mut(parent).set_size(6, 4);
+        app.render();
+        assert_eq!(app.get(corner).get_offset(), IVec2::new(25, 17));
+        assert_eq!(app.get(child).get_offset(), IVec2::new(2, 1));
+    }
+
+    #[test]
+    fn clearing_an_anchor_leaves_the_element_where_it_was() {
+        let mut app = Headless::new(20, 10);
+        let id = app.insert_element(Element::rect(4, 2, Color::WHITE).with_anchor(Anchor::Center, 0));
+        app.render();
+
+        app.get_mut(id).clear_anchor();
+        assert!(app.needs_redraw());
+
+        app.resize(30, 20);
+        app.render();
+        assert_eq!(app.get(id).get_offset(), IVec2::new(8, 4));
+    }
+}
@@ -1,15 +1,30 @@
+pub mod bitplane;
 pub mod draw;
 pub mod element;
 pub mod event;
 pub mod react;
 pub mod color;
+pub mod dither;
+pub mod font;
 pub mod app;
+pub mod backend;
+pub mod headless;
+pub mod layout;
+pub mod recorder;
+pub mod screenshot;
+pub mod testing;
+pub mod viewport;
 mod util;
 
 pub mod prelude {
     pub use super::app::{Application, ApplicationBuilder};
+    pub use super::backend::{Backend, BackendError, BackendEvent, Frame};
+    pub use super::headless::Headless;
+    pub use super::screenshot::Screenshot;
+    pub use super::recorder::{Capture, Recording};
     pub use super::react::{WriteSignal, ReadSignal};
     pub use super::color::Color;
+    pub use super::font::Font;
     pub use super::event::*;
     pub use super::element::{
         Element,
@@ -18,7 +33,11 @@ pub mod prelude {
         icon::Icon,
         button::Button,
         rect::Rect,
+        container::{Container, Sizing},
     };
+    pub use super::layout::{Layout, LayoutKind, Align, Padding, Anchor};
+    pub use super::viewport::Rotation;
+    pub use super::util::{IVec2, UVec2, BoundingBox};
 }
 
 pub fn add(left: usize, right: usize) -> usize {
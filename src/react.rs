@@ -1,5 +1,5 @@
-use std::{any::Any, cell::RefCell, marker::PhantomData, rc::Rc};
-use chrono::{Duration, TimeDelta};
+use std::{any::Any, marker::PhantomData, mem, rc::Rc, sync::{mpsc::Sender, Arc}, time::Instant};
+use chrono::TimeDelta;
 use slotmap::{new_key_type, SlotMap};
 use timer::{Guard, Timer};
 use winit::event_loop::EventLoopProxy;
@@ -15,8 +15,13 @@ new_key_type! {
 }
 
 pub(crate) struct Context {
-    event_loop: EventLoopProxy<ProxyEvent>,
-    timer: Timer,
+    event_loop: EventSender,
+    clock: Clock,
+    /// The timer a manual clock replaced, kept alive so that the timeouts
+    /// and intervals it already scheduled still fire.
+    retired_timer: Option<Timer>,
+    /// When the context was created, which real-time clock readings count from.
+    started: Instant,
     signals: SlotMap<SignalId, Box<dyn Any>>,
     pub(crate) timeouts: SlotMap<TimeoutId, Timeout>,
     pub(crate) intervals: SlotMap<IntervalId, Interval>,
@@ -32,10 +37,12 @@ pub(crate) trait Ctx {
 }
 
 impl Context {
-    pub(crate) fn new(ev: EventLoopProxy<ProxyEvent>) -> Self {
+    pub(crate) fn new(ev: EventSender) -> Self {
         Context {
             event_loop: ev,
-            timer: Timer::new(),
+            clock: Clock::Timer(None),
+            retired_timer: None,
+            started: Instant::now(),
             signals: SlotMap::with_key(),
             timeouts: SlotMap::with_key(),
             intervals: SlotMap::with_key(),
@@ -47,6 +54,51 @@ impl Context {
         self.clean = true;
     }
 
+    /// Fires timeouts and intervals set from now on only when the clock is advanced.
+    /// Ones that were already set keep running in real time.
+    pub(crate) fn use_manual_clock(&mut self) {
+        if self.manual_clock().is_some() {
+            return;
+        }
+
+        if let Clock::Timer(Some(timer)) = mem::replace(&mut self.clock, Clock::Manual(ManualClock::default())) {
+            // Dropping the timer would stop it before anything it scheduled fires
+            if !self.timeouts.is_empty() || !self.intervals.is_empty() {
+                self.retired_timer = Some(timer);
+            }
+        }
+    }
+
+    pub(crate) fn manual_clock(&self) -> Option<&ManualClock> {
+        match &self.clock {
+            Clock::Manual(clock) => Some(clock),
+            Clock::Timer(_) => None,
+        }
+    }
+
+    pub(crate) fn manual_clock_mut(&mut self) -> Option<&mut ManualClock> {
+        match &mut self.clock {
+            Clock::Manual(clock) => Some(clock),
+            Clock::Timer(_) => None,
+        }
+    }
+
+    /// Whether any timeout or interval could still fire on its own, without the clock being advanced.
+    pub(crate) fn has_real_time_timers(&self) -> bool {
+        let running = matches!(self.clock, Clock::Timer(Some(_))) || self.retired_timer.is_some();
+
+        running && (!self.timeouts.is_empty() || !self.intervals.is_empty())
+    }
+
+    /// The time on the clock that timeouts and intervals run on,
+    /// which is how far a manual clock has been advanced, or how long the context has existed.
+    pub(crate) fn now(&self) -> TimeDelta {
+        match &self.clock {
+            Clock::Manual(clock) => clock.now(),
+            Clock::Timer(_) => TimeDelta::from_std(self.started.elapsed()).expect("elapsed time should fit in a TimeDelta"),
+        }
+    }
+
     pub fn create_signal<T: Any + 'static>(&mut self, init: T) -> (ReadSignal<T>, WriteSignal<T>) {
         let content = Box::new(init) as Box<dyn Any>;
         let id = self.signals.insert(content);
@@ -56,11 +108,19 @@ impl Context {
 
     pub fn set_timeout(&mut self, delay: TimeDelta, f: impl FnOnce(&mut Application) + 'static) -> TimeoutId {
         self.timeouts.insert_with_key(|id| {
-            let proxy = self.event_loop.clone();
+            let handle = match &mut self.clock {
+                Clock::Timer(timer) => {
+                    let proxy = self.event_loop.clone();
 
-            let handle = self.timer.schedule_with_delay(delay, move || {
-                proxy.send_event(ProxyEvent::Timeout(id)).expect("event loop should still be active");
-            });
+                    Some(timer.get_or_insert_with(Timer::new).schedule_with_delay(delay, move || {
+                        proxy.send(ProxyEvent::Timeout(id));
+                    }))
+                }
+                Clock::Manual(clock) => {
+                    clock.schedule(delay, ProxyEvent::Timeout(id), None);
+                    None
+                }
+            };
 
             Timeout {
                 f: Box::new(f),
@@ -71,15 +131,27 @@ impl Context {
 
     pub fn clear_timeout(&mut self, id: TimeoutId) {
         let _ = self.timeouts.remove(id);
+
+        if let Clock::Manual(clock) = &mut self.clock {
+            clock.cancel(ProxyEvent::Timeout(id));
+        }
     }
 
     pub fn set_interval(&mut self, delay: TimeDelta, f: impl Fn(&mut Application) + 'static) -> IntervalId {
         self.intervals.insert_with_key(|id| {
-            let proxy = self.event_loop.clone();
+            let handle = match &mut self.clock {
+                Clock::Timer(timer) => {
+                    let proxy = self.event_loop.clone();
 
-            let handle = self.timer.schedule_repeating(delay, move || {
-                proxy.send_event(ProxyEvent::Interval(id)).expect("event loop should still be active");
-            });
+                    Some(timer.get_or_insert_with(Timer::new).schedule_repeating(delay, move || {
+                        proxy.send(ProxyEvent::Interval(id));
+                    }))
+                }
+                Clock::Manual(clock) => {
+                    clock.schedule(delay, ProxyEvent::Interval(id), Some(delay));
+                    None
+                }
+            };
 
             Interval {
                 f: Rc::new(f),
@@ -90,6 +162,10 @@ impl Context {
 
     pub fn clear_interval(&mut self, id: IntervalId) {
         let _ = self.intervals.remove(id);
+
+        if let Clock::Manual(clock) = &mut self.clock {
+            clock.cancel(ProxyEvent::Interval(id));
+        }
     }
 
     fn try_with<T: 'static, R>(&self, id: SignalId, f: impl FnOnce(&T) -> R) -> R {
@@ -105,18 +181,130 @@ impl Context {
 
         if self.clean {
             self.clean = false;
-            self.event_loop.send_event(ProxyEvent::React).expect("event loop should still be active");
+            self.event_loop.send(ProxyEvent::React);
         }
     }
 }
 
+/// Wakes the event loop to run a timeout, an interval or pending signal updates.
+///
+/// A [`Backend`](`crate::backend::Backend`) hands these back to the application unchanged,
+/// on the thread the application runs on.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub(crate) enum ProxyEvent {
+pub enum ProxyEvent {
     Timeout(TimeoutId),
     Interval(IntervalId),
     React,
 }
 
+/// Where a [`Context`] delivers its [`ProxyEvent`]s, which is decided by the backend.
+///
+/// Events are sent from the timer thread as well as the application's own,
+/// so the sender has to be shareable between threads.
+#[derive(Clone)]
+pub struct EventSender(Arc<dyn Fn(ProxyEvent) + Send + Sync>);
+
+impl EventSender {
+    pub fn new(send: impl Fn(ProxyEvent) + Send + Sync + 'static) -> Self {
+        Self(Arc::new(send))
+    }
+
+    pub fn send(&self, event: ProxyEvent) {
+        (self.0)(event);
+    }
+}
+
+impl From<EventLoopProxy<ProxyEvent>> for EventSender {
+    fn from(proxy: EventLoopProxy<ProxyEvent>) -> Self {
+        EventSender::new(move |event| proxy.send_event(event).expect("event loop should still be active"))
+    }
+}
+
+impl From<Sender<ProxyEvent>> for EventSender {
+    fn from(sender: Sender<ProxyEvent>) -> Self {
+        EventSender::new(move |event| sender.send(event).expect("event receiver should still be active"))
+    }
+}
+
+/// Decides when timeouts and intervals fire.
+pub(crate) enum Clock {
+    /// A background thread that fires them in real time,
+    /// which is only started once the first one is set.
+    Timer(Option<Timer>),
+    /// A clock that only moves forward when it is advanced.
+    Manual(ManualClock),
+}
+
+/// The shortest interval a [`ManualClock`] repeats at,
+/// so that advancing it can't loop forever.
+const MIN_MANUAL_INTERVAL: TimeDelta = TimeDelta::milliseconds(1);
+
+#[derive(Default)]
+pub(crate) struct ManualClock {
+    now: TimeDelta,
+    /// Counts up with every scheduled event, to fire events that are due together
+    /// in the order they were scheduled.
+    sequence: u64,
+    scheduled: Vec<Scheduled>,
+}
+
+struct Scheduled {
+    due: TimeDelta,
+    sequence: u64,
+    event: ProxyEvent,
+    repeat: Option<TimeDelta>,
+}
+
+impl ManualClock {
+    /// How far the clock has been advanced since it was created.
+    pub(crate) fn now(&self) -> TimeDelta {
+        self.now
+    }
+
+    fn schedule(&mut self, delay: TimeDelta, event: ProxyEvent, repeat: Option<TimeDelta>) {
+        self.sequence += 1;
+        self.scheduled.push(Scheduled {
+            due: self.now + delay.max(TimeDelta::zero()),
+            sequence: self.sequence,
+            event,
+            repeat: repeat.map(|repeat| repeat.max(MIN_MANUAL_INTERVAL)),
+        });
+    }
+
+    fn cancel(&mut self, event: ProxyEvent) {
+        self.scheduled.retain(|scheduled| scheduled.event != event);
+    }
+
+    /// Takes the earliest event that is due by `until`, moving the clock to when it is due.
+    /// Intervals are scheduled again for their next repeat.
+    pub(crate) fn next_due(&mut self, until: TimeDelta) -> Option<ProxyEvent> {
+        let (index, _) = self.scheduled
+            .iter()
+            .enumerate()
+            .filter(|(_, scheduled)| scheduled.due <= until)
+            .min_by_key(|(_, scheduled)| (scheduled.due, scheduled.sequence))?;
+
+        let scheduled = self.scheduled.swap_remove(index);
+        self.now = scheduled.due;
+
+        if let Some(repeat) = scheduled.repeat {
+            self.sequence += 1;
+            self.scheduled.push(Scheduled {
+                due: scheduled.due + repeat,
+                sequence: self.sequence,
+                ..scheduled
+            });
+        }
+
+        Some(scheduled.event)
+    }
+
+    /// Moves the clock forward without firing anything, once every due event has been taken.
+    pub(crate) fn set_now(&mut self, now: TimeDelta) {
+        self.now = self.now.max(now);
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct WriteSignal<T> {
     id: SignalId,
@@ -132,6 +320,9 @@ impl<T> WriteSignal<T> {
     }
 }
 
+// `Ctx` stays crate-private so that it cannot be implemented on external types,
+// which means the bounds below cannot be named outside of the crate.
+#[allow(private_bounds)]
 impl <T: 'static> WriteSignal<T> {
     pub fn set<A: Ctx>(&self, app: &mut A, data: T) {
         app.get_ctx_mut().update(self.id, |sig| *sig = data);
@@ -157,6 +348,7 @@ impl<T> ReadSignal<T> {
     }
 }
 
+#[allow(private_bounds)]
 impl<T: Clone + 'static> ReadSignal<T> {
     pub fn get<A: Ctx>(&self, app: &A) -> T {
         app.get_ctx().try_with(self.id, T::clone)
@@ -169,10 +361,92 @@ impl<T: Clone + 'static> ReadSignal<T> {
 
 pub(crate) struct Timeout {
     pub(crate) f: Box<dyn FnOnce(&mut Application) + 'static>,
-    __guard: Guard,
+    // `None` when the timeout runs on a manual clock
+    __guard: Option<Guard>,
 }
 
 pub(crate) struct Interval {
     pub(crate) f: Rc<dyn Fn(&mut Application) + 'static>,
-    __guard: Guard,
+    __guard: Option<Guard>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn timeouts(count: usize) -> Vec<ProxyEvent> {
+        let mut ids = SlotMap::<TimeoutId, ()>::with_key();
+        (0..count).map(|_| ProxyEvent::Timeout(ids.insert(()))).collect()
+    }
+
+    fn drain(clock: &mut ManualClock, until: TimeDelta) -> Vec<(ProxyEvent, TimeDelta)> {
+        std::iter::from_fn(|| clock.next_due(until).map(|event| (event, clock.now()))).collect()
+    }
+
+    #[test]
+    fn advance_fires_events_in_the_order_they_are_due() {
+        let events = timeouts(3);
+        let interval = ProxyEvent::Interval(SlotMap::<IntervalId, ()>::with_key().insert(()));
+        let mut clock = ManualClock::default();
+        let ms = TimeDelta::milliseconds;
+
+        clock.schedule(ms(30), events[0], None);
+        clock.schedule(ms(10), events[1], None);
+        clock.schedule(ms(10), interval, Some(ms(10)));
+        clock.schedule(ms(10), events[2], None);
+
+        // Events due together fire in the order they were scheduled, and intervals go to the back of the line
+        assert_eq!(
+            drain(&mut clock, ms(30)),
+            [
+                (events[1], ms(10)),
+                (interval, ms(10)),
+                (events[2], ms(10)),
+                (interval, ms(20)),
+                (events[0], ms(30)),
+                (interval, ms(30)),
+            ],
+        );
+    }
+
+    #[test]
+    fn advance_stops_at_events_that_are_not_due_yet() {
+        let events = timeouts(2);
+        let mut clock = ManualClock::default();
+
+        clock.schedule(TimeDelta::milliseconds(5), events[0], None);
+        clock.schedule(TimeDelta::milliseconds(15), events[1], None);
+
+        assert_eq!(drain(&mut clock, TimeDelta::milliseconds(10)), [(events[0], TimeDelta::milliseconds(5))]);
+
+        clock.set_now(TimeDelta::milliseconds(10));
+        assert_eq!(clock.now(), TimeDelta::milliseconds(10));
+        assert_eq!(drain(&mut clock, TimeDelta::milliseconds(15)), [(events[1], TimeDelta::milliseconds(15))]);
+    }
+
+    #[test]
+    fn cancelled_events_never_fire() {
+        let events = timeouts(2);
+        let mut clock = ManualClock::default();
+
+        clock.schedule(TimeDelta::milliseconds(5), events[0], None);
+        clock.schedule(TimeDelta::milliseconds(5), events[1], None);
+        clock.cancel(events[0]);
+
+        assert_eq!(drain(&mut clock, TimeDelta::seconds(1)), [(events[1], TimeDelta::milliseconds(5))]);
+    }
+
+    #[test]
+    fn timer_thread_only_starts_for_real_time_timeouts() {
+        let mut ctx = Context::new(EventSender::new(|_| {}));
+        assert!(matches!(ctx.clock, Clock::Timer(None)));
+
+        ctx.set_timeout(TimeDelta::hours(1), |_| {});
+        assert!(matches!(ctx.clock, Clock::Timer(Some(_))));
+
+        let mut ctx = Context::new(EventSender::new(|_| {}));
+        ctx.use_manual_clock();
+        ctx.set_timeout(TimeDelta::hours(1), |_| {});
+        assert!(ctx.retired_timer.is_none());
+    }
 }
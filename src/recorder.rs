@@ -0,0 +1,642 @@
+use std::{collections::HashMap, fs::File, io::{self, BufWriter, Write}, path::Path};
+
+use chrono::TimeDelta;
+
+#[cfg(all(feature = "grayscale", not(any(feature = "full-color", feature = "palette"))))]
+use crate::dither;
+use crate::{react::IntervalId, screenshot::Screenshot, util::UVec2};
+
+/// The largest code an LZW stream in a GIF can hold.
+const MAX_LZW_CODE: u16 = 4095;
+
+/// When an application captures frames while it is being recorded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Capture {
+    /// After every frame presented to the backend.
+    EveryPresent,
+    /// At a fixed rate on the same clock as timeouts and intervals,
+    /// whether anything changed or not.
+    Every(TimeDelta),
+}
+
+/// A recording that is still capturing frames.
+pub(crate) struct Recorder {
+    pub(crate) capture: Capture,
+    /// The clock time the recording started at.
+    pub(crate) start: TimeDelta,
+    /// The interval capturing frames, for fixed-rate recordings.
+    pub(crate) interval: Option<IntervalId>,
+    pub(crate) frames: Vec<RecordedFrame>,
+}
+
+impl Recorder {
+    pub(crate) fn new(capture: Capture, start: TimeDelta) -> Self {
+        Self { capture, start, interval: None, frames: Vec::new() }
+    }
+
+    pub(crate) fn push(&mut self, now: TimeDelta, screenshot: Screenshot) {
+        self.frames.push(RecordedFrame {
+            time: now - self.start,
+            screenshot,
+        });
+    }
+
+    pub(crate) fn finish(self, now: TimeDelta) -> Recording {
+        Recording {
+            frames: self.frames,
+            duration: now - self.start,
+        }
+    }
+}
+
+/// A frame captured during a recording.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordedFrame {
+    time: TimeDelta,
+    screenshot: Screenshot,
+}
+
+impl RecordedFrame {
+    /// How long after the start of the recording the frame was captured.
+    pub fn get_time(&self) -> TimeDelta {
+        self.time
+    }
+
+    pub fn screenshot(&self) -> &Screenshot {
+        &self.screenshot
+    }
+}
+
+/// Every frame captured between starting and stopping a recording,
+/// which can be saved as an animated GIF.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Recording {
+    frames: Vec<RecordedFrame>,
+    duration: TimeDelta,
+}
+
+impl Recording {
+    pub fn frames(&self) -> &[RecordedFrame] {
+        &self.frames
+    }
+
+    /// How long the recording ran for, which is how long the last frame is shown.
+    pub fn get_duration(&self) -> TimeDelta {
+        self.duration
+    }
+
+    /// Writes the recording as a looping animated GIF.
+    ///
+    /// Every frame only stores the rectangle that changed since the one before it,
+    /// with unchanged pixels inside of it left transparent.
+    /// Frames identical to the one before them are dropped, and frames shown for less than
+    /// a hundredth of a second are replaced by the next one, since GIF delays can't be any shorter.
+    pub fn write_gif<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        let size = self.frames.iter().fold(UVec2::default(), |size, frame| {
+            let frame_size = frame.screenshot.get_size();
+            UVec2::new(size.x.max(frame_size.x), size.y.max(frame_size.y))
+        });
+
+        if size.x > u16::MAX as usize || size.y > u16::MAX as usize {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "recording should fit in 65535x65535 pixels"));
+        }
+
+        let palette = GifPalette::new(&self.frames);
+
+        out.write_all(b"GIF89a")?;
+        out.write_all(&(size.x as u16).to_le_bytes())?;
+        out.write_all(&(size.y as u16).to_le_bytes())?;
+        out.write_all(&[0xf0 | palette.size_bits(), 0, 0])?;
+        out.write_all(&palette.table())?;
+
+        // Loop forever
+        out.write_all(b"\x21\xff\x0bNETSCAPE2.0\x03\x01\x00\x00\x00")?;
+
+        // Each frame is shown from the hundredth of a second it was captured in until the next one
+        let mut shown: Vec<(i64, &Screenshot)> = Vec::new();
+
+        for frame in &self.frames {
+            let start = centiseconds(frame.time);
+
+            match shown.last() {
+                Some((_, last)) if **last == frame.screenshot => continue,
+                Some((last_start, _)) if *last_start >= start => {
+                    shown.pop();
+                }
+                _ => {}
+            }
+
+            shown.push((start, &frame.screenshot));
+        }
+
+        let mut previous: Option<(UVec2, Vec<u8>)> = None;
+
+        for (index, (start, screenshot)) in shown.iter().enumerate() {
+            let end = shown.get(index + 1).map_or(centiseconds(self.duration), |(next, _)| *next);
+            let delay = (end - start).clamp(0, u16::MAX as i64) as u16;
+
+            let frame_size = screenshot.get_size();
+            let indices: Vec<u8> = screenshot.rgb().iter().map(|rgb| palette.index(*rgb)).collect();
+
+            let (x, y, width, height, pixels) = match &previous {
+                Some((previous_size, previous)) if *previous_size == frame_size => {
+                    match changed_bounds(frame_size, previous, &indices) {
+                        Some((x, y, width, height)) => {
+                            let pixels = (y..y + height)
+                                .flat_map(|row| (x..x + width).map(move |column| row * frame_size.x + column))
+                                .map(|at| if previous[at] == indices[at] { palette.transparent() } else { indices[at] })
+                                .collect();
+
+                            (x, y, width, height, pixels)
+                        }
+                        // Only the palette mapping changed, so the frame before it stays up for longer
+                        None => (0, 0, 1, 1, vec![palette.transparent()]),
+                    }
+                }
+                _ => (0, 0, frame_size.x, frame_size.y, indices.clone()),
+            };
+
+            // Leave each frame in place for the next one to draw over, with a transparent index
+            out.write_all(&[0x21, 0xf9, 0x04, 0x05])?;
+            out.write_all(&delay.to_le_bytes())?;
+            out.write_all(&[palette.transparent(), 0])?;
+
+            out.write_all(&[0x2c])?;
+            for value in [x, y, width, height] {
+                out.write_all(&(value as u16).to_le_bytes())?;
+            }
+            out.write_all(&[0])?;
+
+            write_lzw(out, palette.min_code_size(), &pixels)?;
+
+            previous = Some((frame_size, indices));
+        }
+
+        out.write_all(&[0x3b])
+    }
+
+    /// Saves the recording as an animated GIF.
+    pub fn save_gif<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut out = BufWriter::new(File::create(path)?);
+
+        self.write_gif(&mut out)?;
+        out.flush()
+    }
+}
+
+/// Rounds a time to hundredths of a second, which is what GIF delays count in.
+/// Delays are taken between rounded times so that rounding doesn't add up over the recording.
+fn centiseconds(time: TimeDelta) -> i64 {
+    (time.num_milliseconds() + 5).div_euclid(10)
+}
+
+/// The smallest rectangle holding every pixel that differs, as `(x, y, width, height)`.
+fn changed_bounds(size: UVec2, previous: &[u8], current: &[u8]) -> Option<(usize, usize, usize, usize)> {
+    let mut bounds: Option<(usize, usize, usize, usize)> = None;
+
+    for (at, _) in previous.iter().zip(current).enumerate().filter(|(_, (before, after))| before != after) {
+        let (x, y) = (at % size.x, at / size.x);
+
+        bounds = Some(match bounds {
+            Some((x1, y1, x2, y2)) => (x1.min(x), y1.min(y), x2.max(x), y2.max(y)),
+            None => (x, y, x, y),
+        });
+    }
+
+    bounds.map(|(x1, y1, x2, y2)| (x1, y1, x2 - x1 + 1, y2 - y1 + 1))
+}
+
+/// The global color table of a GIF, with the last entry kept for transparency.
+struct GifPalette {
+    colors: Vec<u32>,
+    /// Maps colors straight to their entry when every color in the recording fits in the table.
+    exact: Option<HashMap<u32, u8>>,
+}
+
+impl GifPalette {
+    fn new(frames: &[RecordedFrame]) -> Self {
+        let mut colors: Vec<u32> = Vec::new();
+        let mut exact = HashMap::new();
+
+        for rgb in frames.iter().flat_map(|frame| frame.screenshot.rgb()) {
+            if exact.contains_key(rgb) {
+                continue;
+            }
+
+            if colors.len() == 255 {
+                return GifPalette { colors: fallback_palette(), exact: None };
+            }
+
+            exact.insert(*rgb, colors.len() as u8);
+            colors.push(*rgb);
+        }
+
+        GifPalette { colors, exact: Some(exact) }
+    }
+
+    /// The index of the entry closest to a color.
+    fn index(&self, rgb: u32) -> u8 {
+        match &self.exact {
+            Some(exact) => exact[&rgb],
+            None => fallback_index(rgb),
+        }
+    }
+
+    fn transparent(&self) -> u8 {
+        self.colors.len() as u8
+    }
+
+    /// The table holds `2^(bits + 1)` entries, enough for every color and the transparent one.
+    fn size_bits(&self) -> u8 {
+        let entries = (self.colors.len() + 1).max(2).next_power_of_two();
+
+        entries.trailing_zeros() as u8 - 1
+    }
+
+    fn min_code_size(&self) -> u8 {
+        (self.size_bits() + 1).max(2)
+    }
+
+    fn table(&self) -> Vec<u8> {
+        let mut table: Vec<u8> = self.colors
+            .iter()
+            .flat_map(|rgb| [(rgb >> 16) as u8, (rgb >> 8) as u8, *rgb as u8])
+            .collect();
+
+        table.resize(3 << (self.size_bits() + 1), 0);
+        table
+    }
+}
+
+/// A ramp of 255 grays, for grayscale recordings with more levels than fit in the table.
+#[cfg(all(feature = "grayscale", not(any(feature = "full-color", feature = "palette"))))]
+fn fallback_palette() -> Vec<u32> {
+    (0..255u32).map(|level| level * 255 / 254 * 0x010101).collect()
+}
+
+#[cfg(all(feature = "grayscale", not(any(feature = "full-color", feature = "palette"))))]
+fn fallback_index(rgb: u32) -> u8 {
+    ((dither::luminance(rgb) as u32 * 254 + 127) / 255) as u8
+}
+
+/// A 6x7x6 color cube, for recordings with more colors than fit in the table.
+/// Green gets the extra level since the eye is most sensitive to it.
+#[cfg(not(all(feature = "grayscale", not(any(feature = "full-color", feature = "palette")))))]
+fn fallback_palette() -> Vec<u32> {
+    let mut colors = Vec::new();
+
+    for r in 0..6u32 {
+        for g in 0..7u32 {
+            for b in 0..6u32 {
+                colors.push(((r * 255 / 5) << 16) | ((g * 255 / 6) << 8) | (b * 255 / 5));
+            }
+        }
+    }
+
+    colors
+}
+
+#[cfg(not(all(feature = "grayscale", not(any(feature = "full-color", feature = "palette")))))]
+fn fallback_index(rgb: u32) -> u8 {
+    let level = |value: u32, levels: u32| ((value & 0xff) * (levels - 1) + 127) / 255;
+
+    (level(rgb >> 16, 6) * 42 + level(rgb >> 8, 7) * 6 + level(rgb, 6)) as u8
+}
+
+/// Writes image data compressed with the variable-width LZW that GIF uses,
+/// split into sub-blocks of up to 255 bytes.
+fn write_lzw<W: Write>(out: &mut W, min_code_size: u8, pixels: &[u8]) -> io::Result<()> {
+    let clear = 1u16 << min_code_size;
+    let end = clear + 1;
+
+    let mut writer = BitWriter::default();
+    let mut table: HashMap<(u16, u8), u16> = HashMap::new();
+    let mut next_code = end + 1;
+    let mut code_size = min_code_size + 1;
+
+    writer.write(clear, code_size);
+
+    let mut pixels = pixels.iter();
+    let mut current = match pixels.next() {
+        Some(first) => *first as u16,
+        None => {
+            writer.write(end, code_size);
+            return finish_lzw(out, min_code_size, writer);
+        }
+    };
+
+    for pixel in pixels {
+        if let Some(code) = table.get(&(current, *pixel)) {
+            current = *code;
+            continue;
+        }
+
+        writer.write(current, code_size);
+
+        if next_code > MAX_LZW_CODE {
+            // The table is full, so start over
+            writer.write(clear, code_size);
+            table.clear();
+            next_code = end + 1;
+            code_size = min_code_size + 1;
+        } else {
+            table.insert((current, *pixel), next_code);
+            next_code += 1;
+
+            // The decoder is one code behind, so it only widens once this code is used
+            if next_code > 1 << code_size && code_size < 12 {
+                code_size += 1;
+            }
+        }
+
+        current = *pixel as u16;
+    }
+
+    writer.write(current, code_size);
+    writer.write(end, code_size);
+
+    finish_lzw(out, min_code_size, writer)
+}
+
+fn finish_lzw<W: Write>(out: &mut W, min_code_size: u8, writer: BitWriter) -> io::Result<()> {
+    out.write_all(&[min_code_size])?;
+
+    for block in writer.finish().chunks(255) {
+        out.write_all(&[block.len() as u8])?;
+        out.write_all(block)?;
+    }
+
+    out.write_all(&[0])
+}
+
+/// Packs codes least significant bit first.
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    buffer: u32,
+    bits: u8,
+}
+
+impl BitWriter {
+    fn write(&mut self, code: u16, size: u8) {
+        self.buffer |= (code as u32) << self.bits;
+        self.bits += size;
+
+        while self.bits >= 8 {
+            self.bytes.push(self.buffer as u8);
+            self.buffer >>= 8;
+            self.bits -= 8;
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.bits > 0 {
+            self.bytes.push(self.buffer as u8);
+        }
+
+        self.bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::color::Color;
+
+    use super::*;
+
+    /// An image of a decoded GIF, with its pixels as indices into the palette.
+    #[derive(Debug)]
+    struct Image {
+        delay: u16,
+        transparent: u8,
+        bounds: (usize, usize, usize, usize),
+        pixels: Vec<u8>,
+    }
+
+    /// Reads the sub-blocks starting at `at` back into one run of bytes, returning where they end.
+    fn read_sub_blocks(bytes: &[u8], mut at: usize) -> (Vec<u8>, usize) {
+        let mut data = Vec::new();
+
+        while bytes[at] != 0 {
+            let len = bytes[at] as usize;
+            data.extend(&bytes[at + 1..at + 1 + len]);
+            at += len + 1;
+        }
+
+        (data, at + 1)
+    }
+
+    /// Decodes GIF-flavored LZW, returning the pixels and the widest code that was read.
+    fn decode_lzw(min_code_size: u8, data: &[u8]) -> (Vec<u8>, u8) {
+        let clear = 1usize << min_code_size;
+        let end = clear + 1;
+        let reset = || -> Vec<Vec<u8>> { (0..clear + 2).map(|code| vec![code as u8]).collect() };
+
+        let mut table = reset();
+        let mut code_size = min_code_size + 1;
+        let mut widest = code_size;
+        let mut previous: Option<Vec<u8>> = None;
+        let mut pixels = Vec::new();
+        let mut bit = 0;
+
+        loop {
+            let code = (0..code_size as usize)
+                .map(|offset| (data[(bit + offset) / 8] >> ((bit + offset) % 8) & 1) as usize)
+                .enumerate()
+                .fold(0, |code, (offset, value)| code | value << offset);
+            bit += code_size as usize;
+
+            if code == clear {
+                table = reset();
+                code_size = min_code_size + 1;
+                previous = None;
+                continue;
+            }
+
+            if code == end {
+                return (pixels, widest);
+            }
+
+            let entry = match (table.get(code), &previous) {
+                (Some(entry), _) => entry.clone(),
+                (None, Some(previous)) if code == table.len() => [previous.as_slice(), &previous[..1]].concat(),
+                _ => panic!("code {code} should be in the table"),
+            };
+
+            if let Some(previous) = previous {
+                if table.len() < 4096 {
+                    table.push([previous.as_slice(), &entry[..1]].concat());
+                }
+            }
+
+            if table.len() == 1 << code_size && code_size < 12 {
+                code_size += 1;
+                widest = widest.max(code_size);
+            }
+
+            pixels.extend(&entry);
+            previous = Some(entry);
+        }
+    }
+
+    fn round_trip(min_code_size: u8, pixels: &[u8]) -> (Vec<u8>, u8) {
+        let mut out = Vec::new();
+        write_lzw(&mut out, min_code_size, pixels).expect("writing to a vec should succeed");
+
+        assert_eq!(out[0], min_code_size);
+        let (data, end) = read_sub_blocks(&out, 1);
+        assert_eq!(end, out.len());
+
+        decode_lzw(min_code_size, &data)
+    }
+
+    /// Decodes every image of a GIF, along with its palette.
+    fn decode_gif(bytes: &[u8]) -> (Vec<u32>, Vec<Image>) {
+        assert_eq!(&bytes[..6], b"GIF89a");
+
+        let palette_len = 2 << (bytes[10] & 0x07);
+        let palette = bytes[13..13 + palette_len * 3]
+            .chunks(3)
+            .map(|rgb| (rgb[0] as u32) << 16 | (rgb[1] as u32) << 8 | rgb[2] as u32)
+            .collect();
+
+        let mut at = 13 + palette_len * 3;
+        let mut control = (0, 0);
+        let mut images = Vec::new();
+
+        loop {
+            match bytes[at] {
+                0x21 if bytes[at + 1] == 0xf9 => {
+                    control = (u16::from_le_bytes([bytes[at + 4], bytes[at + 5]]), bytes[at + 6]);
+                    at = read_sub_blocks(bytes, at + 2).1;
+                }
+                0x21 => at = read_sub_blocks(bytes, at + 2).1,
+                0x2c => {
+                    let field = |index: usize| u16::from_le_bytes([bytes[at + index], bytes[at + index + 1]]) as usize;
+                    let bounds = (field(1), field(3), field(5), field(7));
+
+                    let (data, end) = read_sub_blocks(bytes, at + 11);
+                    let (pixels, _) = decode_lzw(bytes[at + 10], &data);
+
+                    images.push(Image { delay: control.0, transparent: control.1, bounds, pixels });
+                    at = end;
+                }
+                0x3b => return (palette, images),
+                byte => panic!("block {byte:#x} should be known"),
+            }
+        }
+    }
+
+    fn frame(time: i64, width: usize, rgb: Vec<u32>) -> RecordedFrame {
+        let size = UVec2::new(width, rgb.len() / width);
+
+        RecordedFrame {
+            time: TimeDelta::milliseconds(time),
+            screenshot: Screenshot::new(size, vec![Color::BLACK; rgb.len()], rgb),
+        }
+    }
+
+    fn gif(frames: Vec<RecordedFrame>, duration: i64) -> (Vec<u32>, Vec<Image>) {
+        let recording = Recording { frames, duration: TimeDelta::milliseconds(duration) };
+
+        let mut out = Vec::new();
+        recording.write_gif(&mut out).expect("writing to a vec should succeed");
+        decode_gif(&out)
+    }
+
+    #[test]
+    fn lzw_round_trips() {
+        let pixels: Vec<u8> = (0..200).map(|index| (index * 7 / 5 % 4) as u8).collect();
+
+        assert_eq!(round_trip(2, &pixels).0, pixels);
+        assert_eq!(round_trip(2, &[3]).0, [3]);
+        assert_eq!(round_trip(2, &[]).0, []);
+    }
+
+    #[test]
+    fn lzw_widens_codes_and_resets_a_full_table() {
+        // Noise barely compresses, so this fills the 4096 codes of the table several times over,
+        // which only decodes if the encoder clears it in step with the decoder
+        let mut state = 1u32;
+        let pixels: Vec<u8> = (0..40_000)
+            .map(|_| {
+                state = state.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+                (state >> 16) as u8
+            })
+            .collect();
+
+        let (decoded, widest) = round_trip(8, &pixels);
+
+        assert_eq!(decoded, pixels);
+        assert_eq!(widest, 12);
+    }
+
+    #[test]
+    fn palette_holds_up_to_255_exact_colors() {
+        let colors: Vec<u32> = (0..255).map(|index| index * 0x010203).collect();
+        let palette = GifPalette::new(&[frame(0, 255, colors.clone())]);
+
+        assert_eq!(palette.colors, colors);
+        assert_eq!(palette.transparent(), 255);
+        assert_eq!(palette.index(colors[100]), 100);
+    }
+
+    #[test]
+    fn palette_falls_back_above_255_colors() {
+        let colors: Vec<u32> = (0..256).map(|index| index * 0x010203).collect();
+        let palette = GifPalette::new(&[frame(0, 256, colors)]);
+
+        assert!(palette.exact.is_none());
+        assert_eq!(palette.colors, fallback_palette());
+        assert_eq!(palette.colors[palette.index(0x000000) as usize], 0x000000);
+        assert_eq!(palette.colors[palette.index(0xFFFFFF) as usize], 0xFFFFFF);
+        assert!((palette.transparent() as usize) < 256);
+    }
+
+    #[test]
+    fn frames_only_store_the_rectangle_that_changed() {
+        const A: u32 = 0x102030;
+        const B: u32 = 0xA0B0C0;
+
+        #[rustfmt::skip]
+        let (palette, images) = gif(vec![
+            frame(0, 4, vec![
+                A, A, A, A,
+                A, A, A, A,
+                A, A, A, A,
+            ]),
+            frame(100, 4, vec![
+                A, A, A, A,
+                A, B, A, A,
+                A, A, B, A,
+            ]),
+        ], 200);
+
+        assert_eq!(images.len(), 2);
+        assert_eq!(images[0].bounds, (0, 0, 4, 3));
+        assert_eq!(images[0].pixels, [0; 12]);
+
+        // Pixels that didn't change are left transparent
+        let clear = images[1].transparent;
+        assert_eq!(palette[..3], [A, B, 0]);
+        assert_eq!(clear, 2);
+        assert_eq!(images[1].bounds, (1, 1, 2, 2));
+        assert_eq!(images[1].pixels, [1, clear, clear, 1]);
+    }
+
+    #[test]
+    fn frames_shorter_than_a_hundredth_of_a_second_are_dropped() {
+        let (_, images) = gif(vec![
+            frame(0, 1, vec![0x000000]),
+            // Shown from the same hundredth of a second, so it replaces the first frame
+            frame(3, 1, vec![0x111111]),
+            frame(20, 1, vec![0x222222]),
+            // The same as the frame before it, so it only keeps that one up for longer
+            frame(30, 1, vec![0x222222]),
+        ], 50);
+
+        let delays: Vec<u16> = images.iter().map(|image| image.delay).collect();
+        assert_eq!(delays, [2, 3]);
+        assert_eq!(images[0].pixels, [1]);
+    }
+}
@@ -0,0 +1,267 @@
+use std::{fs::File, io::{self, BufWriter, Write}, path::Path};
+
+use crate::{color::Color, dither, util::UVec2};
+
+/// Whether a Netpbm image is written as text or as raw bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Encoding {
+    /// The plain format (`P1`, `P2`), with every sample written as a decimal number.
+    Ascii,
+    /// The raw format (`P4`, `P5`, `P6`).
+    #[default]
+    Binary,
+}
+
+/// A copy of a composed frame.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Screenshot {
+    size: UVec2,
+    pixels: Vec<Color>,
+    /// The `0x00RRGGBB` value every pixel was presented as.
+    rgb: Vec<u32>,
+}
+
+impl Screenshot {
+    pub(crate) fn new(size: UVec2, pixels: Vec<Color>, rgb: Vec<u32>) -> Self {
+        Self { size, pixels, rgb }
+    }
+
+    pub fn get_size(&self) -> UVec2 {
+        self.size
+    }
+
+    /// The composed pixels in row-major order.
+    pub fn pixels(&self) -> &[Color] {
+        &self.pixels
+    }
+
+    pub fn into_pixels(self) -> Vec<Color> {
+        self.pixels
+    }
+
+    /// The `0x00RRGGBB` values the pixels are presented as, in row-major order.
+    pub fn rgb(&self) -> &[u32] {
+        &self.rgb
+    }
+
+    fn levels(&self) -> impl Iterator<Item = u8> + '_ {
+        self.rgb.iter().map(|rgb| dither::luminance(*rgb))
+    }
+
+    /// Whether each pixel is black, which is what a bit set to 1 means in a PBM.
+    fn black(&self) -> impl Iterator<Item = bool> + '_ {
+        self.levels().map(|level| level < 128)
+    }
+
+    /// Writes a black and white PBM (`P1` or `P4`).
+    /// Pixels darker than half brightness become black.
+    pub fn write_pbm<W: Write>(&self, out: &mut W, encoding: Encoding) -> io::Result<()> {
+        match encoding {
+            Encoding::Ascii => {
+                writeln!(out, "P1\n{} {}", self.size.x, self.size.y)?;
+                self.write_ascii_rows(out, self.black().map(u8::from))
+            }
+            Encoding::Binary => {
+                writeln!(out, "P4\n{} {}", self.size.x, self.size.y)?;
+
+                let black: Vec<bool> = self.black().collect();
+                let mut row = vec![0u8; self.size.x.div_ceil(8)];
+
+                for pixels in black.chunks(self.size.x.max(1)) {
+                    row.fill(0);
+
+                    // Rows are padded to a whole byte, with the leftmost pixel in the highest bit
+                    for (x, _) in pixels.iter().enumerate().filter(|(_, black)| **black) {
+                        row[x / 8] |= 0x80 >> (x % 8);
+                    }
+
+                    out.write_all(&row)?;
+                }
+
+                Ok(())
+            }
+        }
+    }
+
+    /// Writes an 8-bit grayscale PGM (`P2` or `P5`), using the brightness of every pixel.
+    pub fn write_pgm<W: Write>(&self, out: &mut W, encoding: Encoding) -> io::Result<()> {
+        match encoding {
+            Encoding::Ascii => {
+                writeln!(out, "P2\n{} {}\n255", self.size.x, self.size.y)?;
+                self.write_ascii_rows(out, self.levels())
+            }
+            Encoding::Binary => {
+                writeln!(out, "P5\n{} {}\n255", self.size.x, self.size.y)?;
+
+                let levels: Vec<u8> = self.levels().collect();
+                out.write_all(&levels)
+            }
+        }
+    }
+
+    /// Writes an 8-bit color PPM (`P6`).
+    pub fn write_ppm<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        writeln!(out, "P6\n{} {}\n255", self.size.x, self.size.y)?;
+
+        let bytes: Vec<u8> = self.rgb
+            .iter()
+            .flat_map(|rgb| [(rgb >> 16) as u8, (rgb >> 8) as u8, *rgb as u8])
+            .collect();
+
+        out.write_all(&bytes)
+    }
+
+    /// Writes an uncompressed 24-bit BMP.
+    pub fn write_bmp<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        const FILE_HEADER_SIZE: u32 = 14;
+        const INFO_HEADER_SIZE: u32 = 40;
+
+        // Every row is padded to a multiple of four bytes
+        let stride = (self.size.x * 3).next_multiple_of(4);
+        let image_size = (stride * self.size.y) as u32;
+        let offset = FILE_HEADER_SIZE + INFO_HEADER_SIZE;
+
+        out.write_all(b"BM")?;
+        out.write_all(&(offset + image_size).to_le_bytes())?;
+        out.write_all(&[0; 4])?;
+        out.write_all(&offset.to_le_bytes())?;
+
+        out.write_all(&INFO_HEADER_SIZE.to_le_bytes())?;
+        out.write_all(&(self.size.x as i32).to_le_bytes())?;
+        out.write_all(&(self.size.y as i32).to_le_bytes())?;
+        out.write_all(&1u16.to_le_bytes())?; // planes
+        out.write_all(&24u16.to_le_bytes())?; // bits per pixel
+        out.write_all(&0u32.to_le_bytes())?; // no compression
+        out.write_all(&image_size.to_le_bytes())?;
+        out.write_all(&2835i32.to_le_bytes())?; // 72 DPI horizontally
+        out.write_all(&2835i32.to_le_bytes())?; // and vertically
+        out.write_all(&0u32.to_le_bytes())?; // colors in the palette
+        out.write_all(&0u32.to_le_bytes())?; // important colors
+
+        let mut row = vec![0u8; stride];
+
+        // Rows are stored bottom to top, with every pixel in BGR order
+        for pixels in self.rgb.chunks(self.size.x.max(1)).rev() {
+            for (x, rgb) in pixels.iter().enumerate() {
+                row[x * 3] = *rgb as u8;
+                row[x * 3 + 1] = (rgb >> 8) as u8;
+                row[x * 3 + 2] = (rgb >> 16) as u8;
+            }
+
+            out.write_all(&row)?;
+        }
+
+        Ok(())
+    }
+
+    /// Saves the screenshot in the format picked by the extension of the path:
+    /// `pbm`, `pgm` or `ppm` in their binary encodings, or `bmp`.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(str::to_ascii_lowercase);
+
+        let format = match extension.as_deref() {
+            Some(format @ ("pbm" | "pgm" | "ppm" | "bmp")) => format,
+            _ => return Err(io::Error::new(io::ErrorKind::InvalidInput, "screenshot should be saved as pbm, pgm, ppm or bmp")),
+        };
+
+        let mut out = BufWriter::new(File::create(path)?);
+
+        match format {
+            "pbm" => self.write_pbm(&mut out, Encoding::Binary)?,
+            "pgm" => self.write_pgm(&mut out, Encoding::Binary)?,
+            "ppm" => self.write_ppm(&mut out)?,
+            _ => self.write_bmp(&mut out)?,
+        }
+
+        out.flush()
+    }
+
+    /// Writes every sample as a decimal number, with one row of pixels per line.
+    fn write_ascii_rows<W: Write>(&self, out: &mut W, samples: impl Iterator<Item = u8>) -> io::Result<()> {
+        let samples: Vec<u8> = samples.collect();
+
+        for row in samples.chunks(self.size.x.max(1)) {
+            let line: Vec<String> = row.iter().map(u8::to_string).collect();
+            writeln!(out, "{}", line.join(" "))?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BLACK: u32 = 0x000000;
+    const WHITE: u32 = 0xFFFFFF;
+
+    fn screenshot(width: usize, height: usize, rgb: Vec<u32>) -> Screenshot {
+        Screenshot::new(UVec2::new(width, height), vec![Color::BLACK; rgb.len()], rgb)
+    }
+
+    /// Nine pixels wide, so that PBM rows need a second, padded byte.
+    fn nine_wide() -> Screenshot {
+        let mut rgb = vec![WHITE; 18];
+        rgb[0] = BLACK;
+        rgb[8] = BLACK;
+        rgb[10] = BLACK;
+
+        screenshot(9, 2, rgb)
+    }
+
+    fn encode(write: impl FnOnce(&mut Vec<u8>) -> io::Result<()>) -> Vec<u8> {
+        let mut out = Vec::new();
+        write(&mut out).expect("writing to a vec should succeed");
+        out
+    }
+
+    #[test]
+    fn binary_pbm_pads_rows_to_whole_bytes() {
+        let out = encode(|out| nine_wide().write_pbm(out, Encoding::Binary));
+
+        assert_eq!(out, [b"P4\n9 2\n".as_slice(), &[0x80, 0x80, 0x40, 0x00]].concat());
+    }
+
+    #[test]
+    fn ascii_pbm_writes_a_line_per_row() {
+        let out = encode(|out| nine_wide().write_pbm(out, Encoding::Ascii));
+
+        assert_eq!(out, b"P1\n9 2\n1 0 0 0 0 0 0 0 1\n0 1 0 0 0 0 0 0 0\n");
+    }
+
+    #[test]
+    fn pgm_writes_the_brightness_of_every_pixel() {
+        let screenshot = screenshot(2, 1, vec![0xFF0000, 0x00FF00]);
+
+        assert_eq!(encode(|out| screenshot.write_pgm(out, Encoding::Binary)), [b"P5\n2 1\n255\n".as_slice(), &[76, 149]].concat());
+        assert_eq!(encode(|out| screenshot.write_pgm(out, Encoding::Ascii)), b"P2\n2 1\n255\n76 149\n");
+    }
+
+    #[test]
+    fn ppm_writes_rgb_triples() {
+        let out = encode(|out| screenshot(2, 1, vec![0xFF0000, 0x00FF80]).write_ppm(out));
+
+        assert_eq!(out, [b"P6\n2 1\n255\n".as_slice(), &[0xFF, 0x00, 0x00, 0x00, 0xFF, 0x80]].concat());
+    }
+
+    #[test]
+    fn bmp_writes_padded_bgr_rows_bottom_to_top() {
+        let out = encode(|out| screenshot(1, 2, vec![0x112233, 0x445566]).write_bmp(out));
+
+        assert_eq!(out.len(), 62);
+        assert_eq!(&out[0..2], b"BM");
+        assert_eq!(&out[2..6], 62u32.to_le_bytes());
+        assert_eq!(&out[10..14], 54u32.to_le_bytes());
+        assert_eq!(&out[14..18], 40u32.to_le_bytes());
+        assert_eq!(&out[18..22], 1i32.to_le_bytes());
+        assert_eq!(&out[22..26], 2i32.to_le_bytes());
+        assert_eq!(&out[28..30], 24u16.to_le_bytes());
+        assert_eq!(&out[34..38], 8u32.to_le_bytes());
+        assert_eq!(&out[54..], [0x66, 0x55, 0x44, 0x00, 0x33, 0x22, 0x11, 0x00]);
+    }
+}
@@ -0,0 +1,191 @@
+use std::{env, fs, path::PathBuf};
+
+use crate::{app::Application, color::Color, screenshot::Screenshot};
+
+/// Setting this to anything but `0` writes snapshots instead of comparing against them.
+pub const UPDATE_SNAPSHOTS_VAR: &str = "PIXEL_UI_UPDATE_SNAPSHOTS";
+
+/// Writes a pixel as `#` for white or `.` for black.
+#[cfg(all(feature = "single-color", not(any(feature = "grayscale", feature = "full-color", feature = "palette"))))]
+fn pixel_to_ascii(color: Color) -> String {
+    String::from(if color == Color::WHITE { "#" } else { "." })
+}
+
+/// Writes a pixel as its two digit hex level.
+#[cfg(all(feature = "grayscale", not(any(feature = "full-color", feature = "palette"))))]
+fn pixel_to_ascii(color: Color) -> String {
+    format!("{:02x}", color.luminance())
+}
+
+/// Writes a pixel as its `rrggbb` hex value.
+#[cfg(feature = "full-color")]
+fn pixel_to_ascii(color: Color) -> String {
+    format!("{:02x}{:02x}{:02x}", color.r(), color.g(), color.b())
+}
+
+/// Writes a pixel as its two digit hex palette index.
+#[cfg(all(feature = "palette", not(feature = "full-color")))]
+fn pixel_to_ascii(color: Color) -> String {
+    format!("{:02x}", color.index())
+}
+
+/// Writes a screenshot as text, with one line per row of pixels.
+///
+/// Single-color pixels are written as `#` or `.`,
+/// every other mode writes each pixel as hex separated by spaces.
+pub fn to_ascii(screenshot: &Screenshot) -> String {
+    #[cfg(all(feature = "single-color", not(any(feature = "grayscale", feature = "full-color", feature = "palette"))))]
+    let separator = "";
+    #[cfg(any(feature = "grayscale", feature = "full-color", feature = "palette"))]
+    let separator = " ";
+
+    let width = screenshot.get_size().x.max(1);
+    let mut out = String::new();
+
+    for row in screenshot.pixels().chunks(width) {
+        let pixels: Vec<String> = row.iter().map(|color| pixel_to_ascii(*color)).collect();
+
+        out.push_str(&pixels.join(separator));
+        out.push('\n');
+    }
+
+    out
+}
+
+/// Describes every row that differs between two snapshots,
+/// or returns `None` if they are the same.
+pub fn diff(expected: &str, actual: &str) -> Option<String> {
+    if expected == actual {
+        return None;
+    }
+
+    let expected_rows: Vec<&str> = expected.lines().collect();
+    let actual_rows: Vec<&str> = actual.lines().collect();
+    let rows = expected_rows.len().max(actual_rows.len());
+    let mut out = String::new();
+    let mut changed = 0;
+
+    for row in 0..rows {
+        let before = expected_rows.get(row).copied();
+        let after = actual_rows.get(row).copied();
+
+        if before == after {
+            continue;
+        }
+
+        changed += 1;
+        out.push_str(&format!("row {row}:\n"));
+        out.push_str(&format!("  - {}\n", before.unwrap_or("<missing>")));
+        out.push_str(&format!("  + {}\n", after.unwrap_or("<missing>")));
+
+        // Point out the first column that changed
+        if let (Some(before), Some(after)) = (before, after) {
+            let column = before
+                .chars()
+                .zip(after.chars())
+                .position(|(before, after)| before != after)
+                .unwrap_or(before.len().min(after.len()));
+
+            out.push_str(&format!("    {}^\n", " ".repeat(column)));
+        }
+    }
+
+    if expected_rows.len() != actual_rows.len() {
+        out.insert_str(0, &format!("expected {} rows, found {}\n", expected_rows.len(), actual_rows.len()));
+    } else if changed == 0 {
+        // Only line endings or a trailing newline differ
+        out.push_str("rows match, but the line endings differ\n");
+    }
+
+    Some(out)
+}
+
+/// Where the snapshot with the given name is stored,
+/// which is `tests/snapshots/<name>.txt` under the crate being tested.
+pub fn snapshot_path(name: &str) -> PathBuf {
+    let root = env::var_os("CARGO_MANIFEST_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_default();
+
+    root.join("tests").join("snapshots").join(format!("{name}.txt"))
+}
+
+fn updating_snapshots() -> bool {
+    env::var(UPDATE_SNAPSHOTS_VAR).is_ok_and(|value| value != "0")
+}
+
+/// Renders an application and compares the frame against a stored snapshot.
+///
+/// Set [`UPDATE_SNAPSHOTS_VAR`] to write the snapshot instead,
+/// for new snapshots or ones that are out of date.
+///
+/// # Panics
+///
+/// Panics with a diff if the frame doesn't match the snapshot,
+/// or if the snapshot doesn't exist and snapshots aren't being updated.
+pub fn assert_snapshot(app: &mut Application, name: &str) {
+    assert_snapshot_ascii(name, &to_ascii(&app.screenshot()));
+}
+
+/// Compares a frame already written with [`to_ascii`] against a stored snapshot.
+///
+/// # Panics
+///
+/// See [`assert_snapshot`].
+pub fn assert_snapshot_ascii(name: &str, actual: &str) {
+    let path = snapshot_path(name);
+
+    if updating_snapshots() {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).expect("should be able to create the snapshot directory");
+        }
+
+        fs::write(&path, actual).expect("should be able to write the snapshot");
+        return;
+    }
+
+    let Ok(expected) = fs::read_to_string(&path) else {
+        panic!(
+            "snapshot `{name}` doesn't exist at {}, run with {UPDATE_SNAPSHOTS_VAR}=1 to create it\n\n{actual}",
+            path.display(),
+        );
+    };
+
+    if let Some(diff) = diff(&expected, actual) {
+        panic!(
+            "snapshot `{name}` doesn't match, run with {UPDATE_SNAPSHOTS_VAR}=1 to update it\n\n{diff}",
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(all(feature = "single-color", not(any(feature = "grayscale", feature = "full-color", feature = "palette"))))]
+    #[test]
+    fn rect_and_button_match_their_snapshot() {
+        use crate::{element::{button::Button, Element}, headless::Headless};
+
+        let mut app = Headless::new(14, 10).with_background(Color::BLACK);
+        app.insert_element(Element::rect(12, 2, Color::WHITE).with_offset(1, 1));
+        app.insert_element(Element::new(Button::new(8, 6, Color::BLACK).rounded()).with_offset(3, 4));
+
+        assert_snapshot(&mut app, "rect_and_button");
+    }
+
+    #[test]
+    fn diff_points_at_the_first_changed_column() {
+        let diff = diff("..#\n...\n", "..#\n.#.\n").expect("snapshots should differ");
+
+        assert_eq!(diff, "row 1:\n  - ...\n  + .#.\n     ^\n");
+    }
+
+    #[test]
+    fn diff_reports_missing_rows() {
+        let diff = diff("#\n#\n", "#\n").expect("snapshots should differ");
+
+        assert!(diff.starts_with("expected 2 rows, found 1\n"), "{diff}");
+        assert!(diff.contains("  + <missing>\n"), "{diff}");
+    }
+}
@@ -1,6 +1,6 @@
 use std::ops;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub struct UVec2 {
     pub x: usize,
     pub y: usize,
@@ -25,12 +25,6 @@ impl From<(usize, usize)> for UVec2 {
     }
 }
 
-impl Default for UVec2 {
-    fn default() -> Self {
-        Self { x: 0, y: 0 }
-    }
-}
-
 impl ops::Add for UVec2 {
     type Output = Self;
 
@@ -43,7 +37,7 @@ impl ops::Add for UVec2 {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub struct IVec2 {
     pub x: isize,
     pub y: isize,
@@ -68,12 +62,6 @@ impl From<(isize, isize)> for IVec2 {
     }
 }
 
-impl Default for IVec2 {
-    fn default() -> Self {
-        Self { x: 0, y: 0 }
-    }
-}
-
 impl ops::Add for IVec2 {
     type Output = Self;
 
@@ -146,7 +134,33 @@ impl BoundingBox {
         let greater = self.min.x <= target.x && self.min.y <= target.y;
         let less = self.max.x >= target.x && self.max.y >= target.y;
 
-        return greater && less;
+        greater && less
+    }
+
+    /// Whether a position is inside the box, excluding the far edges.
+    pub fn contains(&self, target: IVec2) -> bool {
+        self.min.x <= target.x && self.min.y <= target.y
+            && target.x < self.max.x && target.y < self.max.y
+    }
+
+    /// The overlap of two boxes, or `None` if they don't overlap.
+    pub fn intersection(&self, other: &BoundingBox) -> Option<BoundingBox> {
+        let min = IVec2::new(self.min.x.max(other.min.x), self.min.y.max(other.min.y));
+        let max = IVec2::new(self.max.x.min(other.max.x), self.max.y.min(other.max.y));
+
+        if min.x >= max.x || min.y >= max.y {
+            return None;
+        }
+
+        Some(BoundingBox { min, max })
+    }
+
+    /// The smallest box that covers both boxes.
+    pub fn union(&self, other: &BoundingBox) -> BoundingBox {
+        let min = IVec2::new(self.min.x.min(other.min.x), self.min.y.min(other.min.y));
+        let max = IVec2::new(self.max.x.max(other.max.x), self.max.y.max(other.max.y));
+
+        BoundingBox { min, max }
     }
 
     pub fn size(&self) -> UVec2 {
@@ -168,16 +182,3 @@ impl BoundingBox {
         (self.max.y - self.min.y).unsigned_abs()
     }
 }
-
-pub fn u8_to_bool_vec(content: &[u8]) -> Vec<bool> {
-    let mut buf = Vec::with_capacity(content.len() * 8);
-    for byte in content {
-        for bit in 0..8 {
-            let value = (byte >> bit) & 0x01;
-            // SAFETY: It is assured that `value` is either 0 or 1
-            buf.push(unsafe { std::mem::transmute(value) });
-        }
-    }
-
-    buf
-}
@@ -0,0 +1,204 @@
+use crate::util::{BoundingBox, IVec2, UVec2};
+
+/// How far the framebuffer is turned clockwise before it is shown in the window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Rotation {
+    #[default]
+    Deg0,
+    Deg90,
+    Deg180,
+    Deg270,
+}
+
+impl Rotation {
+    /// Whether the rotation swaps the width and height.
+    pub fn is_sideways(&self) -> bool {
+        matches!(self, Rotation::Deg90 | Rotation::Deg270)
+    }
+}
+
+/// How the logical framebuffer is placed in the window.
+///
+/// The framebuffer is rotated, mirrored left to right if asked,
+/// then scaled up by the largest integer factor that fits
+/// and centered with the rest of the window left as a letterbox.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Viewport {
+    logical: UVec2,
+    window: UVec2,
+    rotation: Rotation,
+    mirrored: bool,
+    scale: usize,
+    /// Where the top-left corner of the framebuffer ends up in the window.
+    /// Negative if the window is too small to show all of it.
+    offset: IVec2,
+}
+
+impl Viewport {
+    pub(crate) fn new(logical: UVec2, window: UVec2, rotation: Rotation, mirrored: bool) -> Self {
+        let display = match rotation.is_sideways() {
+            true => UVec2::new(logical.y, logical.x),
+            false => logical,
+        };
+
+        let scale = match (window.x.checked_div(display.x), window.y.checked_div(display.y)) {
+            (Some(x), Some(y)) => x.min(y).max(1),
+            _ => 1,
+        };
+
+        let offset = IVec2::new(
+            (window.x as isize - (display.x * scale) as isize) / 2,
+            (window.y as isize - (display.y * scale) as isize) / 2,
+        );
+
+        Self { logical, window, rotation, mirrored, scale, offset }
+    }
+
+    pub(crate) fn get_scale(&self) -> usize {
+        self.scale
+    }
+
+    pub(crate) fn window_bounds(&self) -> BoundingBox {
+        BoundingBox::from_size(IVec2::default(), self.window)
+    }
+
+    /// The width of the framebuffer once it is rotated.
+    fn display_width(&self) -> isize {
+        match self.rotation.is_sideways() {
+            true => self.logical.y as isize,
+            false => self.logical.x as isize,
+        }
+    }
+
+    /// Converts a position in the window into logical pixels.
+    /// Positions in the letterbox end up outside of the framebuffer.
+    pub(crate) fn logical_position(&self, pos: IVec2) -> IVec2 {
+        let scale = self.scale as isize;
+        let width = self.logical.x as isize;
+        let height = self.logical.y as isize;
+
+        let x = (pos.x - self.offset.x).div_euclid(scale);
+        let y = (pos.y - self.offset.y).div_euclid(scale);
+        let x = if self.mirrored { self.display_width() - 1 - x } else { x };
+
+        match self.rotation {
+            Rotation::Deg0 => IVec2::new(x, y),
+            Rotation::Deg90 => IVec2::new(y, height - 1 - x),
+            Rotation::Deg180 => IVec2::new(width - 1 - x, height - 1 - y),
+            Rotation::Deg270 => IVec2::new(width - 1 - y, x),
+        }
+    }
+
+    /// The part of the window that a region of the framebuffer covers.
+    pub(crate) fn window_region(&self, region: BoundingBox) -> Option<BoundingBox> {
+        let scale = self.scale as isize;
+        let width = self.logical.x as isize;
+        let height = self.logical.y as isize;
+        let min = region.get_min();
+        let max = region.get_max();
+
+        let (x1, y1, x2, y2) = match self.rotation {
+            Rotation::Deg0 => (min.x, min.y, max.x, max.y),
+            Rotation::Deg90 => (height - max.y, min.x, height - min.y, max.x),
+            Rotation::Deg180 => (width - max.x, height - max.y, width - min.x, height - min.y),
+            Rotation::Deg270 => (min.y, width - max.x, max.y, width - min.x),
+        };
+
+        let (x1, x2) = match self.mirrored {
+            true => (self.display_width() - x2, self.display_width() - x1),
+            false => (x1, x2),
+        };
+
+        BoundingBox::new(
+            x1 * scale + self.offset.x,
+            y1 * scale + self.offset.y,
+            x2 * scale + self.offset.x,
+            y2 * scale + self.offset.y,
+        ).intersection(&self.window_bounds())
+    }
+
+    /// The index into the framebuffer shown at a position in the window,
+    /// or `None` if the position is in the letterbox.
+    pub(crate) fn source_index(&self, pos: IVec2) -> Option<usize> {
+        let logical = self.logical_position(pos);
+
+        let inside = logical.x >= 0 && logical.y >= 0
+            && (logical.x as usize) < self.logical.x && (logical.y as usize) < self.logical.y;
+
+        inside.then(|| logical.y as usize * self.logical.x + logical.x as usize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROTATIONS: [Rotation; 4] = [Rotation::Deg0, Rotation::Deg90, Rotation::Deg180, Rotation::Deg270];
+
+    /// A 3x2 framebuffer shown in a window exactly its size once rotated.
+    fn viewport(rotation: Rotation, mirrored: bool) -> Viewport {
+        let window = if rotation.is_sideways() { UVec2::new(2, 3) } else { UVec2::new(3, 2) };
+        Viewport::new(UVec2::new(3, 2), window, rotation, mirrored)
+    }
+
+    /// The framebuffer index shown at every pixel of the window, row by row.
+    fn shown(viewport: &Viewport) -> Vec<Option<usize>> {
+        let window = viewport.window;
+
+        (0..window.area())
+            .map(|index| viewport.source_index(IVec2::new((index % window.x) as isize, (index / window.x) as isize)))
+            .collect()
+    }
+
+    #[test]
+    fn rotations_turn_the_framebuffer_clockwise() {
+        let expected: [&[usize]; 4] = [&[0, 1, 2, 3, 4, 5], &[3, 0, 4, 1, 5, 2], &[5, 4, 3, 2, 1, 0], &[2, 5, 1, 4, 0, 3]];
+
+        for (rotation, expected) in ROTATIONS.into_iter().zip(expected) {
+            let expected: Vec<_> = expected.iter().copied().map(Some).collect();
+            assert_eq!(shown(&viewport(rotation, false)), expected, "{rotation:?}");
+        }
+    }
+
+    #[test]
+    fn mirroring_flips_after_rotating() {
+        let expected: [&[usize]; 4] = [&[2, 1, 0, 5, 4, 3], &[0, 3, 1, 4, 2, 5], &[3, 4, 5, 0, 1, 2], &[5, 2, 4, 1, 3, 0]];
+
+        for (rotation, expected) in ROTATIONS.into_iter().zip(expected) {
+            let expected: Vec<_> = expected.iter().copied().map(Some).collect();
+            assert_eq!(shown(&viewport(rotation, true)), expected, "{rotation:?}");
+        }
+    }
+
+    #[test]
+    fn window_region_covers_the_pixels_a_region_is_shown_at() {
+        for rotation in ROTATIONS {
+            for mirrored in [false, true] {
+                let viewport = viewport(rotation, mirrored);
+                let shown = shown(&viewport);
+
+                for index in 0..6 {
+                    let pixel = BoundingBox::from_size(IVec2::new(index % 3, index / 3), UVec2::new(1, 1));
+                    let region = viewport.window_region(pixel).expect("pixel should be in the window");
+                    let at = region.get_min().y as usize * viewport.window.x + region.get_min().x as usize;
+
+                    assert_eq!(region.get_max() - region.get_min(), IVec2::new(1, 1));
+                    assert_eq!(shown[at], Some(index as usize), "{rotation:?}, mirrored: {mirrored}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn scaling_letterboxes_the_rest_of_the_window() {
+        let viewport = Viewport::new(UVec2::new(3, 2), UVec2::new(8, 5), Rotation::Deg0, false);
+
+        assert_eq!(viewport.get_scale(), 2);
+        assert_eq!(viewport.source_index(IVec2::new(0, 0)), None);
+        assert_eq!(viewport.source_index(IVec2::new(1, 0)), Some(0));
+        assert_eq!(viewport.source_index(IVec2::new(2, 1)), Some(0));
+        assert_eq!(viewport.source_index(IVec2::new(3, 3)), Some(4));
+        assert_eq!(viewport.source_index(IVec2::new(7, 0)), None);
+        assert_eq!(viewport.window_region(BoundingBox::new(1, 1, 2, 2)), Some(BoundingBox::new(3, 2, 5, 4)));
+    }
+}
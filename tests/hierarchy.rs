@@ -0,0 +1,112 @@
+use std::{cell::RefCell, rc::Rc};
+
+use pixel_ui::{app::ElementId, prelude::*};
+use winit::event::{ElementState, MouseButton};
+
+/// Draws the framebuffer as `#` for white and `.` for anything else, a line per row.
+fn ascii(app: &mut Headless) -> String {
+    app.render();
+
+    let size = app.get_size();
+    (0..size.y)
+        .map(|y| (0..size.x).map(|x| if app.pixel(x, y) == Some(Color::WHITE) { '#' } else { '.' }).collect::<String>() + "\n")
+        .collect()
+}
+
+/// A black parent at (1, 1) holding a white child that hangs off its bottom-right corner,
+/// which in turn holds a grandchild.
+fn nested() -> (Headless, ElementId, ElementId, ElementId) {
+    let mut app = Headless::new(6, 6).with_background(Color::BLACK);
+    let parent = app.insert_element(Element::rect(3, 3, Color::BLACK).with_offset(1, 1));
+    let child = app.insert_child(parent, Element::rect(4, 4, Color::WHITE).with_offset(1, 1));
+    let grandchild = app.insert_child(child, Element::rect(1, 1, Color::BLACK).with_offset(1, 1));
+
+    (app, parent, child, grandchild)
+}
+
+#[test]
+fn children_are_offset_and_clipped_by_their_parent() {
+    let (mut app, ..) = nested();
+
+    assert_eq!(ascii(&mut app), concat!(
+        "......\n",
+        "......\n",
+        "..##..\n",
+        "..#...\n",
+        "......\n",
+        "......\n",
+    ));
+}
+
+#[test]
+fn hiding_a_parent_hides_its_subtree() {
+    let (mut app, parent, child, _) = nested();
+
+    app.get_mut(parent).set_hidden(true);
+    assert_eq!(ascii(&mut app), "......\n".repeat(6));
+    assert_eq!(app.element_at(IVec2::new(2, 2)), None);
+
+    app.get_mut(parent).set_hidden(false);
+    app.get_mut(child).set_hidden(true);
+    assert_eq!(ascii(&mut app), "......\n".repeat(6));
+    assert_eq!(app.element_at(IVec2::new(2, 2)), Some(parent));
+}
+
+#[test]
+fn remove_element_drops_every_descendant() {
+    let (mut app, parent, child, grandchild) = nested();
+    let sibling = app.insert_element(Element::rect(1, 1, Color::WHITE));
+    ascii(&mut app);
+
+    let removed = app.remove_element(child).expect("child should exist");
+    assert!(removed.get_children().is_empty());
+    assert_eq!(removed.get_parent(), None);
+
+    assert!(app.try_get(child).is_none());
+    assert!(app.try_get(grandchild).is_none());
+    assert!(app.get(parent).get_children().is_empty());
+    assert_eq!(app.root_elements(), [parent, sibling]);
+
+    // Removing it again, or anything under it, finds nothing
+    assert!(app.remove_element(child).is_none());
+    assert!(app.remove_element(grandchild).is_none());
+
+    assert_eq!(ascii(&mut app), format!("#.....\n{}", "......\n".repeat(5)));
+}
+
+#[test]
+fn removing_a_root_removes_the_whole_tree() {
+    let (mut app, parent, child, grandchild) = nested();
+
+    app.remove_element(parent);
+
+    for id in [parent, child, grandchild] {
+        assert!(app.try_get(id).is_none());
+    }
+    assert!(app.root_elements().is_empty());
+}
+
+#[test]
+fn hits_resolve_to_the_deepest_element_and_bubble_up() {
+    let (mut app, parent, child, grandchild) = nested();
+
+    assert_eq!(app.element_at(IVec2::new(3, 3)), Some(grandchild));
+    assert_eq!(app.element_at(IVec2::new(2, 2)), Some(child));
+    assert_eq!(app.element_at(IVec2::new(1, 1)), Some(parent));
+
+    // The child reaches past its parent, but is clipped there
+    assert_eq!(app.element_at(IVec2::new(4, 4)), None);
+
+    let clicks = Rc::new(RefCell::new(Vec::new()));
+    for id in [parent, child, grandchild] {
+        let seen = clicks.clone();
+        app.get_mut(id).on_click(move |_, clicked, ev| {
+            if ev.state == ElementState::Released {
+                seen.borrow_mut().push(clicked);
+            }
+        });
+    }
+
+    app.click_at(IVec2::new(3, 3), MouseButton::Left);
+    assert_eq!(*clicks.borrow(), [grandchild, child, parent]);
+}
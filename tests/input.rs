@@ -0,0 +1,93 @@
+use std::{cell::RefCell, rc::Rc};
+
+use pixel_ui::prelude::*;
+use winit::event::{ElementState, Modifiers, MouseButton};
+
+fn mouse(x: isize, y: isize, state: ElementState) -> Event {
+    Event::Mouse(MouseEvent {
+        pos: IVec2::new(x, y),
+        modifiers: Modifiers::default(),
+        state,
+        button: MouseButton::Left,
+    })
+}
+
+fn is_depressed(app: &Headless, id: pixel_ui::app::ElementId) -> bool {
+    app.get(id).as_button().expect("element should be a button").is_depressed()
+}
+
+#[test]
+fn button_is_depressed_while_the_left_button_is_held_on_it() {
+    let mut app = Headless::new(20, 20);
+    let id = app.insert_element(Element::button(8, 6, Color::WHITE).with_offset(2, 2));
+
+    app.dispatch(mouse(4, 4, ElementState::Pressed));
+    assert!(is_depressed(&app, id));
+
+    app.dispatch(mouse(4, 4, ElementState::Released));
+    assert!(!is_depressed(&app, id));
+}
+
+#[test]
+fn button_ignores_presses_outside_of_it() {
+    let mut app = Headless::new(20, 20);
+    let id = app.insert_element(Element::button(8, 6, Color::WHITE).with_offset(2, 2));
+
+    // The far edges are just outside of the button
+    app.dispatch(mouse(10, 4, ElementState::Pressed));
+    assert!(!is_depressed(&app, id));
+
+    app.dispatch(mouse(4, 8, ElementState::Pressed));
+    assert!(!is_depressed(&app, id));
+}
+
+#[test]
+fn depressed_button_is_drawn_inverted() {
+    let mut app = Headless::new(8, 8).with_background(Color::BLACK);
+    app.insert_element(Element::button(8, 8, Color::WHITE));
+
+    let center = app.render().get(4, 4);
+
+    app.dispatch(mouse(4, 4, ElementState::Pressed));
+    assert_eq!(app.render().get(4, 4), center.map(|color| !color));
+}
+
+#[test]
+fn on_click_sees_the_press_and_release_in_logical_pixels() {
+    let mut app = Headless::new(20, 20);
+    let id = app.insert_element(Element::button(8, 6, Color::WHITE).with_offset(2, 2));
+
+    let clicks = Rc::new(RefCell::new(Vec::new()));
+    let seen = clicks.clone();
+    app.get_mut(id).on_click(move |_, clicked, ev| seen.borrow_mut().push((clicked, ev.pos, ev.state)));
+
+    app.click_at(IVec2::new(3, 7), MouseButton::Left);
+    app.click_at(IVec2::new(15, 15), MouseButton::Left);
+
+    assert_eq!(
+        *clicks.borrow(),
+        [(id, IVec2::new(3, 7), ElementState::Pressed), (id, IVec2::new(3, 7), ElementState::Released)],
+    );
+}
+
+#[test]
+fn on_click_bubbles_from_a_child_to_its_parent() {
+    let mut app = Headless::new(20, 20);
+    let parent = app.insert_element(Element::rect(12, 12, Color::BLACK));
+    let child = app.insert_child(parent, Element::button(4, 4, Color::WHITE).with_offset(2, 2));
+
+    let clicks = Rc::new(RefCell::new(Vec::new()));
+    for id in [parent, child] {
+        let seen = clicks.clone();
+        app.get_mut(id).on_click(move |_, clicked, ev| {
+            if ev.state == ElementState::Released {
+                seen.borrow_mut().push(clicked);
+            }
+        });
+    }
+
+    app.click_at(IVec2::new(3, 3), MouseButton::Left);
+    app.click_at(IVec2::new(10, 10), MouseButton::Left);
+
+    assert_eq!(*clicks.borrow(), [child, parent, parent]);
+}
@@ -0,0 +1,46 @@
+use chrono::TimeDelta;
+use pixel_ui::{backend::memory::MemoryBackend, prelude::*};
+
+fn backend(width: usize, height: usize) -> MemoryBackend {
+    let mut backend = MemoryBackend::new();
+    backend.push_event(BackendEvent::Resized(UVec2::new(width, height)));
+    backend
+}
+
+#[test]
+fn run_returns_once_every_event_is_handled() {
+    let backend = backend(4, 2);
+    let screen = backend.screen();
+
+    let mut builder = Application::builder_with_backend(backend);
+    builder.insert_element(Element::rect(2, 2, Color::WHITE));
+    builder.run().expect("memory backend should run");
+
+    assert_eq!(screen.get_size(), UVec2::new(4, 2));
+    assert_eq!(screen.pixel(1, 1), Some(0xFFFFFF));
+    assert_eq!(screen.pixel(2, 1), Some(0x000000));
+}
+
+#[test]
+fn run_waits_for_pending_timeouts() {
+    let backend = backend(2, 2);
+    let screen = backend.screen();
+
+    let mut builder = Application::builder_with_backend(backend);
+    builder.set_timeout(TimeDelta::milliseconds(20), |app| app.set_background(Color::WHITE));
+    builder.run().expect("memory backend should run");
+
+    assert_eq!(screen.pixel(0, 0), Some(0xFFFFFF));
+}
+
+#[test]
+fn run_does_not_wait_for_a_manual_clock() {
+    let backend = backend(2, 2);
+    let screen = backend.screen();
+
+    let mut builder = Application::builder_with_backend(backend).with_manual_clock();
+    builder.set_timeout(TimeDelta::milliseconds(20), |app| app.set_background(Color::WHITE));
+    builder.run().expect("memory backend should run");
+
+    assert_eq!(screen.pixel(0, 0), Some(0x000000));
+}
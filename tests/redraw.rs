@@ -0,0 +1,35 @@
+use pixel_ui::prelude::*;
+
+#[test]
+fn idle_renders_present_nothing() {
+    let mut app = Headless::new(20, 10);
+    app.insert_element(Element::text("hi", Color::WHITE));
+
+    app.render();
+    assert_eq!(app.backend().get_present_count(), 1);
+
+    app.render();
+    app.render();
+    assert_eq!(app.backend().get_present_count(), 1);
+    assert!(app.damage().is_empty());
+}
+
+#[test]
+fn every_change_presents_one_frame() {
+    let mut app = Headless::new(40, 10);
+    let id = app.insert_element(Element::text("a", Color::WHITE));
+    app.insert_element(Element::text("b", Color::WHITE).with_offset(20, 0));
+
+    app.render();
+    app.get_mut(id).as_text_mut().expect("element should be text").set_text("c");
+    app.render();
+    assert_eq!(app.backend().get_present_count(), 2);
+
+    // Only the text that changed is recomposited
+    let bounds = app.get_absolute_bounding_box(id);
+    assert!(!app.damage().is_empty());
+    assert!(app.damage().iter().all(|region| region.intersection(&bounds) == Some(*region)), "{:?}", app.damage());
+
+    app.render();
+    assert_eq!(app.backend().get_present_count(), 2);
+}
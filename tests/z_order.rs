@@ -0,0 +1,97 @@
+use std::{cell::RefCell, rc::Rc};
+
+use pixel_ui::{app::ElementId, prelude::*};
+use winit::event::{ElementState, MouseButton};
+
+/// A white and a black rect on a black background, overlapping in columns 2 and 3.
+fn overlapping() -> (Headless, ElementId, ElementId) {
+    let mut app = Headless::new(6, 1).with_background(Color::BLACK);
+    let white = app.insert_element(Element::rect(4, 1, Color::WHITE));
+    let black = app.insert_element(Element::rect(4, 1, Color::BLACK).with_offset(2, 0));
+
+    (app, white, black)
+}
+
+/// Which columns are white after rendering.
+fn white_columns(app: &mut Headless) -> Vec<usize> {
+    app.render();
+
+    (0..app.get_size().x).filter(|x| app.pixel(*x, 0) == Some(Color::WHITE)).collect()
+}
+
+/// Which elements saw a click released on them.
+fn clicked(app: &mut Headless, ids: &[ElementId], pos: IVec2) -> Vec<ElementId> {
+    let clicks = Rc::new(RefCell::new(Vec::new()));
+    let handlers: Vec<_> = ids
+        .iter()
+        .map(|id| {
+            let seen = clicks.clone();
+            let handler = app.get_mut(*id).on_click(move |_, clicked, ev| {
+                if ev.state == ElementState::Released {
+                    seen.borrow_mut().push(clicked);
+                }
+            });
+
+            (*id, handler)
+        })
+        .collect();
+
+    app.click_at(pos, MouseButton::Left);
+
+    for (id, handler) in handlers {
+        app.get_mut(id).remove_handler(handler);
+    }
+
+    clicks.take()
+}
+
+#[test]
+fn later_elements_draw_over_earlier_ones_with_the_same_z_index() {
+    let (mut app, white, black) = overlapping();
+
+    assert_eq!(white_columns(&mut app), [0, 1]);
+    assert_eq!(app.element_at(IVec2::new(2, 0)), Some(black));
+    assert_eq!(app.element_at(IVec2::new(1, 0)), Some(white));
+}
+
+#[test]
+fn higher_z_index_draws_on_top() {
+    let (mut app, white, black) = overlapping();
+
+    app.get_mut(white).set_z_index(1);
+    assert_eq!(white_columns(&mut app), [0, 1, 2, 3]);
+    assert_eq!(app.element_at(IVec2::new(3, 0)), Some(white));
+
+    app.get_mut(black).set_z_index(2);
+    assert_eq!(white_columns(&mut app), [0, 1]);
+    assert_eq!(app.element_at(IVec2::new(3, 0)), Some(black));
+}
+
+#[test]
+fn bring_to_front_and_send_to_back_reorder_the_overlap() {
+    let (mut app, white, black) = overlapping();
+
+    app.bring_to_front(white);
+    assert_eq!(white_columns(&mut app), [0, 1, 2, 3]);
+
+    app.send_to_back(white);
+    assert_eq!(white_columns(&mut app), [0, 1]);
+    assert_eq!(app.get(white).get_z_index(), 0);
+    assert!(app.get(black).get_z_index() > 0);
+
+    app.send_to_back(black);
+    assert_eq!(white_columns(&mut app), [0, 1, 2, 3]);
+}
+
+#[test]
+fn click_only_reaches_the_topmost_element() {
+    let (mut app, white, black) = overlapping();
+    let ids = [white, black];
+
+    assert_eq!(clicked(&mut app, &ids, IVec2::new(2, 0)), [black]);
+    assert_eq!(clicked(&mut app, &ids, IVec2::new(1, 0)), [white]);
+
+    app.bring_to_front(white);
+    assert_eq!(clicked(&mut app, &ids, IVec2::new(2, 0)), [white]);
+    assert_eq!(clicked(&mut app, &ids, IVec2::new(4, 0)), [black]);
+}